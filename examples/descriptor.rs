@@ -7,19 +7,19 @@ use std::{fs::File, io::BufWriter};
 use nexg::{
     Buffer, BufferDescriptor, BufferUsage, CommandPoolDescriptor, CommandRecorderDescriptor,
     DataFormat, Extent3d, FrameBuffer, FrameBufferDescriptor, Image, ImageDescriptor, ImageFormat,
-    ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp, Pipeline, PipelineDescriptor,
-    PipelineLayout, PipelineLayoutDescriptor, PipelineVertexInputDescriptor, QueueSubmitDescriptor,
-    RenderPass, RenderPassBeginDescriptor, RenderPassDescriptor, RequestConnecterDescriptor,
-    Resource, ResourceBufferDescriptor, ResourceLayout, ResourceLayoutBinding, ResourcePool,
-    ResourcePoolDescriptor, ResourcePoolSize, ResourceType, ResourceUpdateDescriptor, Shader,
-    ShaderStage, ShaderStageDescriptor, Spirv, StoreOp, SubPass, SubPassDescriptor,
-    VertexInputAttributeDescriptor, VertexInputBindingDescriptor,
+    ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp, MemoryLocation, Pipeline,
+    PipelineDescriptor, PipelineLayout, PipelineLayoutDescriptor, PipelineVertexInputDescriptor,
+    QueueSubmitDescriptor, RenderPass, RenderPassBeginDescriptor, RenderPassDescriptor,
+    RequestConnecterDescriptor, Resource, ResourceBufferDescriptor, ResourceLayout,
+    ResourceLayoutBinding, ResourcePool, ResourcePoolDescriptor, ResourcePoolSize, ResourceType,
+    ResourceUpdateDescriptor, Shader, ShaderStage, ShaderStageDescriptor, Spirv, StoreOp, SubPass,
+    SubPassDescriptor, VertexInputAttributeDescriptor, VertexInputBindingDescriptor,
 };
 use png::text_metadata::ZTXtChunk;
 use simple_logger::SimpleLogger;
 
-const VERTEX_S: &'static [u8] = include_bytes!("shader/shader2.vert.spv");
-const FRAGMENT_S: &'static [u8] = include_bytes!("shader/shader.frag.spv");
+const VERTEX_S: &[u8] = include_bytes!("shader/shader2.vert.spv");
+const FRAGMENT_S: &[u8] = include_bytes!("shader/shader.frag.spv");
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -34,7 +34,9 @@ pub struct Vertex {
     color: Vec4,
 }
 
-struct SceneData {
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SceneData {
     rect_center: Vec4,
 }
 
@@ -84,22 +86,23 @@ fn main() {
     let desc = CommandPoolDescriptor::empty().queue_family_index(index);
     let pool = device.create_command_pool(&desc).unwrap();
     let desc = CommandRecorderDescriptor::empty();
-    let recorders = device.allocate_command_recorder(pool, &desc).unwrap();
-    let desc = ImageDescriptor::new().extent(Extent3d::new(WIDTH, HEIGHT, 1));
+    let recorders = device.allocate_command_recorder(&pool, &desc).unwrap();
+    let desc = ImageDescriptor::new()
+        .extent(Extent3d::new(WIDTH, HEIGHT, 1))
+        .location(MemoryLocation::Readback);
     let image = Image::create(&instance, &device, connecter, &desc).unwrap();
     let desc = ImageViewDescriptor::empty().format(ImageFormat::R8G8B8A8Unorm);
-    let image_view = image.create_image_view(&device, &desc);
+    let image_view = image.create_image_view(&device, &desc).unwrap();
 
-    let vertex = Shader::new(&device, &Spirv::from_raw(VERTEX_S).unwrap());
+    let vertex = Shader::new(&device, &Spirv::from_raw(VERTEX_S).unwrap()).unwrap();
 
-    let fragment = Shader::new(&device, &Spirv::from_raw(FRAGMENT_S).unwrap());
+    let fragment = Shader::new(&device, &Spirv::from_raw(FRAGMENT_S).unwrap()).unwrap();
 
     let desc = BufferDescriptor::empty().size(std::mem::size_of::<Vertex>() * VERTEX.len());
     let vertex_buffer = Buffer::new(&instance, connecter, &device, &desc).unwrap();
     vertex_buffer
         .write(&device, VERTEX.as_ptr() as *const c_void)
         .unwrap();
-    vertex_buffer.lock(&device);
     let desc = BufferDescriptor::empty()
         .size(std::mem::size_of::<u16>() * INDICES.len())
         .usage(BufferUsage::Index);
@@ -107,7 +110,6 @@ fn main() {
     index_buffer
         .write(&device, INDICES.as_ptr() as *const c_void)
         .unwrap();
-    index_buffer.lock(&device);
     let desc = BufferDescriptor::empty()
         .size(std::mem::size_of::<SceneData>())
         .usage(BufferUsage::Uniform);
@@ -115,20 +117,19 @@ fn main() {
     uniform_buffer
         .write(&device, &SCENE_DATA as *const SceneData as *const c_void)
         .unwrap();
-    uniform_buffer.lock(&device);
 
     let resource_layout_bindings = vec![ResourceLayoutBinding::empty()
         .binding(0)
         .resource_type(ResourceType::UniformBuffer)
         .count(1)
         .shader_stage(ShaderStage::Vertex)];
-    let resource_layout = ResourceLayout::new(&device, &resource_layout_bindings);
+    let resource_layout = ResourceLayout::new(&device, &resource_layout_bindings).unwrap();
     let pool_sizes = vec![ResourcePoolSize::empty()];
     let pool_desc = ResourcePoolDescriptor::empty()
         .pool_sizes(&pool_sizes)
         .max_sets(1);
-    let resource_pool = ResourcePool::new(&device, &pool_desc);
-    let resource = Resource::allocate(&device, &resource_pool, &resource_layout);
+    let resource_pool = ResourcePool::new(&device, &pool_desc).unwrap();
+    let resource = Resource::allocate(&device, &resource_pool, &resource_layout).unwrap();
 
     let buffer_desc = ResourceBufferDescriptor::new::<SceneData>(&uniform_buffer);
     let desc = vec![buffer_desc];
@@ -180,7 +181,7 @@ fn main() {
         .input_descriptor(&vertex_input_desc)
         .width(WIDTH)
         .height(HEIGHT);
-    let pipeline = Pipeline::new(&device, pipeline_layout, &render_pass, &desc).unwrap();
+    let pipeline = Pipeline::new(&device, &pipeline_layout, &render_pass, &desc).unwrap();
 
     let desc = FrameBufferDescriptor::empty()
         .render_pass(&render_pass)
@@ -195,16 +196,16 @@ fn main() {
         .clear(0.0, 0.0, 0.0, 1.0)
         .render_pass(&render_pass)
         .frame_buffer(&framebuffer);
-    recorders[0].begin(&device, begin_desc);
+    recorders[0].begin(&device, begin_desc).unwrap();
     recorders[0].bind_pipeline(&device, &pipeline[0]);
     recorders[0].bind_vertex_buffer(&device, &vertex_buffer);
     recorders[0].bind_index_buffer(&device, &index_buffer);
     recorders[0].bind_resource(&device, &resource[0], &pipeline_layout);
     recorders[0].draw_indexed(&device, INDICES.len() as u32, 1, 0, 0, 0);
-    recorders[0].end(&device);
+    recorders[0].end(&device).unwrap();
 
     let desc = QueueSubmitDescriptor::empty();
-    queue.submit(&device, &desc, &recorders);
+    queue.submit(&device, &desc, &recorders).unwrap();
 
     let file = File::create("descriptor.png").unwrap();
     let w = &mut BufWriter::new(file);
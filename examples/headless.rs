@@ -0,0 +1,109 @@
+//! Presents frames to a swapchain without a window, using `VK_EXT_headless_surface`.
+//! Runs on drivers such as lavapipe, so swapchain code can be tested on machines without a display.
+
+use nexg::{
+    CommandPoolDescriptor, CommandRecorderDescriptor, Fence, FenceDescriptor, FrameBuffer,
+    FrameBufferDescriptor, ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp,
+    QueuePresentDescriptor, QueueSubmitDescriptor, RenderPass, RenderPassBeginDescriptor,
+    RenderPassDescriptor, RequestConnecterDescriptor, Semaphore, SemaphoreDescriptor, StoreOp,
+    SubPass, SubPassDescriptor, Surface, Swapchain,
+};
+use simple_logger::SimpleLogger;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 480;
+const FRAMES: usize = 60;
+
+fn main() {
+    SimpleLogger::new().init().unwrap();
+    let mut feature = InstanceFeature::empty();
+    feature.use_headless_surface();
+    let instance = InstanceBuilder::new().feature(feature).build().unwrap();
+    let desc = RequestConnecterDescriptor::new()
+        .graphic_support(true)
+        .compute_support(true)
+        .transfer_support(true);
+    let connecters = instance.request_connecters(&[desc]).unwrap();
+    let connecter = connecters[0];
+    let index = connecter.get_queue_family_index();
+
+    let device = connecter.create_device(&instance, index).unwrap();
+
+    let surface = Surface::headless(&instance, WIDTH, HEIGHT).unwrap();
+    let swapchain = Swapchain::new(&surface, &instance, &device, connecter).unwrap();
+
+    let queue = device.get_queue(index);
+    let desc = CommandPoolDescriptor::empty().queue_family_index(index);
+    let pool = device.create_command_pool(&desc).unwrap();
+    let desc = CommandRecorderDescriptor::empty();
+    let recorders = device.allocate_command_recorder(&pool, &desc).unwrap();
+    let images = swapchain.images().unwrap();
+    let desc = ImageViewDescriptor::empty().format(swapchain.format());
+    let image_views = images
+        .iter()
+        .map(|x| x.create_image_view(&device, &desc).unwrap())
+        .collect::<Vec<_>>();
+
+    let desc = SubPassDescriptor::empty();
+    let subpass = SubPass::new(connecter, &desc);
+    let subpasses = &[subpass];
+    let desc = RenderPassDescriptor::empty()
+        .subpasses(subpasses)
+        .load_op(LoadOp::Clear)
+        .store_op(StoreOp::Store);
+    let render_pass = RenderPass::new(&device, &desc).unwrap();
+
+    let frame_buffers = image_views
+        .iter()
+        .map(|x| {
+            let desc = FrameBufferDescriptor::empty()
+                .render_pass(&render_pass)
+                .image_view(x)
+                .width(WIDTH)
+                .height(HEIGHT);
+            FrameBuffer::new(&device, &desc).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let desc = FenceDescriptor::empty().signaled(true);
+    let image_rendered_fence = Fence::new(&device, &desc).unwrap();
+    let semaphore_desc = SemaphoreDescriptor::empty();
+    let swapchain_image_semaphore = Semaphore::new(&device, &semaphore_desc).unwrap();
+    let image_rendered_semaphore = Semaphore::new(&device, &semaphore_desc).unwrap();
+
+    for frame in 0..FRAMES {
+        let (img, _) = swapchain
+            .acquire_next_image(Some(&swapchain_image_semaphore))
+            .unwrap();
+
+        image_rendered_fence.wait(&device, u64::MAX).unwrap();
+        image_rendered_fence.reset(&device).unwrap();
+
+        let shade = frame as f32 / FRAMES as f32;
+        let begin_desc = RenderPassBeginDescriptor::empty()
+            .width(WIDTH)
+            .height(HEIGHT)
+            .clear(shade, shade, shade, 1.0)
+            .render_pass(&render_pass)
+            .frame_buffer(&frame_buffers[img]);
+        recorders[0].reset(&device).unwrap();
+        recorders[0].begin(&device, begin_desc).unwrap();
+        recorders[0].end(&device).unwrap();
+
+        let w_semaphores = std::slice::from_ref(&swapchain_image_semaphore);
+        let s_semaphores = std::slice::from_ref(&image_rendered_semaphore);
+        let desc = QueueSubmitDescriptor::empty()
+            .wait_semaphores(w_semaphores)
+            .signal_semaphores(s_semaphores)
+            .fence(&image_rendered_fence);
+        queue.submit(&device, &desc, &recorders).unwrap();
+
+        let w_semaphores = std::slice::from_ref(&image_rendered_semaphore);
+        let desc = QueuePresentDescriptor::empty()
+            .wait_semaphores(w_semaphores)
+            .queue(&queue);
+        swapchain.present(&desc, img as u32).unwrap();
+    }
+    image_rendered_fence.wait(&device, u64::MAX).unwrap();
+    println!("Presented {FRAMES} frames to a headless surface");
+}
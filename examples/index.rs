@@ -5,11 +5,11 @@ use std::{env, fs::File, io::BufWriter};
 use nexg::{
     Buffer, BufferDescriptor, BufferUsage, CommandPoolDescriptor, CommandRecorderDescriptor,
     DataFormat, Extent3d, FrameBuffer, FrameBufferDescriptor, Image, ImageDescriptor, ImageFormat,
-    ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp, Pipeline, PipelineDescriptor,
-    PipelineLayout, PipelineLayoutDescriptor, PipelineVertexInputDescriptor, QueueSubmitDescriptor,
-    RenderPass, RenderPassBeginDescriptor, RenderPassDescriptor, RequestConnecterDescriptor,
-    Shader, ShaderStage, ShaderStageDescriptor, Spirv, StoreOp, SubPass, SubPassDescriptor,
-    VertexInputAttributeDescriptor, VertexInputBindingDescriptor,
+    ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp, MemoryLocation, Pipeline,
+    PipelineDescriptor, PipelineLayout, PipelineLayoutDescriptor, PipelineVertexInputDescriptor,
+    QueueSubmitDescriptor, RenderPass, RenderPassBeginDescriptor, RenderPassDescriptor,
+    RequestConnecterDescriptor, Shader, ShaderStage, ShaderStageDescriptor, Spirv, StoreOp,
+    SubPass, SubPassDescriptor, VertexInputAttributeDescriptor, VertexInputBindingDescriptor,
 };
 use png::text_metadata::ZTXtChunk;
 use simple_logger::SimpleLogger;
@@ -66,11 +66,13 @@ fn main() {
     let desc = CommandPoolDescriptor::empty().queue_family_index(index);
     let pool = device.create_command_pool(&desc).unwrap();
     let desc = CommandRecorderDescriptor::empty();
-    let recorders = device.allocate_command_recorder(pool, &desc).unwrap();
-    let desc = ImageDescriptor::new().extent(Extent3d::new(WIDTH, HEIGHT, 1));
+    let recorders = device.allocate_command_recorder(&pool, &desc).unwrap();
+    let desc = ImageDescriptor::new()
+        .extent(Extent3d::new(WIDTH, HEIGHT, 1))
+        .location(MemoryLocation::Readback);
     let image = Image::create(&instance, &device, connecter, &desc).unwrap();
     let desc = ImageViewDescriptor::empty().format(ImageFormat::R8G8B8A8Unorm);
-    let image_view = image.create_image_view(&device, &desc);
+    let image_view = image.create_image_view(&device, &desc).unwrap();
 
     let vertex = Shader::new(
         &device,
@@ -79,7 +81,8 @@ fn main() {
             "/examples/shader/shader.vert.spv"
         ))
         .unwrap(),
-    );
+    )
+    .unwrap();
 
     let fragment = Shader::new(
         &device,
@@ -88,18 +91,21 @@ fn main() {
             "/examples/shader/shader.frag.spv"
         ))
         .unwrap(),
-    );
+    )
+    .unwrap();
 
     let desc = BufferDescriptor::empty().size(std::mem::size_of::<Vertex>() * VERTEX.len());
     let vertex_buffer = Buffer::new(&instance, connecter, &device, &desc).unwrap();
-    vertex_buffer.write(&device, VERTEX.as_ptr() as *const c_void);
-    vertex_buffer.lock(&device);
+    vertex_buffer
+        .write(&device, VERTEX.as_ptr() as *const c_void)
+        .unwrap();
     let desc = BufferDescriptor::empty()
         .size(std::mem::size_of::<u16>() * INDICES.len())
         .usage(BufferUsage::Index);
     let index_buffer = Buffer::new(&instance, connecter, &device, &desc).unwrap();
-    index_buffer.write(&device, INDICES.as_ptr() as *const c_void);
-    index_buffer.lock(&device);
+    index_buffer
+        .write(&device, INDICES.as_ptr() as *const c_void)
+        .unwrap();
 
     let desc = SubPassDescriptor::empty();
     let subpass = SubPass::new(connecter, &desc);
@@ -144,7 +150,7 @@ fn main() {
         .input_descriptor(&vertex_input_desc)
         .width(WIDTH)
         .height(HEIGHT);
-    let pipeline = Pipeline::new(&device, pipeline_layout, &render_pass, &desc).unwrap();
+    let pipeline = Pipeline::new(&device, &pipeline_layout, &render_pass, &desc).unwrap();
 
     let desc = FrameBufferDescriptor::empty()
         .render_pass(&render_pass)
@@ -159,15 +165,15 @@ fn main() {
         .clear(0.88, 0.88, 0.88, 1.0)
         .render_pass(&render_pass)
         .frame_buffer(&framebuffer);
-    recorders[0].begin(&device, begin_desc);
+    recorders[0].begin(&device, begin_desc).unwrap();
     recorders[0].bind_pipeline(&device, &pipeline[0]);
     recorders[0].bind_vertex_buffer(&device, &vertex_buffer);
     recorders[0].bind_index_buffer(&device, &index_buffer);
     recorders[0].draw_indexed(&device, INDICES.len() as u32, 1, 0, 0, 0);
-    recorders[0].end(&device);
+    recorders[0].end(&device).unwrap();
 
     let desc = QueueSubmitDescriptor::empty();
-    queue.submit(&device, &desc, &recorders);
+    queue.submit(&device, &desc, &recorders).unwrap();
 
     let file = File::create("../media/img/index_buffer.png").unwrap();
     let w = &mut BufWriter::new(file);
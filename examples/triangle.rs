@@ -5,11 +5,11 @@ use std::{env, fs::File, io::BufWriter};
 use nexg::{
     Buffer, BufferDescriptor, CommandPoolDescriptor, CommandRecorderDescriptor, DataFormat,
     Extent3d, FrameBuffer, FrameBufferDescriptor, Image, ImageDescriptor, ImageFormat,
-    ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp, Pipeline, PipelineDescriptor,
-    PipelineLayout, PipelineLayoutDescriptor, PipelineVertexInputDescriptor, QueueSubmitDescriptor,
-    RenderPass, RenderPassBeginDescriptor, RenderPassDescriptor, RequestConnecterDescriptor,
-    Shader, ShaderStage, ShaderStageDescriptor, Spirv, StoreOp, SubPass, SubPassDescriptor,
-    VertexInputAttributeDescriptor, VertexInputBindingDescriptor,
+    ImageViewDescriptor, InstanceBuilder, InstanceFeature, LoadOp, MemoryLocation, Pipeline,
+    PipelineDescriptor, PipelineLayout, PipelineLayoutDescriptor, PipelineVertexInputDescriptor,
+    QueueSubmitDescriptor, RenderPass, RenderPassBeginDescriptor, RenderPassDescriptor,
+    RequestConnecterDescriptor, Shader, ShaderStage, ShaderStageDescriptor, Spirv, StoreOp,
+    SubPass, SubPassDescriptor, VertexInputAttributeDescriptor, VertexInputBindingDescriptor,
 };
 use png::text_metadata::ZTXtChunk;
 use simple_logger::SimpleLogger;
@@ -60,11 +60,13 @@ fn main() {
     let desc = CommandPoolDescriptor::empty().queue_family_index(index);
     let pool = device.create_command_pool(&desc).unwrap();
     let desc = CommandRecorderDescriptor::empty();
-    let recorders = device.allocate_command_recorder(pool, &desc).unwrap();
-    let desc = ImageDescriptor::new().extent(Extent3d::new(WIDTH, HEIGHT, 1));
+    let recorders = device.allocate_command_recorder(&pool, &desc).unwrap();
+    let desc = ImageDescriptor::new()
+        .extent(Extent3d::new(WIDTH, HEIGHT, 1))
+        .location(MemoryLocation::Readback);
     let image = Image::create(&instance, &device, connecter, &desc).unwrap();
     let desc = ImageViewDescriptor::empty().format(ImageFormat::R8G8B8A8Unorm);
-    let image_view = image.create_image_view(&device, &desc);
+    let image_view = image.create_image_view(&device, &desc).unwrap();
 
     let vertex = Shader::new(
         &device,
@@ -73,7 +75,8 @@ fn main() {
             "/examples/shader/shader.vert.spv"
         ))
         .unwrap(),
-    );
+    )
+    .unwrap();
 
     let fragment = Shader::new(
         &device,
@@ -82,12 +85,14 @@ fn main() {
             "/examples/shader/shader.frag.spv"
         ))
         .unwrap(),
-    );
+    )
+    .unwrap();
 
     let desc = BufferDescriptor::empty().size(std::mem::size_of::<Vertex>() * VERTEX.len());
     let vertex_buffer = Buffer::new(&instance, connecter, &device, &desc).unwrap();
-    vertex_buffer.write(&device, VERTEX.as_ptr() as *const c_void);
-    vertex_buffer.lock(&device);
+    vertex_buffer
+        .write(&device, VERTEX.as_ptr() as *const c_void)
+        .unwrap();
 
     let desc = SubPassDescriptor::empty();
     let subpass = SubPass::new(connecter, &desc);
@@ -132,7 +137,7 @@ fn main() {
         .input_descriptor(&vertex_input_desc)
         .width(WIDTH)
         .height(HEIGHT);
-    let pipeline = Pipeline::new(&device, pipeline_layout, &render_pass, &desc).unwrap();
+    let pipeline = Pipeline::new(&device, &pipeline_layout, &render_pass, &desc).unwrap();
 
     let desc = FrameBufferDescriptor::empty()
         .render_pass(&render_pass)
@@ -147,14 +152,14 @@ fn main() {
         .clear(1.0, 1.0, 1.0, 1.0)
         .render_pass(&render_pass)
         .frame_buffer(&framebuffer);
-    recorders[0].begin(&device, begin_desc);
+    recorders[0].begin(&device, begin_desc).unwrap();
     recorders[0].bind_pipeline(&device, &pipeline[0]);
     recorders[0].bind_vertex_buffer(&device, &vertex_buffer);
     recorders[0].draw(&device, 3, 1, 0, 0);
-    recorders[0].end(&device);
+    recorders[0].end(&device).unwrap();
 
     let desc = QueueSubmitDescriptor::empty();
-    queue.submit(&device, &desc, &recorders);
+    queue.submit(&device, &desc, &recorders).unwrap();
 
     let file = File::create("triangle.png").unwrap();
     let w = &mut BufWriter::new(file);
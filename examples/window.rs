@@ -13,7 +13,6 @@ use nexg::{
 use simple_logger::SimpleLogger;
 use std::ffi::c_void;
 use std::mem::offset_of;
-use winit::event::StartCause;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::EventLoop,
@@ -38,11 +37,8 @@ const SCENE_DATA: SceneData = SceneData {
     rect_center: Vec4(0.3, -0.2, 0.0, 0.0),
 };
 
-const VERTEX_S: &'static [u8] = include_bytes!("shader/shader2.vert.spv");
-const FRAGMENT_S: &'static [u8] = include_bytes!("shader/shader.frag.spv");
-
-const WIDTH: u32 = 640;
-const HEIGHT: u32 = 480;
+const VERTEX_S: &[u8] = include_bytes!("shader/shader2.vert.spv");
+const FRAGMENT_S: &[u8] = include_bytes!("shader/shader.frag.spv");
 
 const VERTEX: [Vertex; 4] = [
     Vertex {
@@ -101,22 +97,23 @@ fn main() {
     let desc = CommandPoolDescriptor::empty().queue_family_index(index);
     let pool = device.create_command_pool(&desc).unwrap();
     let desc = CommandRecorderDescriptor::empty();
-    let recorders = device.allocate_command_recorder(pool, &desc).unwrap();
+    let recorders = device.allocate_command_recorder(&pool, &desc).unwrap();
     let images = swapchain.images().unwrap();
     let mut swapchain_images = vec![];
     let desc = ImageViewDescriptor::empty().format(swapchain.format());
     for i in &images {
-        swapchain_images.push(i.create_image_view(&device, &desc));
+        swapchain_images.push(i.create_image_view(&device, &desc).unwrap());
     }
 
-    let vertex = Shader::new(&device, &Spirv::from_raw(VERTEX_S).unwrap());
+    let vertex = Shader::new(&device, &Spirv::from_raw(VERTEX_S).unwrap()).unwrap();
 
-    let fragment = Shader::new(&device, &Spirv::from_raw(FRAGMENT_S).unwrap());
+    let fragment = Shader::new(&device, &Spirv::from_raw(FRAGMENT_S).unwrap()).unwrap();
 
     let desc = BufferDescriptor::empty().size(std::mem::size_of::<Vertex>() * VERTEX.len());
     let vertex_buffer = Buffer::new(&instance, connecter, &device, &desc).unwrap();
-    vertex_buffer.write(&device, VERTEX.as_ptr() as *const c_void);
-    vertex_buffer.lock(&device);
+    vertex_buffer
+        .write(&device, VERTEX.as_ptr() as *const c_void)
+        .unwrap();
     let desc = BufferDescriptor::empty()
         .size(std::mem::size_of::<u16>() * INDICES.len())
         .usage(BufferUsage::Index);
@@ -124,7 +121,6 @@ fn main() {
     index_buffer
         .write(&device, INDICES.as_ptr() as *const c_void)
         .unwrap();
-    index_buffer.lock(&device);
     let desc = BufferDescriptor::empty()
         .size(std::mem::size_of::<SceneData>())
         .usage(BufferUsage::Uniform);
@@ -132,20 +128,19 @@ fn main() {
     uniform_buffer
         .write(&device, &SCENE_DATA as *const SceneData as *const c_void)
         .unwrap();
-    uniform_buffer.lock(&device);
 
     let resource_layout_bindings = vec![ResourceLayoutBinding::empty()
         .binding(0)
         .resource_type(ResourceType::UniformBuffer)
         .count(1)
         .shader_stage(ShaderStage::Vertex)];
-    let resource_layout = ResourceLayout::new(&device, &resource_layout_bindings);
+    let resource_layout = ResourceLayout::new(&device, &resource_layout_bindings).unwrap();
     let pool_sizes = vec![ResourcePoolSize::empty()];
     let pool_desc = ResourcePoolDescriptor::empty()
         .pool_sizes(&pool_sizes)
         .max_sets(1);
-    let resource_pool = ResourcePool::new(&device, &pool_desc);
-    let resource = Resource::allocate(&device, &resource_pool, &resource_layout);
+    let resource_pool = ResourcePool::new(&device, &pool_desc).unwrap();
+    let resource = Resource::allocate(&device, &resource_pool, &resource_layout).unwrap();
 
     let buffer_desc = ResourceBufferDescriptor::new::<SceneData>(&uniform_buffer);
     let desc = vec![buffer_desc];
@@ -197,7 +192,7 @@ fn main() {
         .input_descriptor(&vertex_input_desc)
         .width(size.width)
         .height(size.height);
-    let pipeline = Pipeline::new(&device, pipeline_layout, &render_pass, &desc).unwrap();
+    let pipeline = Pipeline::new(&device, &pipeline_layout, &render_pass, &desc).unwrap();
 
     let mut frame_buffers = vec![];
     for i in &swapchain_images {
@@ -229,8 +224,9 @@ fn main() {
                 event: WindowEvent::CloseRequested,
                 window_id,
             } if window_id == window.id() => control_flow.set_exit(),
-            Event::RedrawRequested(id) => {
-                let (img, state) = swapchain
+            Event::RedrawRequested(_) => {
+                device.collect_garbage().unwrap();
+                let (img, _) = swapchain
                     .acquire_next_image(Some(&swapchain_image_semaphore))
                     .unwrap();
 
@@ -246,8 +242,8 @@ fn main() {
                     .write(&device, &scene_data as *const SceneData as *const c_void)
                     .unwrap();
 
-                image_rendered_fence.wait(&device, u64::MAX);
-                image_rendered_fence.reset(&device);
+                image_rendered_fence.wait(&device, u64::MAX).unwrap();
+                image_rendered_fence.reset(&device).unwrap();
 
                 let begin_desc = RenderPassBeginDescriptor::empty()
                     .width(size.width)
@@ -255,8 +251,8 @@ fn main() {
                     .clear(0.0, 0.0, 0.0, 1.0)
                     .render_pass(&render_pass)
                     .frame_buffer(&frame_buffers[img]);
-                recorders[0].reset(&device);
-                recorders[0].begin(&device, begin_desc);
+                recorders[0].reset(&device).unwrap();
+                recorders[0].begin(&device, begin_desc).unwrap();
                 recorders[0].bind_pipeline(&device, &pipeline[0]);
                 recorders[0].bind_vertex_buffer(&device, &vertex_buffer);
                 recorders[0].bind_index_buffer(&device, &index_buffer);
@@ -264,8 +260,8 @@ fn main() {
                 recorders[0].draw_indexed(&device, INDICES.len() as u32, 1, 0, 0, 0);
                 recorders[0].end(&device).unwrap();
 
-                let w_semaphores = &[swapchain_image_semaphore];
-                let s_semaphores = &[image_rendered_semaphore];
+                let w_semaphores = std::slice::from_ref(&swapchain_image_semaphore);
+                let s_semaphores = std::slice::from_ref(&image_rendered_semaphore);
                 let desc = QueueSubmitDescriptor::empty()
                     .wait_semaphores(w_semaphores)
                     .signal_semaphores(s_semaphores)
@@ -273,12 +269,12 @@ fn main() {
 
                 queue.submit(&device, &desc, &recorders).unwrap();
 
-                let w_semaphores = &[image_rendered_semaphore];
+                let w_semaphores = std::slice::from_ref(&image_rendered_semaphore);
                 let desc = QueuePresentDescriptor::empty()
                     .wait_semaphores(w_semaphores)
                     .queue(&queue);
 
-                swapchain.present(&desc, img as u32);
+                swapchain.present(&desc, img as u32).unwrap();
             }
             _ => (),
         }
@@ -0,0 +1,440 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+use ash::vk;
+
+use crate::{Device, MemoryLocation, NxError, NxResult, VkError};
+
+/// Size of the blocks resources are carved out of, unless the heap is small.
+const BLOCK_SIZE: u64 = 64 << 20;
+/// Smallest range given to a resource, so that small resources do not split blocks into tiny pieces.
+const MIN_ALLOCATION: u64 = 256;
+
+/// Size of the blocks allocated from a heap: at most an eighth of the heap.
+fn block_size(heap_size: u64) -> u64 {
+    match heap_size / 8 {
+        x if x >= BLOCK_SIZE => BLOCK_SIZE,
+        // Rounded down to a power of two.
+        x => (x.max(MIN_ALLOCATION) + 1).next_power_of_two() / 2,
+    }
+}
+
+/// Whether a resource gets a block of its own rather than a range of a shared block.
+/// Ranges are aligned within their block, so a large alignment needs as much room as a large size.
+fn is_dedicated(requirements: &vk::MemoryRequirements, block_size: u64) -> bool {
+    requirements.size.max(requirements.alignment) > block_size / 2
+}
+
+/// Binary buddy allocator over a range whose size is a power of two.
+/// Ranges are rounded up to a power of two, and their offset is a multiple of their size.
+#[doc(hidden)]
+pub(crate) struct Buddy {
+    min_order: u32,
+    /// Free offsets of each order, starting at `min_order`.
+    free: Vec<BTreeSet<u64>>,
+    /// Order of each allocated offset.
+    allocated: HashMap<u64, u32>,
+}
+
+impl Buddy {
+    /// `size` and `min_size` must be powers of two.
+    pub(crate) fn new(size: u64, min_size: u64) -> Self {
+        let min_order = min_size.trailing_zeros();
+        let levels = (size.trailing_zeros() - min_order + 1) as usize;
+        let mut free = vec![BTreeSet::new(); levels];
+        free[levels - 1].insert(0);
+        Self {
+            min_order,
+            free,
+            allocated: HashMap::new(),
+        }
+    }
+
+    /// Returns the offset and the size of the allocated range.
+    /// `alignment` must be a power of two.
+    pub(crate) fn allocate(&mut self, size: u64, alignment: u64) -> Option<(u64, u64)> {
+        let order = size
+            .max(alignment)
+            .next_power_of_two()
+            .trailing_zeros()
+            .max(self.min_order);
+        let level = (order - self.min_order) as usize;
+        let found = (level..self.free.len()).find(|x| !self.free[*x].is_empty())?;
+        let offset = self.free[found].pop_first().unwrap();
+        // Keeps the lower half of each split, and frees the upper one.
+        for x in level..found {
+            self.free[x].insert(offset + (1 << (x as u32 + self.min_order)));
+        }
+        self.allocated.insert(offset, order);
+        Some((offset, 1 << order))
+    }
+
+    /// Frees the range at `offset`, merging it with its free buddies.
+    pub(crate) fn free(&mut self, offset: u64) {
+        let order = match self.allocated.remove(&offset) {
+            Some(x) => x,
+            None => return,
+        };
+        let mut offset = offset;
+        let mut level = (order - self.min_order) as usize;
+        while level + 1 < self.free.len() {
+            let buddy = offset ^ (1 << (level as u32 + self.min_order));
+            if !self.free[level].remove(&buddy) {
+                break;
+            }
+            offset = offset.min(buddy);
+            level += 1;
+        }
+        self.free[level].insert(offset);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.allocated.is_empty()
+    }
+}
+
+/// Host address of a mapped block.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+struct Mapping(NonNull<u8>);
+
+// Each resource only accesses its own range of the block.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+/// Range of device memory bound to a resource.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub(crate) struct Allocation {
+    pub(crate) memory: vk::DeviceMemory,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    /// Properties of the memory type.
+    pub(crate) flags: vk::MemoryPropertyFlags,
+    mapping: Option<Mapping>,
+    block: u64,
+}
+
+impl Allocation {
+    /// Host address of the range, or `None` if the memory is not host-visible.
+    pub(crate) fn mapped(&self) -> Option<*mut u8> {
+        self.mapping
+            .map(|x| unsafe { x.0.as_ptr().add(self.offset as usize) })
+    }
+
+    /// Offset and size in the memory of `size` bytes at `offset` in the range,
+    /// widened to multiples of `atom` as flushes require, without going past the range.
+    pub(crate) fn atom_range(&self, offset: u64, size: u64, atom: u64) -> (u64, u64) {
+        let start = offset / atom * atom;
+        let end = (offset + size).div_ceil(atom) * atom;
+        (self.offset + start, end.min(self.size) - start)
+    }
+}
+
+#[doc(hidden)]
+struct Block {
+    memory: vk::DeviceMemory,
+    memory_type: u32,
+    /// Linear and optimal resources are kept in different blocks,
+    /// so that neighbouring ranges never violate `bufferImageGranularity`.
+    linear: bool,
+    /// `None` for a block dedicated to a single large resource.
+    buddy: Option<Buddy>,
+    /// Host-visible blocks stay mapped until they are freed.
+    mapping: Option<Mapping>,
+}
+
+#[doc(hidden)]
+#[derive(Default)]
+struct Blocks {
+    blocks: HashMap<u64, Block>,
+    next: u64,
+}
+
+impl Blocks {
+    fn insert(&mut self, block: Block) -> u64 {
+        self.next += 1;
+        self.blocks.insert(self.next, block);
+        self.next
+    }
+
+    /// Carves a range out of an existing block of the memory type.
+    fn suballocate(
+        &mut self,
+        memory_type: u32,
+        linear: bool,
+        requirements: &vk::MemoryRequirements,
+        flags: vk::MemoryPropertyFlags,
+    ) -> Option<Allocation> {
+        self.blocks.iter_mut().find_map(|(id, x)| {
+            if x.memory_type != memory_type || x.linear != linear {
+                return None;
+            }
+            let (offset, size) = x
+                .buddy
+                .as_mut()?
+                .allocate(requirements.size, requirements.alignment)?;
+            Some(Allocation {
+                memory: x.memory,
+                offset,
+                size,
+                flags,
+                mapping: x.mapping,
+                block: *id,
+            })
+        })
+    }
+}
+
+/// Allocates the memory of buffers and images from large blocks of each memory type.
+///
+/// Resources larger than half a block get a block of their own.
+#[doc(hidden)]
+pub(crate) struct MemoryAllocator {
+    types: Vec<vk::MemoryPropertyFlags>,
+    /// Size of the blocks of each memory type.
+    block_sizes: Vec<u64>,
+    min_allocation: u64,
+    non_coherent_atom_size: u64,
+    blocks: Mutex<Blocks>,
+}
+
+impl MemoryAllocator {
+    pub(crate) fn new(
+        properties: &vk::PhysicalDeviceMemoryProperties,
+        non_coherent_atom_size: u64,
+    ) -> Self {
+        let types = &properties.memory_types[..properties.memory_type_count as usize];
+        Self {
+            types: types.iter().map(|x| x.property_flags).collect(),
+            block_sizes: types
+                .iter()
+                .map(|x| block_size(properties.memory_heaps[x.heap_index as usize].size))
+                .collect(),
+            // Flushed ranges must be aligned to `nonCoherentAtomSize`.
+            min_allocation: MIN_ALLOCATION.max(non_coherent_atom_size.next_power_of_two()),
+            non_coherent_atom_size,
+            blocks: Mutex::default(),
+        }
+    }
+
+    /// Allocates from the first memory type suitable for `location`.
+    /// When a memory type is out of memory, the next suitable one is tried.
+    pub(crate) fn allocate(
+        &self,
+        device: &Device,
+        requirements: &vk::MemoryRequirements,
+        location: MemoryLocation,
+        linear: bool,
+    ) -> NxResult<Allocation> {
+        let mut blocks = self.blocks.lock().unwrap();
+        let mut error = NxError::NoSuitableMemoryType;
+        for memory_type in location.memory_types(&self.types, requirements.memory_type_bits) {
+            let flags = self.types[memory_type as usize];
+            if let Some(x) = blocks.suballocate(memory_type, linear, requirements, flags) {
+                return Ok(x);
+            }
+            let block_size = self.block_sizes[memory_type as usize];
+            let dedicated = is_dedicated(requirements, block_size);
+            let size = match dedicated {
+                true => requirements.size,
+                false => block_size,
+            };
+            let (memory, mapping) = match Self::allocate_block(device, memory_type, size, flags) {
+                Ok(x) => x,
+                Err(
+                    e @ NxError::Vulkan {
+                        error: VkError::OutOfDeviceMemory,
+                        ..
+                    },
+                ) => {
+                    error = e;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let block = blocks.insert(Block {
+                memory,
+                memory_type,
+                linear,
+                buddy: (!dedicated).then(|| Buddy::new(size, self.min_allocation)),
+                mapping,
+            });
+            if dedicated {
+                return Ok(Allocation {
+                    memory,
+                    offset: 0,
+                    size,
+                    flags,
+                    mapping,
+                    block,
+                });
+            }
+            // The new block is empty, so the range fits.
+            return Ok(blocks
+                .suballocate(memory_type, linear, requirements, flags)
+                .unwrap());
+        }
+        Err(error)
+    }
+
+    fn allocate_block(
+        device: &Device,
+        memory_type: u32,
+        size: u64,
+        flags: vk::MemoryPropertyFlags,
+    ) -> NxResult<(vk::DeviceMemory, Option<Mapping>)> {
+        let info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type)
+            .build();
+        let memory = unsafe {
+            device
+                .device
+                .allocate_memory(&info, device.allocation_callbacks())
+        }
+        .map_err(|e| device.error("vkAllocateMemory", e))?;
+        if !flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            return Ok((memory, None));
+        }
+        match unsafe {
+            device
+                .device
+                .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+        } {
+            Ok(x) => Ok((memory, NonNull::new(x as *mut u8).map(Mapping))),
+            Err(e) => {
+                unsafe {
+                    device
+                        .device
+                        .free_memory(memory, device.allocation_callbacks())
+                };
+                Err(device.error("vkMapMemory", e))
+            }
+        }
+    }
+
+    /// Returns a range to its block. The GPU must no longer use it.
+    ///
+    /// Empty blocks are freed, except the last block of a memory type, which is kept for the next resources.
+    pub(crate) fn free(
+        &self,
+        device: &ash::Device,
+        callbacks: Option<&vk::AllocationCallbacks>,
+        allocation: &Allocation,
+    ) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let block = match blocks.blocks.get_mut(&allocation.block) {
+            Some(x) => x,
+            None => return,
+        };
+        let (memory_type, linear) = (block.memory_type, block.linear);
+        let empty = match &mut block.buddy {
+            Some(buddy) => {
+                buddy.free(allocation.offset);
+                buddy.is_empty()
+            }
+            None => true,
+        };
+        let dedicated = block.buddy.is_none();
+        let last = !blocks.blocks.iter().any(|(id, x)| {
+            *id != allocation.block
+                && x.memory_type == memory_type
+                && x.linear == linear
+                && x.buddy.is_some()
+        });
+        if empty && (dedicated || !last) {
+            blocks.blocks.remove(&allocation.block);
+            unsafe { device.free_memory(allocation.memory, callbacks) };
+        }
+    }
+
+    /// `nonCoherentAtomSize` of the device.
+    pub(crate) fn atom_size(&self) -> u64 {
+        self.non_coherent_atom_size
+    }
+
+    /// Number of `vkAllocateMemory` allocations currently held.
+    pub(crate) fn block_count(&self) -> usize {
+        self.blocks.lock().unwrap().blocks.len()
+    }
+
+    /// Frees every block. The device must be idle.
+    pub(crate) fn flush(
+        &mut self,
+        device: &ash::Device,
+        callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        for (_, x) in self.blocks.get_mut().unwrap().blocks.drain() {
+            unsafe { device.free_memory(x.memory, callbacks) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_and_merges_buddies() {
+        let mut buddy = Buddy::new(1024, 64);
+        assert_eq!(buddy.allocate(100, 4), Some((0, 128)));
+        assert_eq!(buddy.allocate(64, 64), Some((128, 64)));
+        // Aligned to its alignment rather than its size.
+        assert_eq!(buddy.allocate(16, 256), Some((256, 256)));
+        assert_eq!(buddy.allocate(512, 1), Some((512, 512)));
+        assert_eq!(buddy.allocate(64, 1), Some((192, 64)));
+        assert_eq!(buddy.allocate(64, 1), None);
+
+        for offset in [0, 128, 192, 256] {
+            buddy.free(offset);
+        }
+        // The lower half merged back.
+        assert_eq!(buddy.allocate(512, 1), Some((0, 512)));
+        buddy.free(0);
+        buddy.free(512);
+        assert!(buddy.is_empty());
+        assert_eq!(buddy.allocate(1024, 1), Some((0, 1024)));
+    }
+
+    #[test]
+    fn widens_flushed_ranges_to_atoms() {
+        let allocation = Allocation {
+            memory: vk::DeviceMemory::null(),
+            offset: 1024,
+            size: 256,
+            flags: vk::MemoryPropertyFlags::empty(),
+            mapping: None,
+            block: 0,
+        };
+        assert_eq!(allocation.atom_range(0, 256, 64), (1024, 256));
+        assert_eq!(allocation.atom_range(70, 10, 64), (1088, 64));
+        assert_eq!(allocation.atom_range(100, 60, 64), (1088, 128));
+        // Never past the end of the range.
+        assert_eq!(allocation.atom_range(250, 6, 128), (1152, 128));
+    }
+
+    #[test]
+    fn dedicates_blocks_to_large_sizes_and_alignments() {
+        let requirements = |size, alignment| vk::MemoryRequirements {
+            size,
+            alignment,
+            memory_type_bits: !0,
+        };
+        assert!(!is_dedicated(&requirements(1024, 256), 4096));
+        assert!(is_dedicated(&requirements(3000, 256), 4096));
+        assert!(is_dedicated(&requirements(256, 4096), 4096));
+        // The new shared block fits the range.
+        let mut buddy = Buddy::new(4096, MIN_ALLOCATION);
+        assert!(buddy.allocate(256, 2048).is_some());
+    }
+
+    #[test]
+    fn limits_blocks_to_an_eighth_of_the_heap() {
+        assert_eq!(block_size(8 << 30), BLOCK_SIZE);
+        assert_eq!(block_size(256 << 20), 32 << 20);
+        assert_eq!(block_size(300 << 20), 32 << 20);
+        assert_eq!(block_size(1024), MIN_ALLOCATION);
+    }
+}
@@ -1,13 +1,21 @@
 use crate::mem::DeviceMemory;
-use crate::{Destroy, Device, DeviceConnecter, Instance, NxError, NxResult};
-use ash::vk::{BufferCreateInfo, BufferUsageFlags, MappedMemoryRange, MemoryMapFlags, SharingMode};
+use crate::{
+    Destroy, Device, DeviceChild, DeviceConnecter, Garbage, Instance, MemoryLocation, NotSync,
+    NxResult,
+};
+use ash::vk::{BufferCreateInfo, BufferUsageFlags, SharingMode};
 use std::ffi::c_void;
+use std::marker::PhantomData;
 
+/// Indicates how a buffer is used.
+/// Every buffer can also be the source and the destination of copies, such as uploads with `StagingBelt`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum BufferUsage {
     Vertex,
     Index,
     Uniform,
+    /// Only used as the source or destination of copies, such as a staging buffer.
+    Transfer,
 }
 
 impl From<BufferUsage> for BufferUsageFlags {
@@ -16,6 +24,7 @@ impl From<BufferUsage> for BufferUsageFlags {
             BufferUsage::Vertex => BufferUsageFlags::VERTEX_BUFFER,
             BufferUsage::Index => BufferUsageFlags::INDEX_BUFFER,
             BufferUsage::Uniform => BufferUsageFlags::UNIFORM_BUFFER,
+            BufferUsage::Transfer => BufferUsageFlags::empty(),
         }
     }
 }
@@ -23,6 +32,8 @@ impl From<BufferUsage> for BufferUsageFlags {
 pub struct BufferDescriptor {
     size: usize,
     usage: BufferUsage,
+    location: MemoryLocation,
+    label: Option<String>,
 }
 
 impl BufferDescriptor {
@@ -30,6 +41,8 @@ impl BufferDescriptor {
         Self {
             size: 0,
             usage: BufferUsage::Vertex,
+            location: MemoryLocation::Upload,
+            label: None,
         }
     }
 
@@ -42,95 +55,111 @@ impl BufferDescriptor {
         self.usage = usage;
         self
     }
+
+    /// Specifies where the memory of the Buffer is placed. The default is `MemoryLocation::Upload`.
+    /// `Buffer::write` fails if the location is `MemoryLocation::GpuOnly`.
+    pub fn location(mut self, location: MemoryLocation) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Specifies the debug name of the Buffer. See `DebugName`.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 pub struct Buffer {
     pub(crate) buffer: ash::vk::Buffer,
-    memory: DeviceMemory,
+    pub(crate) memory: DeviceMemory,
     size: usize,
+    owner: DeviceChild,
+    _not_sync: NotSync,
 }
 
 impl Buffer {
+    /// `instance` and `connecter` are unused: memory is carved out of the blocks of the device's allocator.
     pub fn new(
-        instance: &Instance,
-        connecter: DeviceConnecter,
+        _instance: &Instance,
+        _connecter: DeviceConnecter,
         device: &Device,
         descriptor: &BufferDescriptor,
     ) -> NxResult<Self> {
+        Self::create(device, descriptor)
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn create(device: &Device, descriptor: &BufferDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let create_info = BufferCreateInfo::builder()
             .size(descriptor.size as u64)
-            .usage(descriptor.usage.into())
+            .usage(
+                BufferUsageFlags::from(descriptor.usage)
+                    | BufferUsageFlags::TRANSFER_SRC
+                    | BufferUsageFlags::TRANSFER_DST,
+            )
             .sharing_mode(SharingMode::EXCLUSIVE)
             .build();
-        let buffer = unsafe { device.device.create_buffer(&create_info, None) }.unwrap();
-        let mem_props = connecter.get_memory_properties(instance);
-        let mem_req = unsafe { device.device.get_buffer_memory_requirements(buffer) };
-        let memory =
-            match DeviceMemory::alloc_buffer_memory(&device.device, buffer, mem_props, mem_req) {
-                Ok(x) => x,
-                Err(e) => return Err(e),
-            };
+        let buffer = unsafe {
+            device
+                .device
+                .create_buffer(&create_info, device.allocation_callbacks())
+        }
+        .map_err(|e| device.error("vkCreateBuffer", e))?;
+        let memory = match DeviceMemory::alloc_buffer_memory(device, buffer, descriptor.location) {
+            Ok(x) => x,
+            Err(e) => {
+                unsafe {
+                    device
+                        .device
+                        .destroy_buffer(buffer, device.allocation_callbacks())
+                };
+                return Err(e);
+            }
+        };
+        device.label(buffer, descriptor.label.as_deref());
 
         Ok(Self {
             buffer,
             memory,
             size: descriptor.size,
+            owner: DeviceChild::new(device, buffer, descriptor.label.as_deref()),
+            _not_sync: PhantomData,
         })
     }
 
-    pub fn size(&self, device: &Device) -> u64 {
-        self.memory.size(device)
+    /// Size of the buffer, as given by `BufferDescriptor::size`.
+    pub fn size(&self, _device: &Device) -> u64 {
+        self.size as u64
     }
 
+    /// Copies the size of the buffer from `data` into its memory.
+    /// Fails with `NxError::MemoryNotMappable` if the location is `MemoryLocation::GpuOnly`.
     pub fn write(&self, device: &Device, data: *const c_void) -> NxResult<()> {
-        let mapped_memory = match unsafe {
-            device.device.map_memory(
-                self.memory.memory,
-                0,
-                self.size as u64,
-                MemoryMapFlags::empty(),
-            )
-        } {
-            Ok(x) => x,
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                ash::vk::Result::ERROR_MEMORY_MAP_FAILED => Err(NxError::MemoryMapFailed),
-                _ => Err(NxError::Unknown),
-            }?,
-        };
-
-        mem_copy(mapped_memory, data, self.size);
-        let flush_memory_range = MappedMemoryRange::builder()
-            .memory(self.memory.memory)
-            .offset(0)
-            .size(self.size as u64)
-            .build();
-        unsafe {
-            device
-                .device
-                .flush_mapped_memory_ranges(&[flush_memory_range])
-                .unwrap();
-        }
-
-        Ok(())
+        device.check_validation()?;
+        mem_copy(self.memory.mapped()?, data, self.size);
+        self.memory.flush(device)
     }
 
-    pub fn lock(&self, device: &Device) {
-        unsafe {
-            device.device.unmap_memory(self.memory.memory);
-        }
-    }
+    /// Does nothing. Host-visible memory stays mapped while the buffer is alive.
+    #[deprecated(since = "0.1.1", note = "memory stays mapped")]
+    pub fn lock(&self, _device: &Device) {}
 }
 
 impl Destroy for Buffer {
     fn instance(&self, _: &Instance) {}
 
     fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_buffer(self.buffer, None);
-            device.destroy(&self.memory);
-        }
+        self.owner.destroy(Garbage::Buffer(self.buffer));
+        device.destroy(&self.memory);
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        // The memory is dropped after the buffer, so it is released at the same time.
+        self.owner.defer(Garbage::Buffer(self.buffer));
     }
 }
 
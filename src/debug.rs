@@ -0,0 +1,891 @@
+use std::borrow::Cow;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{
+    Buffer, CommandPool, CommandRecorder, Device, Fence, FrameBuffer, HostLock, Image, ImageView,
+    NxError, NxResult, Pipeline, PipelineLayout, Queue, RenderPass, Resource, ResourceLayout,
+    ResourcePool, Semaphore, Shader, Swapchain,
+};
+use ash::vk::{
+    self, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT, Handle,
+    ValidationFeatureEnableEXT,
+};
+
+/// Name of the Khronos validation layer.
+pub(crate) const VALIDATION_LAYER_NAME: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Indicates the severity of a debug message.
+///
+/// Severities are ordered, so `DebugSeverity::Warning` is greater than `DebugSeverity::Info`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum DebugSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl DebugSeverity {
+    #[doc(hidden)]
+    pub(crate) fn from_flags(flags: DebugUtilsMessageSeverityFlagsEXT) -> Self {
+        if flags.contains(DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            DebugSeverity::Error
+        } else if flags.contains(DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            DebugSeverity::Warning
+        } else if flags.contains(DebugUtilsMessageSeverityFlagsEXT::INFO) {
+            DebugSeverity::Info
+        } else {
+            DebugSeverity::Verbose
+        }
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn at_least(self) -> DebugUtilsMessageSeverityFlagsEXT {
+        let mut flags = DebugUtilsMessageSeverityFlagsEXT::ERROR;
+        if self <= DebugSeverity::Warning {
+            flags |= DebugUtilsMessageSeverityFlagsEXT::WARNING;
+        }
+        if self <= DebugSeverity::Info {
+            flags |= DebugUtilsMessageSeverityFlagsEXT::INFO;
+        }
+        if self <= DebugSeverity::Verbose {
+            flags |= DebugUtilsMessageSeverityFlagsEXT::VERBOSE;
+        }
+        flags
+    }
+}
+
+/// What happens when the debug messenger reports a validation error.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ValidationAction {
+    /// Only deliver the message to the debug sink.
+    #[default]
+    Log,
+    /// Panic on the next fallible call, or on `Device::check_validation`.
+    Panic,
+    /// Return `NxError::Validation` from the next fallible call, or from `Device::check_validation`.
+    Error,
+}
+
+/// Stores information needed to set up validation and the debug messenger of an instance.
+///
+/// # Example
+/// ```no_run
+/// use nexg::{InstanceBuilder, ValidationDescriptor};
+///
+/// let validation = if cfg!(debug_assertions) {
+///     ValidationDescriptor::strict()
+/// } else {
+///     ValidationDescriptor::disabled()
+/// };
+/// let instance = InstanceBuilder::new().validation(validation).build().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ValidationDescriptor {
+    pub(crate) layer: bool,
+    pub(crate) messenger: bool,
+    pub(crate) min_severity: DebugSeverity,
+    pub(crate) general: bool,
+    pub(crate) validation: bool,
+    pub(crate) performance: bool,
+    pub(crate) synchronization: bool,
+    pub(crate) best_practices: bool,
+    pub(crate) gpu_assisted: bool,
+    pub(crate) action: ValidationAction,
+    pub(crate) suppressed_ids: Vec<i32>,
+    pub(crate) suppressed_names: Vec<String>,
+}
+
+impl ValidationDescriptor {
+    /// Initializes a new descriptor with default values.
+    /// The debug messenger reports all messages of **Info** severity or above,
+    /// but no validation layer is enabled.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            layer: false,
+            messenger: true,
+            min_severity: DebugSeverity::Info,
+            general: true,
+            validation: true,
+            performance: true,
+            synchronization: false,
+            best_practices: false,
+            gpu_assisted: false,
+            action: ValidationAction::Log,
+            suppressed_ids: Vec::new(),
+            suppressed_names: Vec::new(),
+        }
+    }
+
+    /// No validation layer and no debug messenger.
+    /// `VK_EXT_debug_utils` is not enabled either, which is suitable for release builds.
+    #[inline]
+    pub const fn disabled() -> Self {
+        Self {
+            layer: false,
+            messenger: false,
+            min_severity: DebugSeverity::Error,
+            general: false,
+            validation: false,
+            performance: false,
+            synchronization: false,
+            best_practices: false,
+            gpu_assisted: false,
+            action: ValidationAction::Log,
+            suppressed_ids: Vec::new(),
+            suppressed_names: Vec::new(),
+        }
+    }
+
+    /// Enables the validation layer together with synchronization validation and best practices.
+    /// Messages of **Warning** severity or above are reported.
+    #[inline]
+    pub const fn strict() -> Self {
+        Self {
+            layer: true,
+            messenger: true,
+            min_severity: DebugSeverity::Warning,
+            general: true,
+            validation: true,
+            performance: true,
+            synchronization: true,
+            best_practices: true,
+            gpu_assisted: false,
+            action: ValidationAction::Log,
+            suppressed_ids: Vec::new(),
+            suppressed_names: Vec::new(),
+        }
+    }
+
+    /// Enables `VK_LAYER_KHRONOS_validation`.
+    #[inline]
+    pub const fn layer(mut self, layer: bool) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Creates the debug messenger.
+    #[inline]
+    pub const fn messenger(mut self, messenger: bool) -> Self {
+        self.messenger = messenger;
+        self
+    }
+
+    /// Lowest severity reported by the debug messenger.
+    #[inline]
+    pub const fn min_severity(mut self, severity: DebugSeverity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+
+    /// Reports general messages.
+    #[inline]
+    pub const fn general_messages(mut self, general: bool) -> Self {
+        self.general = general;
+        self
+    }
+
+    /// Reports validation messages.
+    #[inline]
+    pub const fn validation_messages(mut self, validation: bool) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Reports performance messages.
+    #[inline]
+    pub const fn performance_messages(mut self, performance: bool) -> Self {
+        self.performance = performance;
+        self
+    }
+
+    /// Enables synchronization validation.
+    /// Requires the validation layer.
+    #[inline]
+    pub const fn synchronization(mut self, synchronization: bool) -> Self {
+        self.synchronization = synchronization;
+        self
+    }
+
+    /// Enables best practices validation.
+    /// Requires the validation layer.
+    #[inline]
+    pub const fn best_practices(mut self, best_practices: bool) -> Self {
+        self.best_practices = best_practices;
+        self
+    }
+
+    /// Enables GPU-assisted validation.
+    /// Requires the validation layer and makes every submission considerably slower.
+    #[inline]
+    pub const fn gpu_assisted(mut self, gpu_assisted: bool) -> Self {
+        self.gpu_assisted = gpu_assisted;
+        self
+    }
+
+    /// Specifies what happens when a validation error is reported.
+    #[inline]
+    pub const fn on_error(mut self, action: ValidationAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Suppresses messages with the given message ID number.
+    /// Suppressed messages are neither delivered to the debug sink nor treated as errors.
+    pub fn suppress(mut self, message_id_number: i32) -> Self {
+        self.suppressed_ids.push(message_id_number);
+        self
+    }
+
+    /// Suppresses messages with the given message ID name, such as a VUID.
+    /// Suppressed messages are neither delivered to the debug sink nor treated as errors.
+    pub fn suppress_name(mut self, message_id_name: &str) -> Self {
+        self.suppressed_names.push(message_id_name.to_owned());
+        self
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn severity_flags(&self) -> DebugUtilsMessageSeverityFlagsEXT {
+        self.min_severity.at_least()
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn message_type_flags(&self) -> DebugUtilsMessageTypeFlagsEXT {
+        let mut flags = DebugUtilsMessageTypeFlagsEXT::empty();
+        if self.general {
+            flags |= DebugUtilsMessageTypeFlagsEXT::GENERAL;
+        }
+        if self.validation {
+            flags |= DebugUtilsMessageTypeFlagsEXT::VALIDATION;
+        }
+        if self.performance {
+            flags |= DebugUtilsMessageTypeFlagsEXT::PERFORMANCE;
+        }
+        flags
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn enabled_features(&self) -> Vec<ValidationFeatureEnableEXT> {
+        let mut features = vec![];
+        if !self.layer {
+            return features;
+        }
+        if self.synchronization {
+            features.push(ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+        }
+        if self.best_practices {
+            features.push(ValidationFeatureEnableEXT::BEST_PRACTICES);
+        }
+        if self.gpu_assisted {
+            features.push(ValidationFeatureEnableEXT::GPU_ASSISTED);
+            features.push(ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+        }
+        features
+    }
+}
+
+impl Default for ValidationDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Indicates the kind of a debug message.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct DebugMessageType {
+    general: bool,
+    validation: bool,
+    performance: bool,
+}
+
+impl DebugMessageType {
+    /// The message is not related to the specification or performance.
+    pub fn is_general(&self) -> bool {
+        self.general
+    }
+
+    /// The message reports a violation of the specification.
+    pub fn is_validation(&self) -> bool {
+        self.validation
+    }
+
+    /// The message reports a potentially non-optimal use of Vulkan.
+    pub fn is_performance(&self) -> bool {
+        self.performance
+    }
+}
+
+impl From<DebugUtilsMessageTypeFlagsEXT> for DebugMessageType {
+    fn from(value: DebugUtilsMessageTypeFlagsEXT) -> Self {
+        Self {
+            general: value.contains(DebugUtilsMessageTypeFlagsEXT::GENERAL),
+            validation: value.contains(DebugUtilsMessageTypeFlagsEXT::VALIDATION),
+            performance: value.contains(DebugUtilsMessageTypeFlagsEXT::PERFORMANCE),
+        }
+    }
+}
+
+/// An object referenced by a debug message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebugObject {
+    object_type: String,
+    handle: u64,
+    name: Option<String>,
+}
+
+impl DebugObject {
+    #[doc(hidden)]
+    pub(crate) fn new(object_type: vk::ObjectType, handle: u64, name: Option<String>) -> Self {
+        Self {
+            object_type: format!("{:?}", object_type),
+            handle,
+            name,
+        }
+    }
+
+    /// Type of the object, such as `BUFFER` or `IMAGE`.
+    pub fn object_type(&self) -> &str {
+        &self.object_type
+    }
+
+    /// Raw handle of the object.
+    pub fn handle(&self) -> u64 {
+        self.handle
+    }
+
+    /// Debug name of the object, if one was set.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// A message reported by the debug messenger.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebugMessage {
+    severity: DebugSeverity,
+    message_type: DebugMessageType,
+    message_id_number: i32,
+    message_id_name: String,
+    message: String,
+    objects: Vec<DebugObject>,
+    queue_labels: Vec<String>,
+    command_buffer_labels: Vec<String>,
+}
+
+impl DebugMessage {
+    pub fn severity(&self) -> DebugSeverity {
+        self.severity
+    }
+
+    pub fn message_type(&self) -> DebugMessageType {
+        self.message_type
+    }
+
+    /// Numeric identifier of the message, such as the hash of a VUID.
+    pub fn message_id_number(&self) -> i32 {
+        self.message_id_number
+    }
+
+    /// Name of the message, such as a VUID.
+    pub fn message_id_name(&self) -> &str {
+        &self.message_id_name
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Objects related to the message.
+    pub fn objects(&self) -> &[DebugObject] {
+        &self.objects
+    }
+
+    /// Labels of the queue that were active when the message was reported.
+    pub fn queue_labels(&self) -> &[String] {
+        &self.queue_labels
+    }
+
+    /// Labels of the command buffer that were active when the message was reported.
+    pub fn command_buffer_labels(&self) -> &[String] {
+        &self.command_buffer_labels
+    }
+
+    /// Check to see if the message is a validation error.
+    pub fn is_validation_error(&self) -> bool {
+        self.severity == DebugSeverity::Error && self.message_type.is_validation()
+    }
+
+    #[doc(hidden)]
+    unsafe fn from_raw(
+        severity: DebugUtilsMessageSeverityFlagsEXT,
+        message_type: DebugUtilsMessageTypeFlagsEXT,
+        data: &vk::DebugUtilsMessengerCallbackDataEXT,
+    ) -> Self {
+        let objects = raw_slice(data.p_objects, data.object_count)
+            .iter()
+            .map(|x| {
+                DebugObject::new(
+                    x.object_type,
+                    x.object_handle,
+                    (!x.p_object_name.is_null()).then(|| to_string(x.p_object_name).into_owned()),
+                )
+            })
+            .collect();
+        let labels = |labels: &[vk::DebugUtilsLabelEXT]| {
+            labels
+                .iter()
+                .map(|x| to_string(x.p_label_name).into_owned())
+                .collect()
+        };
+        Self {
+            severity: DebugSeverity::from_flags(severity),
+            message_type: message_type.into(),
+            message_id_number: data.message_id_number,
+            message_id_name: to_string(data.p_message_id_name).into_owned(),
+            message: to_string(data.p_message).into_owned(),
+            objects,
+            queue_labels: labels(raw_slice(data.p_queue_labels, data.queue_label_count)),
+            command_buffer_labels: labels(raw_slice(
+                data.p_cmd_buf_labels,
+                data.cmd_buf_label_count,
+            )),
+        }
+    }
+}
+
+/// Receives messages from the debug messenger.
+///
+/// Implemented for every `Fn(&DebugMessage)` closure.
+/// Messages may be delivered from any thread that calls into Vulkan.
+pub trait DebugMessageSink: Send + Sync {
+    fn message(&self, message: &DebugMessage);
+}
+
+impl<F> DebugMessageSink for F
+where
+    F: Fn(&DebugMessage) + Send + Sync,
+{
+    fn message(&self, message: &DebugMessage) {
+        self(message)
+    }
+}
+
+/// Forwards debug messages to the `log` crate.
+/// This is the sink used if none is specified.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogSink;
+
+impl DebugMessageSink for LogSink {
+    fn message(&self, message: &DebugMessage) {
+        match message.severity {
+            DebugSeverity::Verbose => trace!("[Vulkan] {}", message.message),
+            DebugSeverity::Info => info!("[Vulkan] {}", message.message),
+            DebugSeverity::Warning => warn!("[Vulkan] {}", message.message),
+            DebugSeverity::Error => error!("[Vulkan] {}", message.message),
+        }
+    }
+}
+
+/// Stores every debug message in memory.
+///
+/// Clones share the same storage, so a clone can be passed to the `InstanceBuilder`
+/// and the original inspected afterwards.
+///
+/// # Example
+/// ```no_run
+/// use nexg::{CollectingSink, InstanceBuilder, ValidationDescriptor};
+///
+/// let sink = CollectingSink::new();
+/// let instance = InstanceBuilder::new()
+///     .validation(ValidationDescriptor::strict())
+///     .debug_sink(sink.clone())
+///     .build()
+///     .unwrap();
+/// // Render something.
+/// assert!(!sink.has_errors());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CollectingSink {
+    messages: Arc<Mutex<Vec<DebugMessage>>>,
+}
+
+impl CollectingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a copy of all messages collected so far.
+    pub fn messages(&self) -> Vec<DebugMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// Get a copy of the collected messages of **Error** severity.
+    pub fn errors(&self) -> Vec<DebugMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| x.severity == DebugSeverity::Error)
+            .cloned()
+            .collect()
+    }
+
+    /// Check to see if any message of **Error** severity was collected.
+    pub fn has_errors(&self) -> bool {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|x| x.severity == DebugSeverity::Error)
+    }
+
+    /// Remove all collected messages.
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+}
+
+impl DebugMessageSink for CollectingSink {
+    fn message(&self, message: &DebugMessage) {
+        self.messages.lock().unwrap().push(message.clone());
+    }
+}
+
+/// Implemented by objects that can be given a name.
+///
+/// Names appear in validation messages and in debugging tools such as RenderDoc,
+/// which otherwise only show raw handles.
+/// Naming requires the `VK_EXT_debug_utils` extension, which is enabled together with the debug messenger.
+/// Without it, naming does nothing.
+pub trait DebugName {
+    #[doc(hidden)]
+    fn debug_handle(&self) -> (vk::ObjectType, u64);
+
+    /// Set the name of the object.
+    fn set_debug_name(&self, device: &Device, name: &str) -> NxResult<()> {
+        let (object_type, handle) = self.debug_handle();
+        device.set_object_name(object_type, handle, name)
+    }
+}
+
+macro_rules! impl_debug_name {
+    ($($ty:ty => $($field:tt).+;)*) => {
+        $(
+            impl DebugName for $ty {
+                fn debug_handle(&self) -> (vk::ObjectType, u64) {
+                    debug_handle(self.$($field).+)
+                }
+            }
+        )*
+    };
+}
+
+#[doc(hidden)]
+pub(crate) fn debug_string(name: &str) -> CString {
+    // Names cannot contain nul characters, so anything after one is dropped.
+    CString::new(name.split('\0').next().unwrap_or_default()).unwrap()
+}
+
+#[doc(hidden)]
+pub(crate) fn debug_handle<H: Handle>(handle: H) -> (vk::ObjectType, u64) {
+    (H::TYPE, handle.as_raw())
+}
+
+impl_debug_name! {
+    Buffer => buffer;
+    CommandPool => pool;
+    CommandRecorder => buffer;
+    Fence => fence;
+    FrameBuffer => frame_buffer;
+    Image => image;
+    ImageView => image_view;
+    Pipeline => pipeline;
+    PipelineLayout => layout;
+    Queue => queue;
+    RenderPass => render_pass;
+    Resource => descriptor_set;
+    ResourceLayout => inner;
+    ResourcePool => pool;
+    Semaphore => semaphore;
+    Shader => inner;
+    Swapchain => khr;
+}
+
+/// Command buffer or queue to label, with the lock that synchronizes access to it.
+#[doc(hidden)]
+pub(crate) enum LabelTarget {
+    Recorder(vk::CommandBuffer, HostLock),
+    Queue(vk::Queue, HostLock),
+}
+
+impl LabelTarget {
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        match self {
+            LabelTarget::Recorder(_, x) | LabelTarget::Queue(_, x) => x.lock(),
+        }
+    }
+
+    pub(crate) fn begin(&self, device: &Device, name: &str, color: [f32; 4]) {
+        let debug_utils = match device.debug_utils() {
+            Some(x) => x,
+            None => return,
+        };
+        let name = debug_string(name);
+        let label = vk::DebugUtilsLabelEXT::builder()
+            .label_name(&name)
+            .color(color)
+            .build();
+        let _lock = self.lock();
+        unsafe {
+            match *self {
+                LabelTarget::Recorder(x, _) => debug_utils.cmd_begin_debug_utils_label(x, &label),
+                LabelTarget::Queue(x, _) => debug_utils.queue_begin_debug_utils_label(x, &label),
+            }
+        }
+    }
+
+    pub(crate) fn end(&self, device: &Device) {
+        let debug_utils = match device.debug_utils() {
+            Some(x) => x,
+            None => return,
+        };
+        let _lock = self.lock();
+        unsafe {
+            match *self {
+                LabelTarget::Recorder(x, _) => debug_utils.cmd_end_debug_utils_label(x),
+                LabelTarget::Queue(x, _) => debug_utils.queue_end_debug_utils_label(x),
+            }
+        }
+    }
+
+    pub(crate) fn insert(&self, device: &Device, name: &str, color: [f32; 4]) {
+        let debug_utils = match device.debug_utils() {
+            Some(x) => x,
+            None => return,
+        };
+        let name = debug_string(name);
+        let label = vk::DebugUtilsLabelEXT::builder()
+            .label_name(&name)
+            .color(color)
+            .build();
+        let _lock = self.lock();
+        unsafe {
+            match *self {
+                LabelTarget::Recorder(x, _) => debug_utils.cmd_insert_debug_utils_label(x, &label),
+                LabelTarget::Queue(x, _) => debug_utils.queue_insert_debug_utils_label(x, &label),
+            }
+        }
+    }
+}
+
+/// A debug label region that is closed when dropped.
+///
+/// Returned by `CommandRecorder::debug_label_scope` and `Queue::debug_label_scope`.
+#[must_use = "the label region is closed as soon as the scope is dropped"]
+pub struct DebugLabelScope<'a> {
+    device: &'a Device,
+    target: LabelTarget,
+}
+
+impl<'a> DebugLabelScope<'a> {
+    #[doc(hidden)]
+    pub(crate) fn new(
+        device: &'a Device,
+        target: LabelTarget,
+        name: &str,
+        color: [f32; 4],
+    ) -> Self {
+        target.begin(device, name, color);
+        Self { device, target }
+    }
+}
+
+impl Drop for DebugLabelScope<'_> {
+    fn drop(&mut self) {
+        self.target.end(self.device);
+    }
+}
+
+#[doc(hidden)]
+pub(crate) struct DebugState {
+    sink: Box<dyn DebugMessageSink>,
+    action: ValidationAction,
+    suppressed_ids: Vec<i32>,
+    suppressed_names: Vec<String>,
+    pending: Mutex<Option<DebugMessage>>,
+}
+
+impl DebugState {
+    pub(crate) fn new(sink: Box<dyn DebugMessageSink>, validation: &ValidationDescriptor) -> Self {
+        Self {
+            sink,
+            action: validation.action,
+            suppressed_ids: validation.suppressed_ids.clone(),
+            suppressed_names: validation.suppressed_names.clone(),
+            pending: Mutex::new(None),
+        }
+    }
+
+    fn is_suppressed(&self, message: &DebugMessage) -> bool {
+        self.suppressed_ids.contains(&message.message_id_number)
+            || self.suppressed_names.contains(&message.message_id_name)
+    }
+
+    fn report(&self, message: DebugMessage) {
+        if self.is_suppressed(&message) {
+            return;
+        }
+        // Unwinding out of an `extern "system"` function aborts, so a panicking sink is contained here.
+        let _ =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.sink.message(&message)));
+        if self.action != ValidationAction::Log && message.is_validation_error() {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.is_none() {
+                *pending = Some(message);
+            }
+        }
+    }
+
+    /// Reports an object that was still alive when the last handle of its device was dropped.
+    /// The message can be suppressed with the name `nexg-leaked-object`.
+    pub(crate) fn report_leak(&self, object: DebugObject, backtrace: &str) {
+        let name = match object.name() {
+            Some(x) => format!("{} `{}`", object.object_type(), x),
+            None => format!("{} {:#x}", object.object_type(), object.handle()),
+        };
+        self.report(DebugMessage {
+            severity: DebugSeverity::Warning,
+            message_type: DebugUtilsMessageTypeFlagsEXT::GENERAL.into(),
+            message_id_number: 0,
+            message_id_name: "nexg-leaked-object".to_owned(),
+            message: format!(
+                "{} was still alive when the device was dropped. {}",
+                name, backtrace
+            ),
+            objects: vec![object],
+            queue_labels: vec![],
+            command_buffer_labels: vec![],
+        });
+    }
+
+    /// Returns the first validation error reported since the last call.
+    pub(crate) fn take_pending(&self) -> NxResult<()> {
+        let message = match self.pending.lock().unwrap().take() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        match self.action {
+            ValidationAction::Log => Ok(()),
+            ValidationAction::Panic => panic!("Validation error: {}", message.message),
+            ValidationAction::Error => Err(NxError::Validation(Box::new(message))),
+        }
+    }
+}
+
+#[doc(hidden)]
+unsafe fn to_string<'a>(ptr: *const c_char) -> Cow<'a, str> {
+    if ptr.is_null() {
+        Cow::from("")
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy()
+    }
+}
+
+#[doc(hidden)]
+unsafe fn raw_slice<'a, T>(ptr: *const T, count: u32) -> &'a [T] {
+    if ptr.is_null() || count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, count as usize)
+    }
+}
+
+#[doc(hidden)]
+pub(crate) unsafe extern "system" fn vulkan_debug_callback(
+    message_severity: DebugUtilsMessageSeverityFlagsEXT,
+    message_type: DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut c_void,
+) -> vk::Bool32 {
+    if p_callback_data.is_null() || user_data.is_null() {
+        return vk::FALSE;
+    }
+    let state = &*(user_data as *const DebugState);
+    state.report(DebugMessage::from_raw(
+        message_severity,
+        message_type,
+        &*p_callback_data,
+    ));
+
+    vk::FALSE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validation_error(id: i32, name: &str) -> DebugMessage {
+        DebugMessage {
+            severity: DebugSeverity::Error,
+            message_type: DebugUtilsMessageTypeFlagsEXT::VALIDATION.into(),
+            message_id_number: id,
+            message_id_name: name.to_owned(),
+            message: "error".to_owned(),
+            objects: vec![],
+            queue_labels: vec![],
+            command_buffer_labels: vec![],
+        }
+    }
+
+    #[test]
+    fn pending_error_is_returned_once() {
+        let validation = ValidationDescriptor::new().on_error(ValidationAction::Error);
+        let state = DebugState::new(Box::new(LogSink), &validation);
+        state.report(validation_error(1, "VUID-1"));
+        assert!(matches!(state.take_pending(), Err(NxError::Validation(_))));
+        assert!(state.take_pending().is_ok());
+    }
+
+    #[test]
+    fn suppressed_messages_are_dropped() {
+        let sink = CollectingSink::new();
+        let validation = ValidationDescriptor::new()
+            .on_error(ValidationAction::Error)
+            .suppress(1)
+            .suppress_name("VUID-2");
+        let state = DebugState::new(Box::new(sink.clone()), &validation);
+        state.report(validation_error(1, "VUID-1"));
+        state.report(validation_error(2, "VUID-2"));
+        assert!(state.take_pending().is_ok());
+        assert!(!sink.has_errors());
+
+        state.report(validation_error(3, "VUID-3"));
+        assert_eq!(sink.errors().len(), 1);
+        assert!(state.take_pending().is_err());
+    }
+
+    #[test]
+    fn debug_names_stop_at_nul() {
+        assert_eq!(debug_string("vertices").as_bytes(), b"vertices");
+        assert_eq!(debug_string("vertex\0buffer").as_bytes(), b"vertex");
+        assert_eq!(debug_string("").as_bytes(), b"");
+    }
+
+    #[test]
+    fn debug_handles_carry_the_object_type() {
+        assert_eq!(
+            debug_handle(vk::Buffer::from_raw(7)),
+            (vk::ObjectType::BUFFER, 7)
+        );
+        assert_eq!(
+            debug_handle(vk::DescriptorSetLayout::from_raw(3)).0,
+            vk::ObjectType::DESCRIPTOR_SET_LAYOUT
+        );
+        assert_eq!(
+            debug_handle(vk::ShaderModule::from_raw(5)).0,
+            vk::ObjectType::SHADER_MODULE
+        );
+    }
+}
@@ -1,29 +1,532 @@
 use crate::{
-    CommandPool, CommandPoolDescriptor, CommandRecorder, CommandRecorderDescriptor, Destroy,
-    NxResult, Queue, ResourceUpdateDescriptor,
+    debug_string, CommandPool, CommandPoolDescriptor, CommandRecorder, CommandRecorderDescriptor,
+    DebugObject, DebugState, Destroy, DeviceLoss, DeviceUsers, Feature, FeatureSet, Garbage,
+    GarbageCollector, HostCallbacks, HostLock, InstanceOwner, LiveObjects, MemoryAllocator,
+    NxError, NxResult, Queue, QueueFamilyIndices, ResourceUpdateDescriptor, Version,
 };
-use ash::vk::{DescriptorBufferInfo, WriteDescriptorSet};
+use ash::extensions::ext::DebugUtils;
+use ash::vk::{
+    self, AllocationCallbacks, DebugUtilsObjectNameInfoEXT, DescriptorBufferInfo, Handle,
+    WriteDescriptorSet,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[doc(hidden)]
 pub(crate) enum DeviceFeature {
     Swapchain,
 }
 
+impl DeviceFeature {
+    pub(crate) fn name(&self) -> String {
+        match self {
+            DeviceFeature::Swapchain => ash::extensions::khr::Swapchain::name()
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+/// Indicates what a queue is used for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum QueueRole {
+    Graphics,
+    Compute,
+    Transfer,
+    Present,
+}
+
+/// Stores the queues requested from a single queue family.
+///
+/// # Example
+/// ```ignore
+/// // Two compute queues, the second one with a lower priority.
+/// let compute = QueueFamilyDescriptor::new(index)
+///     .priorities(&[1.0, 0.5])
+///     .role(QueueRole::Compute, 0);
+/// ```
+#[derive(Clone)]
+pub struct QueueFamilyDescriptor {
+    pub(crate) family_index: usize,
+    pub(crate) priorities: Vec<f32>,
+    pub(crate) roles: Vec<(QueueRole, usize)>,
+}
+
+impl QueueFamilyDescriptor {
+    /// Initializes a new descriptor requesting a single queue with priority 1.0.
+    pub fn new(family_index: usize) -> Self {
+        Self {
+            family_index,
+            priorities: vec![1.0],
+            roles: vec![],
+        }
+    }
+
+    /// Specifies the number of queues. Every queue has priority 1.0.
+    pub fn queue_count(mut self, count: usize) -> Self {
+        self.priorities = vec![1.0; count];
+        self
+    }
+
+    /// Specifies the priority of each queue. The number of queues is the length of the slice.
+    /// Priorities are clamped to the range 0.0 to 1.0.
+    pub fn priorities(mut self, priorities: &[f32]) -> Self {
+        self.priorities = priorities.iter().map(|x| x.clamp(0.0, 1.0)).collect();
+        self
+    }
+
+    /// Assigns a role to the queue at `queue_index`. See `Device::queue_by_role`.
+    pub fn role(mut self, role: QueueRole, queue_index: usize) -> Self {
+        self.roles.retain(|x| x.0 != role);
+        self.roles.push((role, queue_index));
+        self
+    }
+}
+
+/// Stores information needed to create a Device.
+///
+/// # Example
+/// ```ignore
+/// let descriptor = DeviceDescriptor::new()
+///     .queue_family_index(index)
+///     .feature(Feature::FillModeNonSolid)
+///     .optional_feature(Feature::SamplerAnisotropy);
+/// let device = connecter.request_device(&instance, &descriptor)?;
+/// ```
+pub struct DeviceDescriptor {
+    pub(crate) queue_family_index: usize,
+    pub(crate) queue_families: Vec<QueueFamilyDescriptor>,
+    pub(crate) features: FeatureSet,
+    pub(crate) optional_features: FeatureSet,
+    pub(crate) extensions: Vec<String>,
+}
+
+impl DeviceDescriptor {
+    /// Initializes a new descriptor with default values.
+    pub fn new() -> Self {
+        Self {
+            queue_family_index: 0,
+            queue_families: vec![],
+            features: FeatureSet::new(),
+            optional_features: FeatureSet::new(),
+            extensions: vec![],
+        }
+    }
+
+    /// Specifies the queue family of the queue created with the device.
+    /// Ignored if any family is requested with `queue_family`.
+    pub fn queue_family_index(mut self, queue_family_index: usize) -> Self {
+        self.queue_family_index = queue_family_index;
+        self
+    }
+
+    /// Requests queues from a queue family.
+    /// A descriptor for a family that was already requested replaces the previous one.
+    pub fn queue_family(mut self, descriptor: QueueFamilyDescriptor) -> Self {
+        self.queue_families
+            .retain(|x| x.family_index != descriptor.family_index);
+        self.queue_families.push(descriptor);
+        self
+    }
+
+    /// Requests one queue from each family of `indices` and assigns the matching roles.
+    /// Roles sharing a family share its queue.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (connecter, indices) = instance.select_connecter(&selector)?;
+    /// let descriptor = DeviceDescriptor::new().queue_family_indices(&indices);
+    /// let device = connecter.request_device(&instance, &descriptor)?;
+    /// let transfer = device.queue_by_role(QueueRole::Transfer).unwrap();
+    /// ```
+    pub fn queue_family_indices(mut self, indices: &QueueFamilyIndices) -> Self {
+        let roles = [
+            (QueueRole::Graphics, indices.graphics()),
+            (QueueRole::Compute, indices.compute()),
+            (QueueRole::Transfer, indices.transfer()),
+            (QueueRole::Present, indices.present()),
+        ];
+        for (role, family) in roles {
+            let Some(family) = family else { continue };
+            match self
+                .queue_families
+                .iter_mut()
+                .find(|x| x.family_index == family)
+            {
+                Some(x) => x.roles.push((role, 0)),
+                None => self
+                    .queue_families
+                    .push(QueueFamilyDescriptor::new(family).role(role, 0)),
+            }
+        }
+        self
+    }
+
+    /// Requires a feature. Device creation fails if it is not supported.
+    pub fn feature(mut self, feature: Feature) -> Self {
+        self.features.insert(feature);
+        self
+    }
+
+    /// Requires every feature of the slice.
+    pub fn features(mut self, features: &[Feature]) -> Self {
+        for feature in features {
+            self.features.insert(*feature);
+        }
+        self
+    }
+
+    /// Enables a feature if it is supported.
+    /// Whether it was enabled can be checked with `Device::enabled_features`.
+    pub fn optional_feature(mut self, feature: Feature) -> Self {
+        self.optional_features.insert(feature);
+        self
+    }
+
+    /// Enables a device extension by name, such as `VK_KHR_synchronization2`.
+    /// Device creation fails with `NxError::ExtensionNotPresent` if it is not available.
+    /// Available extensions can be listed with `DeviceConnecter::available_extensions`.
+    ///
+    /// `VK_KHR_swapchain` is enabled by `InstanceFeature::use_surface` and does not need to be requested.
+    pub fn extension(mut self, name: &str) -> Self {
+        if !self.extensions.iter().any(|x| x == name) {
+            self.extensions.push(name.to_owned());
+        }
+        self
+    }
+}
+
+impl Default for DeviceDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Queues created with a device, and the roles assigned to them.
+#[doc(hidden)]
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct QueueMap {
+    counts: HashMap<usize, usize>,
+    roles: HashMap<QueueRole, (usize, usize)>,
+    /// `minImageTransferGranularity` of each family, recorded when the device is created.
+    granularities: HashMap<usize, vk::Extent3D>,
+}
+
+impl QueueMap {
+    /// Collects the queues requested by a descriptor, and returns the families to create.
+    /// Fails if a family requests no queue or a role refers to a queue that is not requested.
+    #[doc(hidden)]
+    pub(crate) fn resolve(
+        descriptor: &DeviceDescriptor,
+    ) -> NxResult<(Self, Vec<QueueFamilyDescriptor>)> {
+        let families = match descriptor.queue_families.is_empty() {
+            true => vec![QueueFamilyDescriptor::new(descriptor.queue_family_index)],
+            false => descriptor.queue_families.clone(),
+        };
+        let mut map = Self::default();
+        for family in &families {
+            if family.priorities.is_empty() {
+                return Err(NxError::QueueNotAvailable {
+                    family: family.family_index,
+                    index: 0,
+                });
+            }
+            for &(role, index) in &family.roles {
+                if index >= family.priorities.len() {
+                    return Err(NxError::QueueNotAvailable {
+                        family: family.family_index,
+                        index,
+                    });
+                }
+                map.roles.insert(role, (family.family_index, index));
+            }
+            map.counts
+                .insert(family.family_index, family.priorities.len());
+        }
+        Ok((map, families))
+    }
+
+    /// Records the image transfer granularity of the requested families.
+    #[doc(hidden)]
+    pub(crate) fn record_granularities(&mut self, families: &[vk::QueueFamilyProperties]) {
+        for &family in self.counts.keys() {
+            if let Some(x) = families.get(family) {
+                self.granularities
+                    .insert(family, x.min_image_transfer_granularity);
+            }
+        }
+    }
+}
+
+/// Destroys the `ash::Device` once every clone of a `Device` and every object created from it is dropped.
+#[doc(hidden)]
+pub(crate) struct DeviceOwner {
+    device: ash::Device,
+    host: Option<Arc<HostCallbacks>>,
+    garbage: GarbageCollector,
+    allocator: MemoryAllocator,
+    /// Released after the device is destroyed, so the instance outlives it.
+    _instance: Arc<InstanceOwner>,
+}
+
+impl Drop for DeviceOwner {
+    fn drop(&mut self) {
+        let callbacks = self.host.as_ref().map(|x| x.raw());
+        unsafe {
+            // Parked objects may still be in use, and a lost device is idle anyway.
+            let _ = self.device.device_wait_idle();
+            self.garbage.flush(&self.device, callbacks, &self.allocator);
+            self.allocator.flush(&self.device, callbacks);
+            self.device.destroy_device(callbacks);
+        }
+    }
+}
+
+/// Keeps the device of an object alive, and ensures the object is destroyed only once.
+#[doc(hidden)]
+pub(crate) struct DeviceChild {
+    device: Device,
+    object_type: vk::ObjectType,
+    handle: u64,
+    destroyed: AtomicBool,
+}
+
+impl DeviceChild {
+    /// Registers the object, which is reported if still alive when the user drops the device.
+    pub(crate) fn new<H: Handle>(device: &Device, handle: H, label: Option<&str>) -> Self {
+        let handle = handle.as_raw();
+        device.live.insert(H::TYPE, handle, label);
+        Self {
+            device: device.uncounted(),
+            object_type: H::TYPE,
+            handle,
+            destroyed: AtomicBool::new(false),
+        }
+    }
+
+    /// Does not register the object, for objects that belong to another one such as the memory of a buffer.
+    pub(crate) fn untracked(device: &Device) -> Self {
+        Self {
+            device: device.uncounted(),
+            object_type: vk::ObjectType::UNKNOWN,
+            handle: 0,
+            destroyed: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the device the first time it is called, and `None` after the object was destroyed.
+    pub(crate) fn release(&self) -> Option<&Device> {
+        match self.destroyed.swap(true, Ordering::AcqRel) {
+            true => None,
+            false => {
+                self.device.live.remove(self.object_type, self.handle);
+                Some(&self.device)
+            }
+        }
+    }
+
+    /// Destroys the object immediately, unless it was already destroyed.
+    pub(crate) fn destroy(&self, garbage: Garbage) {
+        if let Some(device) = self.release() {
+            garbage.destroy(
+                &device.device,
+                device.allocation_callbacks(),
+                device.allocator(),
+            );
+        }
+    }
+
+    /// Destroys the object once the work submitted so far has completed, unless it was already destroyed.
+    pub(crate) fn defer(&self, garbage: Garbage) {
+        if let Some(device) = self.release() {
+            device.owner.garbage.defer(device, garbage);
+        }
+    }
+}
+
+/// Connection to a physical device.
+///
+/// Clones share the same device, which is destroyed once every clone
+/// and every object created from it are dropped.
+///
+/// In debug builds, objects created from the device that are still alive when the last clone is dropped
+/// are reported as warnings to the `DebugMessageSink`, with the backtrace of their creation if `RUST_BACKTRACE` is set.
 #[derive(Clone)]
 pub struct Device {
     #[doc(hidden)]
     pub(crate) device: ash::Device,
+    owner: Arc<DeviceOwner>,
+    #[doc(hidden)]
+    pub(crate) debug_state: Arc<DebugState>,
+    api_version: Version,
+    #[doc(hidden)]
+    pub(crate) host: Option<Arc<HostCallbacks>>,
+    debug_utils: Option<DebugUtils>,
+    features: Arc<FeatureSet>,
+    queues: Arc<QueueMap>,
+    /// Shared by every `Queue` handle of the same queue.
+    queue_locks: Arc<HashMap<(usize, usize), HostLock>>,
+    #[doc(hidden)]
+    pub(crate) loss: Arc<DeviceLoss>,
+    live: Arc<LiveObjects>,
+    /// `None` for the handles held by objects, which do not delay the leak report.
+    _users: Option<Arc<DeviceUsers>>,
 }
 
 impl Device {
     #[doc(hidden)]
-    pub(crate) fn from(device: ash::Device) -> Self {
-        Self { device }
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn from(
+        device: ash::Device,
+        instance: Arc<InstanceOwner>,
+        debug_state: Arc<DebugState>,
+        api_version: Version,
+        host: Option<Arc<HostCallbacks>>,
+        debug_utils: Option<DebugUtils>,
+        features: FeatureSet,
+        queues: QueueMap,
+        loss: DeviceLoss,
+        allocator: MemoryAllocator,
+    ) -> Self {
+        let live = Arc::new(LiveObjects::new());
+        let queue_locks = queues
+            .counts
+            .iter()
+            .flat_map(|(&family, &count)| (0..count).map(move |index| (family, index)))
+            .map(|x| (x, HostLock::default()))
+            .collect();
+        Self {
+            _users: Some(Arc::new(DeviceUsers {
+                live: live.clone(),
+                debug_state: debug_state.clone(),
+            })),
+            live,
+            owner: Arc::new(DeviceOwner {
+                device: device.clone(),
+                host: host.clone(),
+                garbage: GarbageCollector::new(),
+                allocator,
+                _instance: instance,
+            }),
+            device,
+            debug_state,
+            api_version,
+            host,
+            debug_utils,
+            features: Arc::new(features),
+            queues: Arc::new(queues),
+            queue_locks: Arc::new(queue_locks),
+            loss: Arc::new(loss),
+        }
     }
 
-    /// Get the queue corresponding to queue_family_index.
+    /// Clone held by an object created from the device.
+    #[doc(hidden)]
+    pub(crate) fn uncounted(&self) -> Self {
+        Self {
+            _users: None,
+            ..self.clone()
+        }
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn allocation_callbacks(&self) -> Option<&AllocationCallbacks> {
+        self.host.as_ref().map(|x| x.raw())
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn debug_utils(&self) -> Option<&DebugUtils> {
+        self.debug_utils.as_ref()
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn set_object_name(
+        &self,
+        object_type: vk::ObjectType,
+        handle: u64,
+        name: &str,
+    ) -> NxResult<()> {
+        self.live.rename(object_type, handle, name);
+        let debug_utils = match &self.debug_utils {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let name = debug_string(name);
+        let name_info = DebugUtilsObjectNameInfoEXT::builder()
+            .object_type(object_type)
+            .object_handle(handle)
+            .object_name(&name)
+            .build();
+        match unsafe { debug_utils.set_debug_utils_object_name(self.device.handle(), &name_info) } {
+            Ok(_) => Ok(()),
+            Err(e) => Err(self.error("vkSetDebugUtilsObjectNameEXT", e)),
+        }
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn garbage(&self) -> &GarbageCollector {
+        &self.owner.garbage
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn allocator(&self) -> &MemoryAllocator {
+        &self.owner.allocator
+    }
+
+    /// Converts the result of the Vulkan command `call` on this device.
+    #[doc(hidden)]
+    pub(crate) fn error(&self, call: &'static str, result: vk::Result) -> NxError {
+        self.loss.error(call, result)
+    }
+
+    /// Applies the label of a descriptor to a newly created object.
+    /// Naming is only a debugging aid, so failures are logged instead of failing the creation.
+    #[doc(hidden)]
+    pub(crate) fn label<H: Handle>(&self, handle: H, label: Option<&str>) {
+        if let Some(label) = label {
+            if let Err(e) = self.set_object_name(H::TYPE, handle.as_raw(), label) {
+                log::warn!("Failed to set the name of {:?}: {}", H::TYPE, e);
+            }
+        }
+    }
+
+    /// Get the Vulkan version usable with this device.
+    /// This is the lower of the instance version and the version supported by the physical device.
+    ///
     /// # Example
+    /// ```ignore
+    /// if device.api_version() >= Version::V1_3 {
+    ///     // Use Vulkan 1.3 functionality.
+    /// }
     /// ```
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Check to see if the device was lost.
+    /// Once lost, every submission fails with `NxError::DeviceLost` and the device must be recreated.
+    pub fn is_lost(&self) -> bool {
+        self.loss.is_lost()
+    }
+
+    /// Get the features enabled when the device was created.
+    pub fn enabled_features(&self) -> &FeatureSet {
+        &self.features
+    }
+
+    /// Returns the first validation error reported since the last fallible call.
+    /// Does nothing unless `ValidationDescriptor::on_error` was set to `Panic` or `Error`.
+    ///
+    /// Every fallible call that takes a device checks this first,
+    /// so call it at the end of a test to catch errors from the last commands.
+    pub fn check_validation(&self) -> NxResult<()> {
+        self.debug_state.take_pending()
+    }
+
+    /// Get the queue corresponding to queue_family_index.
+    /// # Example
+    /// ```ignore
     /// /// Appropriate instance.
     /// let instance = ..;
     /// // Index of the appropriate queue family. This must be obtained manually.
@@ -34,20 +537,113 @@ impl Device {
     /// let queue = device.get_queue(index);
     ///```
     pub fn get_queue(&self, queue_family_index: usize) -> Queue {
-        Queue(unsafe { self.device.get_device_queue(queue_family_index as u32, 0) })
+        Queue {
+            queue: unsafe { self.device.get_device_queue(queue_family_index as u32, 0) },
+            lock: self.queue_lock(queue_family_index, 0),
+        }
+    }
+
+    /// Get the queue at `queue_index` of a queue family.
+    /// Returns `None` if the queue was not requested when the device was created.
+    pub fn queue(&self, queue_family_index: usize, queue_index: usize) -> Option<Queue> {
+        if queue_index >= self.queue_count(queue_family_index) {
+            return None;
+        }
+        Some(Queue {
+            queue: unsafe {
+                self.device
+                    .get_device_queue(queue_family_index as u32, queue_index as u32)
+            },
+            lock: self.queue_lock(queue_family_index, queue_index),
+        })
+    }
+
+    #[doc(hidden)]
+    fn queue_lock(&self, family: usize, index: usize) -> HostLock {
+        // A queue that was not requested is invalid to use, so its lock does not need to be shared.
+        self.queue_locks
+            .get(&(family, index))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Get the queue assigned to a role with `QueueFamilyDescriptor::role`.
+    pub fn queue_by_role(&self, role: QueueRole) -> Option<Queue> {
+        let (family, index) = *self.queues.roles.get(&role)?;
+        self.queue(family, index)
+    }
+
+    /// Get the queue family of the queue assigned to a role.
+    /// Use this for `CommandPoolDescriptor::queue_family_index`.
+    pub fn queue_family_by_role(&self, role: QueueRole) -> Option<usize> {
+        self.queues.roles.get(&role).map(|x| x.0)
+    }
+
+    /// Get the number of queues created in a queue family.
+    pub fn queue_count(&self, queue_family_index: usize) -> usize {
+        self.queues
+            .counts
+            .get(&queue_family_index)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Granularity of image copies on the queues of a family, or `None` if the family was not requested.
+    #[doc(hidden)]
+    pub(crate) fn image_transfer_granularity(
+        &self,
+        queue_family_index: usize,
+    ) -> Option<vk::Extent3D> {
+        self.queues.granularities.get(&queue_family_index).copied()
+    }
+
+    /// Destroys the objects dropped while the GPU could still use them,
+    /// once the submissions made before they were dropped have completed.
+    ///
+    /// Call this once per frame. Objects still waiting are destroyed with the device.
+    ///
+    /// # Example
+    /// ```ignore
+    /// loop {
+    ///     device.collect_garbage()?;
+    ///     // Record and submit the frame.
+    /// }
+    /// ```
+    pub fn collect_garbage(&self) -> NxResult<()> {
+        self.check_validation()?;
+        self.owner.garbage.collect(self)
+    }
+
+    /// Get the objects created from this device that are still alive, in the order they were created.
+    /// Always empty in release builds.
+    pub fn live_objects(&self) -> Vec<DebugObject> {
+        self.live.objects()
+    }
+
+    /// Get the number of dropped objects waiting for the GPU before being destroyed.
+    pub fn pending_garbage(&self) -> usize {
+        self.owner.garbage.pending()
+    }
+
+    /// Get the number of device memory allocations held for buffers and images.
+    /// Resources are carved out of shared blocks, so this stays far below `maxMemoryAllocationCount`.
+    pub fn memory_blocks(&self) -> usize {
+        self.owner.allocator.block_count()
     }
 
     /// Create a command pool.
     pub fn create_command_pool(&self, descriptor: &CommandPoolDescriptor) -> NxResult<CommandPool> {
-        CommandPool::create(&self.device, descriptor)
+        self.check_validation()?;
+        CommandPool::create(self, descriptor)
     }
 
     /// Allocate command recorder.
     pub fn allocate_command_recorder(
         &self,
-        pool: CommandPool,
+        pool: &CommandPool,
         descriptor: &CommandRecorderDescriptor,
     ) -> NxResult<Vec<CommandRecorder>> {
+        self.check_validation()?;
         CommandRecorder::create(self, pool, descriptor)
     }
 
@@ -75,6 +671,9 @@ impl Device {
         }
     }
 
+    /// Destroys an object immediately instead of when it is dropped.
+    /// Unlike dropping, this does not wait for the GPU, so the object must no longer be in use.
+    /// Destroying an object more than once does nothing.
     pub fn destroy<D>(&self, object: &D)
     where
         D: Destroy,
@@ -83,10 +682,26 @@ impl Device {
     }
 }
 
-impl Drop for Device {
-    fn drop(&mut self) {
-        unsafe {
-            self.device.destroy_device(None);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_queue_roles() {
+        let descriptor = DeviceDescriptor::new()
+            .queue_family(QueueFamilyDescriptor::new(0).role(QueueRole::Graphics, 0))
+            .queue_family(
+                QueueFamilyDescriptor::new(2)
+                    .priorities(&[1.0, 2.0])
+                    .role(QueueRole::Compute, 1),
+            );
+        let (map, families) = QueueMap::resolve(&descriptor).unwrap();
+        assert_eq!(families[1].priorities, vec![1.0, 1.0]);
+        assert_eq!(map.roles[&QueueRole::Compute], (2, 1));
+        assert_eq!(map.counts[&2], 2);
+
+        let descriptor = DeviceDescriptor::new()
+            .queue_family(QueueFamilyDescriptor::new(1).role(QueueRole::Transfer, 1));
+        assert!(QueueMap::resolve(&descriptor).is_err());
     }
 }
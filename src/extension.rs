@@ -0,0 +1,100 @@
+use std::ffi::{c_char, CStr};
+
+use ash::Entry;
+
+use crate::{NxError, NxResult, Version};
+
+/// Represents an instance or device extension.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ExtensionProperties {
+    name: String,
+    spec_version: u32,
+}
+
+impl ExtensionProperties {
+    /// Name of the extension, such as `VK_KHR_swapchain`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Version of the extension specification.
+    pub fn spec_version(&self) -> u32 {
+        self.spec_version
+    }
+}
+
+impl From<ash::vk::ExtensionProperties> for ExtensionProperties {
+    fn from(value: ash::vk::ExtensionProperties) -> Self {
+        Self {
+            name: to_string(&value.extension_name),
+            spec_version: value.spec_version,
+        }
+    }
+}
+
+/// Represents an instance layer.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct LayerProperties {
+    name: String,
+    spec_version: Version,
+    implementation_version: u32,
+    description: String,
+}
+
+impl LayerProperties {
+    /// Name of the layer, such as `VK_LAYER_KHRONOS_validation`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Vulkan version the layer was written against.
+    pub fn spec_version(&self) -> Version {
+        self.spec_version
+    }
+
+    /// Version of the layer implementation.
+    pub fn implementation_version(&self) -> u32 {
+        self.implementation_version
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl From<ash::vk::LayerProperties> for LayerProperties {
+    fn from(value: ash::vk::LayerProperties) -> Self {
+        Self {
+            name: to_string(&value.layer_name),
+            spec_version: Version::from_raw(value.spec_version),
+            implementation_version: value.implementation_version,
+            description: to_string(&value.description),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub(crate) fn to_string(raw: &[c_char]) -> String {
+    unsafe { CStr::from_ptr(raw.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[doc(hidden)]
+pub(crate) fn enumerate_layers(entry: &Entry) -> NxResult<Vec<LayerProperties>> {
+    match entry.enumerate_instance_layer_properties() {
+        Ok(x) => Ok(x.into_iter().map(LayerProperties::from).collect()),
+        Err(e) => Err(NxError::vulkan("vkEnumerateInstanceLayerProperties", e)),
+    }
+}
+
+#[doc(hidden)]
+pub(crate) fn enumerate_extensions(
+    entry: &Entry,
+    layer: Option<&CStr>,
+) -> NxResult<Vec<ExtensionProperties>> {
+    match entry.enumerate_instance_extension_properties(layer) {
+        Ok(x) => Ok(x.into_iter().map(ExtensionProperties::from).collect()),
+        Err(e) => Err(NxError::vulkan("vkEnumerateInstanceExtensionProperties", e)),
+    }
+}
@@ -0,0 +1,324 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use ash::vk;
+
+use crate::extension::to_string;
+use crate::{DeviceConnecter, ExtensionProperties, Instance, NxError, Version};
+
+/// Indicates what went wrong at the address of a `FaultAddress`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FaultAddressKind {
+    /// An invalid read.
+    ReadInvalid,
+    /// An invalid write.
+    WriteInvalid,
+    /// An attempt to execute non-executable memory.
+    ExecuteInvalid,
+    /// Instruction pointer not associated with the fault.
+    InstructionPointerUnknown,
+    /// Instruction pointer associated with an invalid instruction.
+    InstructionPointerInvalid,
+    /// Instruction pointer associated with the fault.
+    InstructionPointerFault,
+    Other,
+}
+
+impl From<vk::DeviceFaultAddressTypeEXT> for FaultAddressKind {
+    fn from(value: vk::DeviceFaultAddressTypeEXT) -> Self {
+        match value {
+            vk::DeviceFaultAddressTypeEXT::READ_INVALID => FaultAddressKind::ReadInvalid,
+            vk::DeviceFaultAddressTypeEXT::WRITE_INVALID => FaultAddressKind::WriteInvalid,
+            vk::DeviceFaultAddressTypeEXT::EXECUTE_INVALID => FaultAddressKind::ExecuteInvalid,
+            vk::DeviceFaultAddressTypeEXT::INSTRUCTION_POINTER_UNKNOWN => {
+                FaultAddressKind::InstructionPointerUnknown
+            }
+            vk::DeviceFaultAddressTypeEXT::INSTRUCTION_POINTER_INVALID => {
+                FaultAddressKind::InstructionPointerInvalid
+            }
+            vk::DeviceFaultAddressTypeEXT::INSTRUCTION_POINTER_FAULT => {
+                FaultAddressKind::InstructionPointerFault
+            }
+            _ => FaultAddressKind::Other,
+        }
+    }
+}
+
+/// GPU virtual address range related to a device fault.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FaultAddress {
+    kind: FaultAddressKind,
+    address: u64,
+    precision: u64,
+}
+
+impl FaultAddress {
+    pub fn kind(&self) -> FaultAddressKind {
+        self.kind
+    }
+
+    /// Reported address. The fault lies within `precision` bytes aligned around it.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    pub fn precision(&self) -> u64 {
+        self.precision
+    }
+}
+
+/// Vendor-specific fault information.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VendorFault {
+    description: String,
+    code: u64,
+    data: u64,
+}
+
+impl VendorFault {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn code(&self) -> u64 {
+        self.code
+    }
+
+    pub fn data(&self) -> u64 {
+        self.data
+    }
+}
+
+/// Information on why a device was lost, reported by `VK_EXT_device_fault`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceFault {
+    description: String,
+    addresses: Vec<FaultAddress>,
+    vendor_faults: Vec<VendorFault>,
+}
+
+impl DeviceFault {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn addresses(&self) -> &[FaultAddress] {
+        &self.addresses
+    }
+
+    pub fn vendor_faults(&self) -> &[VendorFault] {
+        &self.vendor_faults
+    }
+}
+
+/// Check to see if `VK_EXT_device_fault` can be enabled on the physical device.
+#[doc(hidden)]
+pub(crate) fn is_device_fault_supported(
+    instance: &Instance,
+    connecter: DeviceConnecter,
+    available: &[ExtensionProperties],
+) -> bool {
+    // The feature can only be queried and enabled through the Vulkan 1.1 feature chain.
+    if connecter.api_version(instance) < Version::V1_1 {
+        return false;
+    }
+    let name = vk::ExtDeviceFaultFn::name().to_string_lossy();
+    if !available.iter().any(|x| x.name() == name) {
+        return false;
+    }
+    let mut fault = vk::PhysicalDeviceFaultFeaturesEXT::default();
+    let mut features2 = vk::PhysicalDeviceFeatures2::builder()
+        .push_next(&mut fault)
+        .build();
+    unsafe {
+        instance
+            .instance
+            .get_physical_device_features2(connecter.0, &mut features2)
+    };
+    fault.device_fault == vk::TRUE
+}
+
+/// Tracks whether a device was lost.
+#[doc(hidden)]
+pub(crate) struct DeviceLoss {
+    lost: AtomicBool,
+    device: vk::Device,
+    fault_fn: Option<vk::ExtDeviceFaultFn>,
+    fault: Mutex<Option<DeviceFault>>,
+}
+
+impl DeviceLoss {
+    pub(crate) fn new(device: vk::Device, fault_fn: Option<vk::ExtDeviceFaultFn>) -> Self {
+        Self {
+            lost: AtomicBool::new(false),
+            device,
+            fault_fn,
+            fault: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    /// Converts the result of the Vulkan command `call` on the device.
+    /// `VK_ERROR_DEVICE_LOST` marks the device as lost and becomes `NxError::DeviceLost`.
+    pub(crate) fn error(&self, call: &'static str, result: vk::Result) -> NxError {
+        if result != vk::Result::ERROR_DEVICE_LOST {
+            return NxError::vulkan(call, result);
+        }
+        self.lost.store(true, Ordering::Release);
+        // The lock only caches the fault, so a panic while holding it leaves nothing inconsistent.
+        let mut fault = self.fault.lock().unwrap_or_else(|e| e.into_inner());
+        // The fault is only queried once, so every later error reports the same information.
+        if fault.is_none() {
+            *fault = self.query_fault();
+        }
+        NxError::DeviceLost(fault.clone().map(Box::new))
+    }
+
+    fn query_fault(&self) -> Option<DeviceFault> {
+        let fault_fn = self.fault_fn.as_ref()?;
+        let mut counts = vk::DeviceFaultCountsEXT::default();
+        let result = unsafe {
+            (fault_fn.get_device_fault_info_ext)(self.device, &mut counts, std::ptr::null_mut())
+        };
+        if result != vk::Result::SUCCESS {
+            return None;
+        }
+        // Vendor binary data needs its own feature and is not reported.
+        counts.vendor_binary_size = 0;
+        let mut addresses =
+            vec![vk::DeviceFaultAddressInfoEXT::default(); counts.address_info_count as usize];
+        let mut vendor_faults =
+            vec![vk::DeviceFaultVendorInfoEXT::default(); counts.vendor_info_count as usize];
+        let mut info = vk::DeviceFaultInfoEXT {
+            p_address_infos: addresses.as_mut_ptr(),
+            p_vendor_infos: vendor_faults.as_mut_ptr(),
+            ..Default::default()
+        };
+        let result =
+            unsafe { (fault_fn.get_device_fault_info_ext)(self.device, &mut counts, &mut info) };
+        if result != vk::Result::SUCCESS && result != vk::Result::INCOMPLETE {
+            return None;
+        }
+        addresses.truncate(counts.address_info_count as usize);
+        vendor_faults.truncate(counts.vendor_info_count as usize);
+        Some(DeviceFault {
+            description: to_string(&info.description),
+            addresses: addresses
+                .iter()
+                .map(|x| FaultAddress {
+                    kind: x.address_type.into(),
+                    address: x.reported_address,
+                    precision: x.address_precision,
+                })
+                .collect(),
+            vendor_faults: vendor_faults
+                .iter()
+                .map(|x| VendorFault {
+                    description: to_string(&x.description),
+                    code: x.vendor_fault_code,
+                    data: x.vendor_fault_data,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_address_types() {
+        let kinds = [
+            (
+                vk::DeviceFaultAddressTypeEXT::READ_INVALID,
+                FaultAddressKind::ReadInvalid,
+            ),
+            (
+                vk::DeviceFaultAddressTypeEXT::WRITE_INVALID,
+                FaultAddressKind::WriteInvalid,
+            ),
+            (
+                vk::DeviceFaultAddressTypeEXT::EXECUTE_INVALID,
+                FaultAddressKind::ExecuteInvalid,
+            ),
+            (
+                vk::DeviceFaultAddressTypeEXT::INSTRUCTION_POINTER_UNKNOWN,
+                FaultAddressKind::InstructionPointerUnknown,
+            ),
+            (
+                vk::DeviceFaultAddressTypeEXT::INSTRUCTION_POINTER_INVALID,
+                FaultAddressKind::InstructionPointerInvalid,
+            ),
+            (
+                vk::DeviceFaultAddressTypeEXT::INSTRUCTION_POINTER_FAULT,
+                FaultAddressKind::InstructionPointerFault,
+            ),
+            (vk::DeviceFaultAddressTypeEXT::NONE, FaultAddressKind::Other),
+        ];
+        for (raw, kind) in kinds {
+            assert_eq!(FaultAddressKind::from(raw), kind);
+        }
+    }
+
+    fn write_string(dst: &mut [std::ffi::c_char], src: &str) {
+        for (dst, src) in dst.iter_mut().zip(src.bytes()) {
+            *dst = src as _;
+        }
+    }
+
+    unsafe extern "system" fn fault_info(
+        _: vk::Device,
+        counts: *mut vk::DeviceFaultCountsEXT,
+        info: *mut vk::DeviceFaultInfoEXT,
+    ) -> vk::Result {
+        let counts = &mut *counts;
+        counts.address_info_count = 1;
+        counts.vendor_info_count = 0;
+        if let Some(info) = info.as_mut() {
+            write_string(&mut info.description, "page fault");
+            *info.p_address_infos = vk::DeviceFaultAddressInfoEXT {
+                address_type: vk::DeviceFaultAddressTypeEXT::WRITE_INVALID,
+                reported_address: 0x1000,
+                address_precision: 64,
+            };
+        }
+        vk::Result::SUCCESS
+    }
+
+    #[test]
+    fn device_lost_reports_the_fault() {
+        let loss = DeviceLoss::new(vk::Device::null(), None);
+        let error = loss.error("vkQueueSubmit", vk::Result::ERROR_OUT_OF_DEVICE_MEMORY);
+        assert!(matches!(error, NxError::Vulkan { .. }));
+        assert!(!loss.is_lost());
+        let error = loss.error("vkQueueSubmit", vk::Result::ERROR_DEVICE_LOST);
+        assert!(loss.is_lost());
+        assert_eq!(error.to_string(), "Device lost");
+
+        let fault_fn = vk::ExtDeviceFaultFn {
+            get_device_fault_info_ext: fault_info,
+        };
+        let loss = DeviceLoss::new(vk::Device::null(), Some(fault_fn));
+        let NxError::DeviceLost(Some(fault)) =
+            loss.error("vkQueueSubmit", vk::Result::ERROR_DEVICE_LOST)
+        else {
+            panic!("the fault is not reported");
+        };
+        assert_eq!(fault.description(), "page fault");
+        assert_eq!(
+            fault.addresses(),
+            [FaultAddress {
+                kind: FaultAddressKind::WriteInvalid,
+                address: 0x1000,
+                precision: 64,
+            }]
+        );
+        assert!(fault.vendor_faults().is_empty());
+        let error = NxError::DeviceLost(Some(fault));
+        assert_eq!(error.to_string(), "Device lost: page fault");
+    }
+}
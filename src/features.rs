@@ -0,0 +1,341 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use ash::vk;
+
+use crate::{DeviceConnecter, Instance, Version};
+
+/// Generates `Feature` and the conversions between `FeatureSet` and the Vulkan feature structs.
+macro_rules! features {
+    ($($group:ident: $raw:ident, $version:ident { $($variant:ident => $field:ident,)* })*) => {
+        /// Represents an optional feature of a physical device.
+        ///
+        /// Each variant corresponds to a member of `VkPhysicalDeviceFeatures`
+        /// or of the Vulkan 1.1, 1.2 and 1.3 feature structs.
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        pub enum Feature {
+            $($($variant,)*)*
+        }
+
+        impl Feature {
+            /// Name of the member in the Vulkan feature struct, such as `sampler_anisotropy`.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($(Feature::$variant => stringify!($field),)*)*
+                }
+            }
+
+            /// Lowest Vulkan version with which this library can query and enable the feature.
+            /// Features of the Vulkan 1.1 struct require Vulkan 1.2, since that struct was added in Vulkan 1.2.
+            pub fn required_version(&self) -> Version {
+                match self {
+                    $($(Feature::$variant => Version::$version,)*)*
+                }
+            }
+        }
+
+        #[doc(hidden)]
+        #[derive(Default)]
+        pub(crate) struct RawFeatures {
+            $(pub(crate) $group: vk::$raw,)*
+        }
+
+        impl RawFeatures {
+            pub(crate) fn to_set(&self) -> FeatureSet {
+                let mut set = FeatureSet::new();
+                $($(
+                    if self.$group.$field == vk::TRUE {
+                        set.insert(Feature::$variant);
+                    }
+                )*)*
+                set
+            }
+
+            pub(crate) fn from_set(set: &FeatureSet) -> Self {
+                let mut raw = Self::default();
+                $($(
+                    if set.contains(Feature::$variant) {
+                        raw.$group.$field = vk::TRUE;
+                    }
+                )*)*
+                raw
+            }
+        }
+    };
+}
+
+features! {
+    core: PhysicalDeviceFeatures, V1_0 {
+        RobustBufferAccess => robust_buffer_access,
+        FullDrawIndexUint32 => full_draw_index_uint32,
+        ImageCubeArray => image_cube_array,
+        IndependentBlend => independent_blend,
+        GeometryShader => geometry_shader,
+        TessellationShader => tessellation_shader,
+        SampleRateShading => sample_rate_shading,
+        DualSrcBlend => dual_src_blend,
+        LogicOp => logic_op,
+        MultiDrawIndirect => multi_draw_indirect,
+        DrawIndirectFirstInstance => draw_indirect_first_instance,
+        DepthClamp => depth_clamp,
+        DepthBiasClamp => depth_bias_clamp,
+        FillModeNonSolid => fill_mode_non_solid,
+        DepthBounds => depth_bounds,
+        WideLines => wide_lines,
+        LargePoints => large_points,
+        AlphaToOne => alpha_to_one,
+        MultiViewport => multi_viewport,
+        SamplerAnisotropy => sampler_anisotropy,
+        TextureCompressionEtc2 => texture_compression_etc2,
+        TextureCompressionAstcLdr => texture_compression_astc_ldr,
+        TextureCompressionBc => texture_compression_bc,
+        OcclusionQueryPrecise => occlusion_query_precise,
+        PipelineStatisticsQuery => pipeline_statistics_query,
+        VertexPipelineStoresAndAtomics => vertex_pipeline_stores_and_atomics,
+        FragmentStoresAndAtomics => fragment_stores_and_atomics,
+        ShaderTessellationAndGeometryPointSize => shader_tessellation_and_geometry_point_size,
+        ShaderImageGatherExtended => shader_image_gather_extended,
+        ShaderStorageImageExtendedFormats => shader_storage_image_extended_formats,
+        ShaderStorageImageMultisample => shader_storage_image_multisample,
+        ShaderStorageImageReadWithoutFormat => shader_storage_image_read_without_format,
+        ShaderStorageImageWriteWithoutFormat => shader_storage_image_write_without_format,
+        ShaderUniformBufferArrayDynamicIndexing => shader_uniform_buffer_array_dynamic_indexing,
+        ShaderSampledImageArrayDynamicIndexing => shader_sampled_image_array_dynamic_indexing,
+        ShaderStorageBufferArrayDynamicIndexing => shader_storage_buffer_array_dynamic_indexing,
+        ShaderStorageImageArrayDynamicIndexing => shader_storage_image_array_dynamic_indexing,
+        ShaderClipDistance => shader_clip_distance,
+        ShaderCullDistance => shader_cull_distance,
+        ShaderFloat64 => shader_float64,
+        ShaderInt64 => shader_int64,
+        ShaderInt16 => shader_int16,
+        ShaderResourceResidency => shader_resource_residency,
+        ShaderResourceMinLod => shader_resource_min_lod,
+        SparseBinding => sparse_binding,
+        SparseResidencyBuffer => sparse_residency_buffer,
+        SparseResidencyImage2D => sparse_residency_image2_d,
+        SparseResidencyImage3D => sparse_residency_image3_d,
+        SparseResidency2Samples => sparse_residency2_samples,
+        SparseResidency4Samples => sparse_residency4_samples,
+        SparseResidency8Samples => sparse_residency8_samples,
+        SparseResidency16Samples => sparse_residency16_samples,
+        SparseResidencyAliased => sparse_residency_aliased,
+        VariableMultisampleRate => variable_multisample_rate,
+        InheritedQueries => inherited_queries,
+    }
+    vulkan11: PhysicalDeviceVulkan11Features, V1_2 {
+        StorageBuffer16BitAccess => storage_buffer16_bit_access,
+        UniformAndStorageBuffer16BitAccess => uniform_and_storage_buffer16_bit_access,
+        StoragePushConstant16 => storage_push_constant16,
+        StorageInputOutput16 => storage_input_output16,
+        Multiview => multiview,
+        MultiviewGeometryShader => multiview_geometry_shader,
+        MultiviewTessellationShader => multiview_tessellation_shader,
+        VariablePointersStorageBuffer => variable_pointers_storage_buffer,
+        VariablePointers => variable_pointers,
+        ProtectedMemory => protected_memory,
+        SamplerYcbcrConversion => sampler_ycbcr_conversion,
+        ShaderDrawParameters => shader_draw_parameters,
+    }
+    vulkan12: PhysicalDeviceVulkan12Features, V1_2 {
+        SamplerMirrorClampToEdge => sampler_mirror_clamp_to_edge,
+        DrawIndirectCount => draw_indirect_count,
+        StorageBuffer8BitAccess => storage_buffer8_bit_access,
+        UniformAndStorageBuffer8BitAccess => uniform_and_storage_buffer8_bit_access,
+        StoragePushConstant8 => storage_push_constant8,
+        ShaderBufferInt64Atomics => shader_buffer_int64_atomics,
+        ShaderSharedInt64Atomics => shader_shared_int64_atomics,
+        ShaderFloat16 => shader_float16,
+        ShaderInt8 => shader_int8,
+        DescriptorIndexing => descriptor_indexing,
+        ShaderInputAttachmentArrayDynamicIndexing => shader_input_attachment_array_dynamic_indexing,
+        ShaderUniformTexelBufferArrayDynamicIndexing => shader_uniform_texel_buffer_array_dynamic_indexing,
+        ShaderStorageTexelBufferArrayDynamicIndexing => shader_storage_texel_buffer_array_dynamic_indexing,
+        ShaderUniformBufferArrayNonUniformIndexing => shader_uniform_buffer_array_non_uniform_indexing,
+        ShaderSampledImageArrayNonUniformIndexing => shader_sampled_image_array_non_uniform_indexing,
+        ShaderStorageBufferArrayNonUniformIndexing => shader_storage_buffer_array_non_uniform_indexing,
+        ShaderStorageImageArrayNonUniformIndexing => shader_storage_image_array_non_uniform_indexing,
+        ShaderInputAttachmentArrayNonUniformIndexing => shader_input_attachment_array_non_uniform_indexing,
+        ShaderUniformTexelBufferArrayNonUniformIndexing => shader_uniform_texel_buffer_array_non_uniform_indexing,
+        ShaderStorageTexelBufferArrayNonUniformIndexing => shader_storage_texel_buffer_array_non_uniform_indexing,
+        DescriptorBindingUniformBufferUpdateAfterBind => descriptor_binding_uniform_buffer_update_after_bind,
+        DescriptorBindingSampledImageUpdateAfterBind => descriptor_binding_sampled_image_update_after_bind,
+        DescriptorBindingStorageImageUpdateAfterBind => descriptor_binding_storage_image_update_after_bind,
+        DescriptorBindingStorageBufferUpdateAfterBind => descriptor_binding_storage_buffer_update_after_bind,
+        DescriptorBindingUniformTexelBufferUpdateAfterBind => descriptor_binding_uniform_texel_buffer_update_after_bind,
+        DescriptorBindingStorageTexelBufferUpdateAfterBind => descriptor_binding_storage_texel_buffer_update_after_bind,
+        DescriptorBindingUpdateUnusedWhilePending => descriptor_binding_update_unused_while_pending,
+        DescriptorBindingPartiallyBound => descriptor_binding_partially_bound,
+        DescriptorBindingVariableDescriptorCount => descriptor_binding_variable_descriptor_count,
+        RuntimeDescriptorArray => runtime_descriptor_array,
+        SamplerFilterMinmax => sampler_filter_minmax,
+        ScalarBlockLayout => scalar_block_layout,
+        ImagelessFramebuffer => imageless_framebuffer,
+        UniformBufferStandardLayout => uniform_buffer_standard_layout,
+        ShaderSubgroupExtendedTypes => shader_subgroup_extended_types,
+        SeparateDepthStencilLayouts => separate_depth_stencil_layouts,
+        HostQueryReset => host_query_reset,
+        TimelineSemaphore => timeline_semaphore,
+        BufferDeviceAddress => buffer_device_address,
+        BufferDeviceAddressCaptureReplay => buffer_device_address_capture_replay,
+        BufferDeviceAddressMultiDevice => buffer_device_address_multi_device,
+        VulkanMemoryModel => vulkan_memory_model,
+        VulkanMemoryModelDeviceScope => vulkan_memory_model_device_scope,
+        VulkanMemoryModelAvailabilityVisibilityChains => vulkan_memory_model_availability_visibility_chains,
+        ShaderOutputViewportIndex => shader_output_viewport_index,
+        ShaderOutputLayer => shader_output_layer,
+        SubgroupBroadcastDynamicId => subgroup_broadcast_dynamic_id,
+    }
+    vulkan13: PhysicalDeviceVulkan13Features, V1_3 {
+        RobustImageAccess => robust_image_access,
+        InlineUniformBlock => inline_uniform_block,
+        DescriptorBindingInlineUniformBlockUpdateAfterBind => descriptor_binding_inline_uniform_block_update_after_bind,
+        PipelineCreationCacheControl => pipeline_creation_cache_control,
+        PrivateData => private_data,
+        ShaderDemoteToHelperInvocation => shader_demote_to_helper_invocation,
+        ShaderTerminateInvocation => shader_terminate_invocation,
+        SubgroupSizeControl => subgroup_size_control,
+        ComputeFullSubgroups => compute_full_subgroups,
+        Synchronization2 => synchronization2,
+        TextureCompressionAstcHdr => texture_compression_astc_hdr,
+        ShaderZeroInitializeWorkgroupMemory => shader_zero_initialize_workgroup_memory,
+        DynamicRendering => dynamic_rendering,
+        ShaderIntegerDotProduct => shader_integer_dot_product,
+        Maintenance4 => maintenance4,
+    }
+
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A set of device features.
+///
+/// # Example
+/// ```ignore
+/// let supported = connecter.features(&instance);
+/// if supported.contains(Feature::SamplerAnisotropy) {
+///     // Enable anisotropic filtering.
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FeatureSet {
+    features: BTreeSet<Feature>,
+}
+
+impl FeatureSet {
+    /// Initializes an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, feature: Feature) {
+        self.features.insert(feature);
+    }
+
+    pub fn remove(&mut self, feature: Feature) {
+        self.features.remove(&feature);
+    }
+
+    pub fn contains(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Feature> + '_ {
+        self.features.iter().copied()
+    }
+
+    /// Returns the first feature of this set that is not in `supported`.
+    pub fn first_missing(&self, supported: &FeatureSet) -> Option<Feature> {
+        self.iter().find(|x| !supported.contains(*x))
+    }
+}
+
+impl FromIterator<Feature> for FeatureSet {
+    fn from_iter<T: IntoIterator<Item = Feature>>(iter: T) -> Self {
+        Self {
+            features: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl From<&[Feature]> for FeatureSet {
+    fn from(value: &[Feature]) -> Self {
+        value.iter().copied().collect()
+    }
+}
+
+impl RawFeatures {
+    /// Query the features supported by a physical device.
+    #[doc(hidden)]
+    pub(crate) fn query(instance: &Instance, connecter: DeviceConnecter) -> Self {
+        let mut raw = Self::default();
+        let version = connecter.api_version(instance);
+        if version < Version::V1_1 {
+            raw.core = unsafe { instance.instance.get_physical_device_features(connecter.0) };
+            return raw;
+        }
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder();
+        if version >= Version::V1_2 {
+            features2 = features2
+                .push_next(&mut raw.vulkan11)
+                .push_next(&mut raw.vulkan12);
+        }
+        if version >= Version::V1_3 {
+            features2 = features2.push_next(&mut raw.vulkan13);
+        }
+        let mut features2 = features2.build();
+        unsafe {
+            instance
+                .instance
+                .get_physical_device_features2(connecter.0, &mut features2)
+        };
+        raw.core = features2.features;
+        raw.unlink();
+        raw
+    }
+
+    /// Clears the `p_next` pointers left by chaining the structs.
+    #[doc(hidden)]
+    pub(crate) fn unlink(&mut self) {
+        self.vulkan11.p_next = std::ptr::null_mut();
+        self.vulkan12.p_next = std::ptr::null_mut();
+        self.vulkan13.p_next = std::ptr::null_mut();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_features_round_trip() {
+        let set: FeatureSet = [
+            Feature::SamplerAnisotropy,
+            Feature::ShaderDrawParameters,
+            Feature::TimelineSemaphore,
+            Feature::DynamicRendering,
+        ]
+        .as_slice()
+        .into();
+        let raw = RawFeatures::from_set(&set);
+        assert_eq!(raw.core.sampler_anisotropy, vk::TRUE);
+        assert_eq!(raw.core.wide_lines, vk::FALSE);
+        assert_eq!(raw.vulkan13.dynamic_rendering, vk::TRUE);
+        assert_eq!(raw.to_set(), set);
+        assert_eq!(
+            set.first_missing(&FeatureSet::new()),
+            Some(Feature::SamplerAnisotropy)
+        );
+    }
+}
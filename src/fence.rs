@@ -1,16 +1,21 @@
 use ash::vk::FenceCreateInfo;
+use std::marker::PhantomData;
 
-use crate::{Destroy, Device, Instance, NxError, NxResult};
+use crate::{Destroy, Device, DeviceChild, Garbage, Instance, NotSync, NxResult};
 
 /// Stores information needed to create a Fence.
 pub struct FenceDescriptor {
     signaled: bool,
+    label: Option<String>,
 }
 
 impl FenceDescriptor {
     /// Initializes a new descriptor with default values.
     pub const fn empty() -> Self {
-        Self { signaled: false }
+        Self {
+            signaled: false,
+            label: None,
+        }
     }
 
     /// Put the fence in signal state.
@@ -18,12 +23,20 @@ impl FenceDescriptor {
         self.signaled = signaled;
         self
     }
+
+    /// Specifies the debug name of the Fence. See `DebugName`.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 /// An object to wait for work.
 /// This is used by the CPU to wait for the GPU to finish its work.
 pub struct Fence {
     pub(crate) fence: ash::vk::Fence,
+    owner: DeviceChild,
+    _not_sync: NotSync,
 }
 
 impl Fence {
@@ -33,44 +46,50 @@ impl Fence {
     /// * `device` - Reference to the appropriate device.
     /// * `descriptor` - Appropriate FenceDescriptor.
     pub fn new(device: &Device, descriptor: &FenceDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let flag = match descriptor.signaled {
             true => ash::vk::FenceCreateFlags::SIGNALED,
             false => ash::vk::FenceCreateFlags::empty(),
         };
         let create_info = FenceCreateInfo::builder().flags(flag).build();
-        let fence = match unsafe { device.device.create_fence(&create_info, None) } {
+        let fence = match unsafe {
+            device
+                .device
+                .create_fence(&create_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkCreateFence", e)),
         };
-        Ok(Self { fence })
+        device.label(fence, descriptor.label.as_deref());
+        Ok(Self {
+            fence,
+            owner: DeviceChild::new(device, fence, descriptor.label.as_deref()),
+            _not_sync: PhantomData,
+        })
     }
 
     /// Wait until the GPU finishes processing.
     /// The time until timeout must be specified.
     pub fn wait(&self, device: &Device, timeout: u64) -> NxResult<()> {
+        device.check_validation()?;
         match unsafe { device.device.wait_for_fences(&[self.fence], true, timeout) } {
             Ok(_) => Ok(()),
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => Err(device.error("vkWaitForFences", e)),
         }
     }
 
+    /// Check whether the fence is signaled, without waiting.
+    pub fn is_signaled(&self, device: &Device) -> NxResult<bool> {
+        unsafe { device.device.get_fence_status(self.fence) }
+            .map_err(|e| device.error("vkGetFenceStatus", e))
+    }
+
     /// Reset fence status.
     pub fn reset(&self, device: &Device) -> NxResult<()> {
+        device.check_validation()?;
         match unsafe { device.device.reset_fences(&[self.fence]) } {
             Ok(_) => Ok(()),
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => Err(device.error("vkResetFences", e)),
         }
     }
 }
@@ -78,9 +97,13 @@ impl Fence {
 impl Destroy for Fence {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_fence(self.fence, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::Fence(self.fence));
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::Fence(self.fence));
     }
 }
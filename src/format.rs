@@ -0,0 +1,169 @@
+use ash::vk;
+
+use crate::{Extent3d, ImageTiling};
+
+/// Indicates an operation that a format may support.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FormatFeature {
+    /// Can be sampled in a shader.
+    SampledImage,
+    /// Can be used as a storage image.
+    StorageImage,
+    ColorAttachment,
+    /// Can be used as a color attachment with blending.
+    ColorAttachmentBlend,
+    DepthStencilAttachment,
+    /// Can be the source of a blit.
+    BlitSrc,
+    /// Can be the destination of a blit.
+    BlitDst,
+    /// Can be sampled with linear filtering.
+    SampledImageFilterLinear,
+    TransferSrc,
+    TransferDst,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    VertexBuffer,
+}
+
+impl From<FormatFeature> for vk::FormatFeatureFlags {
+    fn from(value: FormatFeature) -> Self {
+        match value {
+            FormatFeature::SampledImage => vk::FormatFeatureFlags::SAMPLED_IMAGE,
+            FormatFeature::StorageImage => vk::FormatFeatureFlags::STORAGE_IMAGE,
+            FormatFeature::ColorAttachment => vk::FormatFeatureFlags::COLOR_ATTACHMENT,
+            FormatFeature::ColorAttachmentBlend => vk::FormatFeatureFlags::COLOR_ATTACHMENT_BLEND,
+            FormatFeature::DepthStencilAttachment => {
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+            }
+            FormatFeature::BlitSrc => vk::FormatFeatureFlags::BLIT_SRC,
+            FormatFeature::BlitDst => vk::FormatFeatureFlags::BLIT_DST,
+            FormatFeature::SampledImageFilterLinear => {
+                vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+            }
+            FormatFeature::TransferSrc => vk::FormatFeatureFlags::TRANSFER_SRC,
+            FormatFeature::TransferDst => vk::FormatFeatureFlags::TRANSFER_DST,
+            FormatFeature::UniformTexelBuffer => vk::FormatFeatureFlags::UNIFORM_TEXEL_BUFFER,
+            FormatFeature::StorageTexelBuffer => vk::FormatFeatureFlags::STORAGE_TEXEL_BUFFER,
+            FormatFeature::VertexBuffer => vk::FormatFeatureFlags::VERTEX_BUFFER,
+        }
+    }
+}
+
+/// Set of operations supported by a format.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FormatFeatures(vk::FormatFeatureFlags);
+
+impl FormatFeatures {
+    /// Check to see if the operation is supported.
+    pub fn contains(&self, feature: FormatFeature) -> bool {
+        self.0.contains(feature.into())
+    }
+
+    /// Check to see if every operation of the slice is supported.
+    pub fn contains_all(&self, features: &[FormatFeature]) -> bool {
+        features.iter().all(|x| self.contains(*x))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Operations supported by a format on a physical device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FormatProperties {
+    linear_tiling: FormatFeatures,
+    optimal_tiling: FormatFeatures,
+    buffer: FormatFeatures,
+}
+
+impl FormatProperties {
+    /// Operations supported by images with `ImageTiling::Linear`.
+    pub fn linear_tiling(&self) -> FormatFeatures {
+        self.linear_tiling
+    }
+
+    /// Operations supported by images with `ImageTiling::Optimal`.
+    pub fn optimal_tiling(&self) -> FormatFeatures {
+        self.optimal_tiling
+    }
+
+    /// Operations supported by images with the given tiling.
+    pub fn tiling(&self, tiling: ImageTiling) -> FormatFeatures {
+        match tiling {
+            ImageTiling::Linear => self.linear_tiling,
+            ImageTiling::Optimal => self.optimal_tiling,
+        }
+    }
+
+    /// Operations supported by buffers.
+    pub fn buffer(&self) -> FormatFeatures {
+        self.buffer
+    }
+}
+
+impl From<vk::FormatProperties> for FormatProperties {
+    fn from(value: vk::FormatProperties) -> Self {
+        Self {
+            linear_tiling: FormatFeatures(value.linear_tiling_features),
+            optimal_tiling: FormatFeatures(value.optimal_tiling_features),
+            buffer: FormatFeatures(value.buffer_features),
+        }
+    }
+}
+
+/// Limits of images created with a particular format, dimension, tiling and usage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ImageFormatProperties {
+    max_extent: Extent3d,
+    max_mip_levels: u32,
+    max_array_layers: u32,
+    sample_counts: u32,
+    max_resource_size: u64,
+}
+
+impl ImageFormatProperties {
+    pub fn max_extent(&self) -> Extent3d {
+        self.max_extent
+    }
+
+    pub fn max_mip_levels(&self) -> u32 {
+        self.max_mip_levels
+    }
+
+    pub fn max_array_layers(&self) -> u32 {
+        self.max_array_layers
+    }
+
+    /// Bitmask in which each set bit is a supported sample count.
+    pub fn sample_counts(&self) -> u32 {
+        self.sample_counts
+    }
+
+    /// Check to see if the sample count is supported.
+    pub fn is_sample_count_supported(&self, samples: u32) -> bool {
+        samples.is_power_of_two() && self.sample_counts & samples != 0
+    }
+
+    /// Upper bound of the total size of an image in bytes.
+    pub fn max_resource_size(&self) -> u64 {
+        self.max_resource_size
+    }
+}
+
+impl From<vk::ImageFormatProperties> for ImageFormatProperties {
+    fn from(value: vk::ImageFormatProperties) -> Self {
+        Self {
+            max_extent: Extent3d::new(
+                value.max_extent.width,
+                value.max_extent.height,
+                value.max_extent.depth,
+            ),
+            max_mip_levels: value.max_mip_levels,
+            max_array_layers: value.max_array_layers,
+            sample_counts: value.sample_counts.as_raw(),
+            max_resource_size: value.max_resource_size,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use ash::vk::FramebufferCreateInfo;
 
-use crate::{Destroy, Device, ImageView, Instance, NxError, NxResult, RenderPass};
+use crate::{
+    Destroy, Device, DeviceChild, Garbage, ImageView, Instance, NxError, NxResult, RenderPass,
+};
 
 /// Stores information needed to create a FrameBuffer.
 pub struct FrameBufferDescriptor<'a> {
@@ -8,65 +10,80 @@ pub struct FrameBufferDescriptor<'a> {
     height: u32,
     render_pass: Option<&'a RenderPass>,
     image_view: Option<&'a ImageView>,
+    label: Option<String>,
 }
 
 impl<'a> FrameBufferDescriptor<'a> {
-    #[inline]
     /// Initializes a new descriptor with default values.
+    #[inline]
     pub const fn empty() -> Self {
         Self {
             width: 100,
             height: 100,
             render_pass: None,
             image_view: None,
+            label: None,
         }
     }
 
-    #[inline]
     /// Specifies the width of the FrameBuffer.
+    #[inline]
     pub fn width(mut self, width: u32) -> Self {
         self.width = width;
         self
     }
 
-    #[inline]
     /// Specifies the height of the FrameBuffer.
+    #[inline]
     pub fn height(mut self, height: u32) -> Self {
         self.height = height;
         self
     }
 
+    /// ImageView used to create the FrameBuffer.
     #[inline]
     #[must_use]
-    /// ImageView used to create the FrameBuffer.
     pub fn image_view(mut self, image_view: &'a ImageView) -> Self {
         self.image_view = Some(image_view);
         self
     }
 
+    /// RenderPass used to create the FrameBuffer.
     #[inline]
     #[must_use]
-    /// RenderPass used to create the FrameBuffer.
     pub fn render_pass(mut self, render_pass: &'a RenderPass) -> Self {
         self.render_pass = Some(render_pass);
         self
     }
+
+    /// Specifies the debug name of the FrameBuffer. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 pub struct FrameBuffer {
     pub(crate) frame_buffer: ash::vk::Framebuffer,
+    owner: DeviceChild,
 }
 
 impl FrameBuffer {
-    #[inline]
     /// Create a new Framebuffer.
     /// # Arguments
     ///
     /// * `device` - Reference to the appropriate device.
     /// * `descriptor` - Appropriate FenceDescriptor.
+    #[inline]
     pub fn new(device: &Device, descriptor: &FrameBufferDescriptor) -> NxResult<Self> {
-        let render_pass = descriptor.render_pass.unwrap();
-        let image_view = descriptor.image_view.unwrap();
+        device.check_validation()?;
+        let render_pass = descriptor
+            .render_pass
+            .ok_or(NxError::MissingDescriptorField("render_pass"))?;
+        let image_view = descriptor
+            .image_view
+            .ok_or(NxError::MissingDescriptorField("image_view"))?;
         let create_info = FramebufferCreateInfo::builder()
             .width(descriptor.width)
             .height(descriptor.height)
@@ -74,24 +91,32 @@ impl FrameBuffer {
             .render_pass(render_pass.render_pass)
             .attachments(&[image_view.image_view])
             .build();
-        let frame_buffer = match unsafe { device.device.create_framebuffer(&create_info, None) } {
+        let frame_buffer = match unsafe {
+            device
+                .device
+                .create_framebuffer(&create_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkCreateFramebuffer", e)),
         };
-        Ok(Self { frame_buffer })
+        device.label(frame_buffer, descriptor.label.as_deref());
+        Ok(Self {
+            frame_buffer,
+            owner: DeviceChild::new(device, frame_buffer, descriptor.label.as_deref()),
+        })
     }
 }
 
 impl Destroy for FrameBuffer {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_framebuffer(self.frame_buffer, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::Framebuffer(self.frame_buffer));
+    }
+}
+
+impl Drop for FrameBuffer {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::Framebuffer(self.frame_buffer));
     }
 }
@@ -0,0 +1,358 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use ash::vk;
+
+use crate::{Allocation, Device, MemoryAllocator, NxResult};
+
+/// Handle of an object waiting to be destroyed.
+#[doc(hidden)]
+pub(crate) enum Garbage {
+    Buffer(vk::Buffer),
+    Memory(Allocation),
+    Image(vk::Image),
+    ImageView(vk::ImageView),
+    Fence(vk::Fence),
+    Semaphore(vk::Semaphore),
+    ShaderModule(vk::ShaderModule),
+    Framebuffer(vk::Framebuffer),
+    RenderPass(vk::RenderPass),
+    CommandPool(vk::CommandPool),
+    DescriptorPool(vk::DescriptorPool),
+    DescriptorSetLayout(vk::DescriptorSetLayout),
+    PipelineLayout(vk::PipelineLayout),
+    Pipeline(vk::Pipeline),
+}
+
+impl Garbage {
+    /// Destroys the object. It must no longer be used by the GPU.
+    pub(crate) fn destroy(
+        self,
+        device: &ash::Device,
+        callbacks: Option<&vk::AllocationCallbacks>,
+        allocator: &MemoryAllocator,
+    ) {
+        unsafe {
+            match self {
+                Garbage::Buffer(x) => device.destroy_buffer(x, callbacks),
+                Garbage::Memory(x) => allocator.free(device, callbacks, &x),
+                Garbage::Image(x) => device.destroy_image(x, callbacks),
+                Garbage::ImageView(x) => device.destroy_image_view(x, callbacks),
+                Garbage::Fence(x) => device.destroy_fence(x, callbacks),
+                Garbage::Semaphore(x) => device.destroy_semaphore(x, callbacks),
+                Garbage::ShaderModule(x) => device.destroy_shader_module(x, callbacks),
+                Garbage::Framebuffer(x) => device.destroy_framebuffer(x, callbacks),
+                Garbage::RenderPass(x) => device.destroy_render_pass(x, callbacks),
+                Garbage::CommandPool(x) => device.destroy_command_pool(x, callbacks),
+                Garbage::DescriptorPool(x) => device.destroy_descriptor_pool(x, callbacks),
+                Garbage::DescriptorSetLayout(x) => {
+                    device.destroy_descriptor_set_layout(x, callbacks)
+                }
+                Garbage::PipelineLayout(x) => device.destroy_pipeline_layout(x, callbacks),
+                Garbage::Pipeline(x) => device.destroy_pipeline(x, callbacks),
+            }
+        }
+    }
+}
+
+/// Submissions numbered in the order they were made, with the fence each one signals.
+#[doc(hidden)]
+pub(crate) struct Submissions<F> {
+    last: u64,
+    in_flight: Vec<(u64, F)>,
+}
+
+impl<F> Submissions<F> {
+    pub(crate) fn new() -> Self {
+        Self {
+            last: 0,
+            in_flight: vec![],
+        }
+    }
+
+    /// Records a submission and returns its index. Indices start at 1.
+    pub(crate) fn push(&mut self, fence: F) -> u64 {
+        self.last += 1;
+        self.in_flight.push((self.last, fence));
+        self.last
+    }
+
+    /// Removes a submission that was not made, and returns its fence.
+    pub(crate) fn cancel(&mut self, index: u64) -> Option<F> {
+        let i = self.in_flight.iter().position(|x| x.0 == index)?;
+        Some(self.in_flight.swap_remove(i).1)
+    }
+
+    /// Fence of a submission still in flight.
+    pub(crate) fn fence(&self, index: u64) -> Option<&F> {
+        self.in_flight.iter().find(|x| x.0 == index).map(|x| &x.1)
+    }
+
+    /// Index of the last submission made.
+    pub(crate) fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Index up to which every submission has completed.
+    pub(crate) fn completed(&self) -> u64 {
+        self.in_flight
+            .iter()
+            .map(|x| x.0)
+            .min()
+            .map_or(self.last, |x| x - 1)
+    }
+
+    /// Removes the submissions for which `is_complete` returns `true`, and returns their fences.
+    pub(crate) fn retire<E>(
+        &mut self,
+        mut is_complete: impl FnMut(&F) -> Result<bool, E>,
+    ) -> Result<Vec<F>, E> {
+        let mut retired = vec![];
+        let mut i = 0;
+        while i < self.in_flight.len() {
+            if is_complete(&self.in_flight[i].1)? {
+                retired.push(self.in_flight.swap_remove(i).1);
+            } else {
+                i += 1;
+            }
+        }
+        Ok(retired)
+    }
+
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = F> + '_ {
+        self.in_flight.drain(..).map(|x| x.1)
+    }
+}
+
+/// Items waiting for the submissions made before they were parked.
+#[doc(hidden)]
+pub(crate) struct Retirement<T> {
+    parked: VecDeque<(u64, T)>,
+}
+
+impl<T> Retirement<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            parked: VecDeque::new(),
+        }
+    }
+
+    /// Parks an item until `submission` has completed.
+    /// Submissions must not decrease between calls.
+    pub(crate) fn park(&mut self, submission: u64, item: T) {
+        self.parked.push_back((submission, item));
+    }
+
+    /// Removes the items whose submission has completed.
+    pub(crate) fn take_completed(&mut self, completed: u64) -> Vec<T> {
+        let count = self.parked.iter().take_while(|x| x.0 <= completed).count();
+        self.parked.drain(..count).map(|x| x.1).collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.parked.len()
+    }
+
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.parked.drain(..).map(|x| x.1)
+    }
+}
+
+#[doc(hidden)]
+struct State {
+    submissions: Submissions<vk::Fence>,
+    parked: Retirement<Garbage>,
+    free_fences: Vec<vk::Fence>,
+}
+
+/// Delays the destruction of dropped objects until the GPU has finished the work submitted before.
+#[doc(hidden)]
+pub(crate) struct GarbageCollector {
+    state: Mutex<State>,
+}
+
+impl GarbageCollector {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                submissions: Submissions::new(),
+                parked: Retirement::new(),
+                free_fences: vec![],
+            }),
+        }
+    }
+
+    /// Reserves the index of a submission about to be made, and returns the fence it must signal.
+    /// The submission counts as in flight from now on, so objects dropped meanwhile by other threads wait for it.
+    pub(crate) fn begin_submission(&self, device: &Device) -> NxResult<(u64, vk::Fence)> {
+        let mut state = self.state.lock().unwrap();
+        let fence = match state.free_fences.pop() {
+            Some(x) => x,
+            None => {
+                let create_info = vk::FenceCreateInfo::builder().build();
+                unsafe {
+                    device
+                        .device
+                        .create_fence(&create_info, device.allocation_callbacks())
+                }
+                .map_err(|e| device.error("vkCreateFence", e))?
+            }
+        };
+        Ok((state.submissions.push(fence), fence))
+    }
+
+    /// Withdraws a submission reserved with `begin_submission` that could not be made.
+    pub(crate) fn cancel_submission(&self, submission: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(fence) = state.submissions.cancel(submission) {
+            state.free_fences.push(fence);
+        }
+    }
+
+    /// Marks a submission whose fence could not be submitted as complete, and destroys the fence.
+    /// The queue must have been idle since the work of the submission was submitted.
+    pub(crate) fn abandon_submission(&self, device: &Device, submission: u64) {
+        let fence = self.state.lock().unwrap().submissions.cancel(submission);
+        if let Some(x) = fence {
+            unsafe {
+                device
+                    .device
+                    .destroy_fence(x, device.allocation_callbacks())
+            };
+        }
+    }
+
+    /// Index up to which every submission has completed, as of the last `collect`.
+    pub(crate) fn completed(&self) -> u64 {
+        self.state.lock().unwrap().submissions.completed()
+    }
+
+    /// Blocks until `submission` has completed, destroying the objects that were waiting for it.
+    pub(crate) fn wait(&self, device: &Device, submission: u64) -> NxResult<()> {
+        loop {
+            self.collect(device)?;
+            let fence = {
+                let state = self.state.lock().unwrap();
+                let completed = state.submissions.completed();
+                if completed >= submission {
+                    return Ok(());
+                }
+                // The oldest submission still in flight.
+                *state.submissions.fence(completed + 1).unwrap()
+            };
+            // Another thread may reset the fence once it is signaled, so the wait is bounded.
+            match unsafe { device.device.wait_for_fences(&[fence], true, 1_000_000) } {
+                Ok(_) | Err(vk::Result::TIMEOUT) => {}
+                Err(e) => return Err(device.error("vkWaitForFences", e)),
+            }
+        }
+    }
+
+    /// Destroys the object now if no submission is in flight, and parks it otherwise.
+    pub(crate) fn defer(&self, device: &Device, garbage: Garbage) {
+        let mut state = self.state.lock().unwrap();
+        if state.submissions.completed() == state.submissions.last() {
+            drop(state);
+            garbage.destroy(
+                &device.device,
+                device.allocation_callbacks(),
+                device.allocator(),
+            );
+        } else {
+            let last = state.submissions.last();
+            state.parked.park(last, garbage);
+        }
+    }
+
+    /// Destroys the parked objects whose submissions have completed.
+    pub(crate) fn collect(&self, device: &Device) -> NxResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let retired = state
+            .submissions
+            .retire(|x| unsafe { device.device.get_fence_status(*x) })
+            .map_err(|e| device.error("vkGetFenceStatus", e))?;
+        if !retired.is_empty() {
+            unsafe { device.device.reset_fences(&retired) }
+                .map_err(|e| device.error("vkResetFences", e))?;
+            state.free_fences.extend(retired);
+        }
+        let completed = state.submissions.completed();
+        let garbage = state.parked.take_completed(completed);
+        drop(state);
+        for x in garbage {
+            x.destroy(
+                &device.device,
+                device.allocation_callbacks(),
+                device.allocator(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Number of objects waiting to be destroyed.
+    pub(crate) fn pending(&self) -> usize {
+        self.state.lock().unwrap().parked.len()
+    }
+
+    /// Destroys every parked object and fence. The device must be idle.
+    pub(crate) fn flush(
+        &mut self,
+        device: &ash::Device,
+        callbacks: Option<&vk::AllocationCallbacks>,
+        allocator: &MemoryAllocator,
+    ) {
+        let state = self.state.get_mut().unwrap();
+        for x in state.parked.drain() {
+            x.destroy(device, callbacks, allocator);
+        }
+        for x in state.submissions.drain().chain(state.free_fences.drain(..)) {
+            unsafe { device.destroy_fence(x, callbacks) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_in_submission_order() {
+        let mut submissions = Submissions::new();
+        assert_eq!(submissions.completed(), 0);
+        submissions.push('a');
+        submissions.push('b');
+        submissions.push('c');
+        let retired = submissions
+            .retire::<()>(|x| Ok(*x == 'b' || *x == 'c'))
+            .unwrap();
+        assert_eq!(retired.len(), 2);
+        // The first submission is still running.
+        assert_eq!(submissions.completed(), 0);
+        submissions.retire::<()>(|_| Ok(true)).unwrap();
+        assert_eq!(submissions.completed(), 3);
+    }
+
+    #[test]
+    fn releases_parked_items_after_their_submission() {
+        let mut parked = Retirement::new();
+        parked.park(1, "buffer");
+        parked.park(2, "pipeline");
+        parked.park(2, "image");
+        assert!(parked.take_completed(0).is_empty());
+        assert_eq!(parked.take_completed(1), vec!["buffer"]);
+        assert_eq!(parked.take_completed(3), vec!["pipeline", "image"]);
+        assert_eq!(parked.len(), 0);
+    }
+
+    #[test]
+    fn cancelled_submissions_do_not_hold_back_completion() {
+        let mut submissions = Submissions::new();
+        let first = submissions.push('a');
+        let second = submissions.push('b');
+        assert_eq!(submissions.cancel(first), Some('a'));
+        assert_eq!(submissions.cancel(first), None);
+        assert_eq!(submissions.completed(), first);
+        submissions.retire::<()>(|_| Ok(true)).unwrap();
+        assert_eq!(submissions.completed(), second);
+    }
+}
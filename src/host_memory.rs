@@ -0,0 +1,525 @@
+use std::alloc::Layout;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ash::vk::{self, InternalAllocationType, SystemAllocationScope};
+
+/// Indicates the lifetime of a host allocation made by the driver.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum AllocationScope {
+    /// Valid only during a single Vulkan command.
+    Command,
+    /// Valid for the lifetime of a Vulkan object.
+    Object,
+    /// Valid for the lifetime of a pipeline cache.
+    Cache,
+    /// Valid for the lifetime of the device.
+    Device,
+    /// Valid for the lifetime of the instance.
+    Instance,
+}
+
+impl AllocationScope {
+    /// All scopes, in the order used by `TrackingAllocator::report`.
+    pub const ALL: [AllocationScope; 5] = [
+        AllocationScope::Command,
+        AllocationScope::Object,
+        AllocationScope::Cache,
+        AllocationScope::Device,
+        AllocationScope::Instance,
+    ];
+
+    #[doc(hidden)]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl From<SystemAllocationScope> for AllocationScope {
+    fn from(value: SystemAllocationScope) -> Self {
+        match value {
+            SystemAllocationScope::COMMAND => AllocationScope::Command,
+            SystemAllocationScope::OBJECT => AllocationScope::Object,
+            SystemAllocationScope::CACHE => AllocationScope::Cache,
+            SystemAllocationScope::DEVICE => AllocationScope::Device,
+            _ => AllocationScope::Instance,
+        }
+    }
+}
+
+/// Allocates host memory on behalf of the Vulkan driver.
+///
+/// Set with `InstanceBuilder::host_allocator`.
+/// The allocator is used by the instance, every device created from it and every object created from those.
+///
+/// # Safety
+/// `allocate` and `reallocate` must return null or memory of at least `size` bytes aligned to `alignment`.
+/// The allocator has to remember the size of each allocation by itself, since `free` is not given it.
+pub unsafe trait HostAllocator: Send + Sync {
+    /// Allocate memory. Returns null on failure.
+    fn allocate(&self, size: usize, alignment: usize, scope: AllocationScope) -> *mut c_void;
+
+    /// Resize an allocation made by this allocator, preserving its contents.
+    /// `original` may be null, in which case this behaves like `allocate`.
+    /// If `size` is zero, `original` is freed and null is returned.
+    ///
+    /// # Safety
+    /// `original` must be null or a live allocation made by this allocator.
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: AllocationScope,
+    ) -> *mut c_void;
+
+    /// Free memory.
+    ///
+    /// # Safety
+    /// `memory` must be null or a live allocation made by this allocator.
+    unsafe fn free(&self, memory: *mut c_void);
+
+    /// Notification that the driver allocated executable memory by itself.
+    fn internal_allocation(&self, _size: usize, _scope: AllocationScope) {}
+
+    /// Notification that the driver freed executable memory allocated by itself.
+    fn internal_free(&self, _size: usize, _scope: AllocationScope) {}
+}
+
+#[doc(hidden)]
+#[repr(C)]
+struct Header {
+    size: usize,
+    alignment: usize,
+    offset: usize,
+    scope: AllocationScope,
+}
+
+/// Allocates host memory with the Rust global allocator.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemAllocator;
+
+impl SystemAllocator {
+    #[doc(hidden)]
+    fn layout(size: usize, alignment: usize) -> Option<(Layout, usize)> {
+        let alignment = alignment.max(std::mem::align_of::<Header>());
+        if !alignment.is_power_of_two() {
+            return None;
+        }
+        // The header is placed right before the returned pointer.
+        let offset = std::mem::size_of::<Header>().div_ceil(alignment) * alignment;
+        let layout = Layout::from_size_align(offset.checked_add(size)?, alignment).ok()?;
+        Some((layout, offset))
+    }
+
+    #[doc(hidden)]
+    unsafe fn header<'a>(memory: *mut c_void) -> &'a Header {
+        &*(memory as *const Header).sub(1)
+    }
+}
+
+unsafe impl HostAllocator for SystemAllocator {
+    fn allocate(&self, size: usize, alignment: usize, scope: AllocationScope) -> *mut c_void {
+        let (layout, offset) = match Self::layout(size, alignment) {
+            Some(x) => x,
+            None => return std::ptr::null_mut(),
+        };
+        unsafe {
+            let base = std::alloc::alloc(layout);
+            if base.is_null() {
+                return std::ptr::null_mut();
+            }
+            let memory = base.add(offset);
+            (memory as *mut Header).sub(1).write(Header {
+                size,
+                alignment: layout.align(),
+                offset,
+                scope,
+            });
+            memory as *mut c_void
+        }
+    }
+
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: AllocationScope,
+    ) -> *mut c_void {
+        if original.is_null() {
+            return self.allocate(size, alignment, scope);
+        }
+        if size == 0 {
+            self.free(original);
+            return std::ptr::null_mut();
+        }
+        let memory = self.allocate(size, alignment, scope);
+        if !memory.is_null() {
+            let old_size = Self::header(original).size;
+            std::ptr::copy_nonoverlapping(
+                original as *const u8,
+                memory as *mut u8,
+                old_size.min(size),
+            );
+            self.free(original);
+        }
+        memory
+    }
+
+    unsafe fn free(&self, memory: *mut c_void) {
+        if memory.is_null() {
+            return;
+        }
+        unsafe {
+            let header = Self::header(memory);
+            let layout =
+                Layout::from_size_align_unchecked(header.offset + header.size, header.alignment);
+            std::alloc::dealloc((memory as *mut u8).sub(header.offset), layout);
+        }
+    }
+}
+
+/// Host memory used by a single allocation scope.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HostMemoryUsage {
+    scope: AllocationScope,
+    current: usize,
+    peak: usize,
+    allocations: usize,
+}
+
+impl HostMemoryUsage {
+    pub fn scope(&self) -> AllocationScope {
+        self.scope
+    }
+
+    /// Bytes currently allocated.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Highest number of bytes allocated at the same time.
+    pub fn peak(&self) -> usize {
+        self.peak
+    }
+
+    /// Number of live allocations.
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+}
+
+#[doc(hidden)]
+#[derive(Default)]
+struct ScopeCounter {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    allocations: AtomicUsize,
+}
+
+impl ScopeCounter {
+    fn add(&self, size: usize) {
+        let current = self.current.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn sub(&self, size: usize) {
+        self.current.fetch_sub(size, Ordering::Relaxed);
+        self.allocations.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Allocates host memory like `SystemAllocator` and records the usage of each allocation scope.
+///
+/// Clones share the same counters, so a clone can be passed to the `InstanceBuilder`
+/// and the original inspected afterwards.
+///
+/// # Example
+/// ```no_run
+/// use nexg::{AllocationScope, InstanceBuilder, TrackingAllocator};
+///
+/// let allocator = TrackingAllocator::new();
+/// let instance = InstanceBuilder::new()
+///     .host_allocator(allocator.clone())
+///     .build()
+///     .unwrap();
+/// for usage in allocator.report() {
+///     println!("{:?}: {} bytes (peak {})", usage.scope(), usage.current(), usage.peak());
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct TrackingAllocator {
+    counters: Arc<[ScopeCounter; 5]>,
+    internal: Arc<[ScopeCounter; 5]>,
+}
+
+impl TrackingAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the usage of a single allocation scope.
+    pub fn usage(&self, scope: AllocationScope) -> HostMemoryUsage {
+        let counter = &self.counters[scope.index()];
+        HostMemoryUsage {
+            scope,
+            current: counter.current.load(Ordering::Relaxed),
+            peak: counter.peak.load(Ordering::Relaxed),
+            allocations: counter.allocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get the usage of every allocation scope.
+    pub fn report(&self) -> Vec<HostMemoryUsage> {
+        AllocationScope::ALL
+            .iter()
+            .map(|x| self.usage(*x))
+            .collect()
+    }
+
+    /// Get the executable memory the driver reported allocating by itself.
+    pub fn internal_usage(&self, scope: AllocationScope) -> HostMemoryUsage {
+        let counter = &self.internal[scope.index()];
+        HostMemoryUsage {
+            scope,
+            current: counter.current.load(Ordering::Relaxed),
+            peak: counter.peak.load(Ordering::Relaxed),
+            allocations: counter.allocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Bytes currently allocated across all scopes.
+    pub fn current(&self) -> usize {
+        self.report().iter().map(|x| x.current).sum()
+    }
+}
+
+unsafe impl HostAllocator for TrackingAllocator {
+    fn allocate(&self, size: usize, alignment: usize, scope: AllocationScope) -> *mut c_void {
+        let memory = SystemAllocator.allocate(size, alignment, scope);
+        if !memory.is_null() {
+            self.counters[scope.index()].add(size);
+        }
+        memory
+    }
+
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: AllocationScope,
+    ) -> *mut c_void {
+        let old = (!original.is_null()).then(|| {
+            let header = SystemAllocator::header(original);
+            (header.size, header.scope)
+        });
+        let memory = SystemAllocator.reallocate(original, size, alignment, scope);
+        if memory.is_null() && size != 0 {
+            // The original allocation is left untouched on failure.
+            return memory;
+        }
+        if let Some((old_size, old_scope)) = old {
+            self.counters[old_scope.index()].sub(old_size);
+        }
+        if !memory.is_null() {
+            self.counters[scope.index()].add(size);
+        }
+        memory
+    }
+
+    unsafe fn free(&self, memory: *mut c_void) {
+        if memory.is_null() {
+            return;
+        }
+        let header = SystemAllocator::header(memory);
+        self.counters[header.scope.index()].sub(header.size);
+        SystemAllocator.free(memory);
+    }
+
+    fn internal_allocation(&self, size: usize, scope: AllocationScope) {
+        self.internal[scope.index()].add(size);
+    }
+
+    fn internal_free(&self, size: usize, scope: AllocationScope) {
+        self.internal[scope.index()].sub(size);
+    }
+}
+
+#[doc(hidden)]
+pub(crate) struct HostCallbacks {
+    // Boxed twice so that `p_user_data` can be a thin pointer.
+    _allocator: Box<Box<dyn HostAllocator>>,
+    raw: vk::AllocationCallbacks,
+}
+
+// `raw` only points to `allocator`, which is `Send + Sync` itself.
+unsafe impl Send for HostCallbacks {}
+unsafe impl Sync for HostCallbacks {}
+
+impl HostCallbacks {
+    pub(crate) fn new(allocator: Box<dyn HostAllocator>) -> Arc<Self> {
+        let allocator = Box::new(allocator);
+        let raw = vk::AllocationCallbacks {
+            p_user_data: &*allocator as *const Box<dyn HostAllocator> as *mut c_void,
+            pfn_allocation: Some(allocation),
+            pfn_reallocation: Some(reallocation),
+            pfn_free: Some(free),
+            pfn_internal_allocation: Some(internal_allocation),
+            pfn_internal_free: Some(internal_free),
+        };
+        Arc::new(Self {
+            _allocator: allocator,
+            raw,
+        })
+    }
+
+    pub(crate) fn raw(&self) -> &vk::AllocationCallbacks {
+        &self.raw
+    }
+}
+
+#[doc(hidden)]
+unsafe fn allocator<'a>(user_data: *mut c_void) -> &'a dyn HostAllocator {
+    &**(user_data as *const Box<dyn HostAllocator>)
+}
+
+// Unwinding out of an `extern "system"` function aborts, so a panicking allocator is contained here
+// and reported to the driver as an allocation failure.
+#[doc(hidden)]
+fn contain<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+#[doc(hidden)]
+unsafe extern "system" fn allocation(
+    user_data: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: SystemAllocationScope,
+) -> *mut c_void {
+    contain(std::ptr::null_mut(), || {
+        allocator(user_data).allocate(size, alignment, scope.into())
+    })
+}
+
+#[doc(hidden)]
+unsafe extern "system" fn reallocation(
+    user_data: *mut c_void,
+    original: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: SystemAllocationScope,
+) -> *mut c_void {
+    contain(std::ptr::null_mut(), || {
+        allocator(user_data).reallocate(original, size, alignment, scope.into())
+    })
+}
+
+#[doc(hidden)]
+unsafe extern "system" fn free(user_data: *mut c_void, memory: *mut c_void) {
+    contain((), || allocator(user_data).free(memory))
+}
+
+#[doc(hidden)]
+unsafe extern "system" fn internal_allocation(
+    user_data: *mut c_void,
+    size: usize,
+    _ty: InternalAllocationType,
+    scope: SystemAllocationScope,
+) {
+    contain((), || {
+        allocator(user_data).internal_allocation(size, scope.into())
+    })
+}
+
+#[doc(hidden)]
+unsafe extern "system" fn internal_free(
+    user_data: *mut c_void,
+    size: usize,
+    _ty: InternalAllocationType,
+    scope: SystemAllocationScope,
+) {
+    contain((), || {
+        allocator(user_data).internal_free(size, scope.into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_current_and_peak() {
+        let allocator = TrackingAllocator::new();
+        let a = allocator.allocate(100, 64, AllocationScope::Object);
+        let b = allocator.allocate(28, 8, AllocationScope::Object);
+        assert_eq!(a as usize % 64, 0);
+        unsafe { allocator.free(a) };
+        let usage = allocator.usage(AllocationScope::Object);
+        assert_eq!(usage.current(), 28);
+        assert_eq!(usage.peak(), 128);
+        assert_eq!(usage.allocations(), 1);
+
+        let b = unsafe { allocator.reallocate(b, 256, 8, AllocationScope::Object) };
+        assert_eq!(allocator.usage(AllocationScope::Object).current(), 256);
+        unsafe { allocator.free(b) };
+        assert_eq!(allocator.current(), 0);
+    }
+
+    #[test]
+    fn reallocation_keeps_contents_and_moves_scope() {
+        let allocator = TrackingAllocator::new();
+        let a = allocator.allocate(4, 4, AllocationScope::Command) as *mut u32;
+        unsafe { a.write(0xdead_beef) };
+        let b = unsafe { allocator.reallocate(a as _, 64, 16, AllocationScope::Object) };
+        assert_eq!(unsafe { *(b as *const u32) }, 0xdead_beef);
+        assert_eq!(allocator.usage(AllocationScope::Command).current(), 0);
+        assert_eq!(allocator.usage(AllocationScope::Object).current(), 64);
+        let c = unsafe { allocator.reallocate(b, 0, 16, AllocationScope::Object) };
+        assert!(c.is_null());
+        assert_eq!(allocator.current(), 0);
+        assert_eq!(allocator.usage(AllocationScope::Object).allocations(), 0);
+    }
+
+    struct PanickingAllocator;
+
+    unsafe impl HostAllocator for PanickingAllocator {
+        fn allocate(&self, _: usize, _: usize, _: AllocationScope) -> *mut c_void {
+            panic!("out of memory")
+        }
+
+        unsafe fn reallocate(
+            &self,
+            _: *mut c_void,
+            _: usize,
+            _: usize,
+            _: AllocationScope,
+        ) -> *mut c_void {
+            panic!("out of memory")
+        }
+
+        unsafe fn free(&self, _: *mut c_void) {
+            panic!("double free")
+        }
+    }
+
+    #[test]
+    fn panicking_allocator_reports_failure() {
+        let callbacks = HostCallbacks::new(Box::new(PanickingAllocator));
+        let raw = callbacks.raw();
+        unsafe {
+            let memory = (raw.pfn_allocation.unwrap())(
+                raw.p_user_data,
+                16,
+                8,
+                SystemAllocationScope::OBJECT,
+            );
+            assert!(memory.is_null());
+            (raw.pfn_free.unwrap())(raw.p_user_data, std::ptr::null_mut());
+        }
+    }
+}
@@ -1,12 +1,14 @@
+use std::marker::PhantomData;
 use std::os::raw::c_void;
 
 use crate::{
-    Destroy, Device, DeviceConnecter, DeviceMemory, Extent3d, Instance, NxError, NxResult,
+    Destroy, Device, DeviceChild, DeviceConnecter, DeviceMemory, Extent3d, Garbage, Instance,
+    MemoryLocation, NotSync, NxError, NxResult,
 };
 use ash::vk::{
-    ComponentMapping, ComponentSwizzle, Format, ImageAspectFlags, ImageCreateInfo, ImageLayout,
-    ImageSubresourceRange, ImageTiling, ImageUsageFlags, ImageViewCreateInfo, ImageViewType,
-    MemoryMapFlags, SampleCountFlags, SharingMode,
+    self, ComponentMapping, ComponentSwizzle, Format, ImageAspectFlags, ImageCreateInfo,
+    ImageSubresourceRange, ImageUsageFlags, ImageViewCreateInfo, ImageViewType, SampleCountFlags,
+    SharingMode,
 };
 
 /// Indicates the format of the image.
@@ -29,11 +31,94 @@ pub enum ImageFormat {
     A2B10G10R10UintPack32,
     Undefined,
     B8G8R8A8Unorm,
+    B8G8R8A8Srgb,
+    R16G16B16A16Sfloat,
+    R32G32B32A32Sfloat,
+    D16Unorm,
+    D32Sfloat,
+    S8Uint,
+    D16UnormS8Uint,
+    D24UnormS8Uint,
+    D32SfloatS8Uint,
 }
 
-impl Into<ImageFormat> for Format {
-    fn into(self) -> ImageFormat {
+impl ImageFormat {
+    /// Check to see if the format has a depth component.
+    pub fn is_depth(self) -> bool {
+        matches!(
+            self,
+            ImageFormat::D16Unorm
+                | ImageFormat::D32Sfloat
+                | ImageFormat::D16UnormS8Uint
+                | ImageFormat::D24UnormS8Uint
+                | ImageFormat::D32SfloatS8Uint
+        )
+    }
+
+    /// Check to see if the format has a stencil component.
+    pub fn has_stencil(self) -> bool {
+        matches!(
+            self,
+            ImageFormat::S8Uint
+                | ImageFormat::D16UnormS8Uint
+                | ImageFormat::D24UnormS8Uint
+                | ImageFormat::D32SfloatS8Uint
+        )
+    }
+
+    /// Get the size of a texel in bytes.
+    /// Returns `None` for `Undefined` and for formats with both depth and stencil,
+    /// whose aspects are stored separately when copied.
+    pub fn texel_size(self) -> Option<u32> {
         match self {
+            ImageFormat::S8Uint => Some(1),
+            ImageFormat::A1R5G5B5UnormPack16 | ImageFormat::D16Unorm => Some(2),
+            ImageFormat::B8G8R8Sscaled
+            | ImageFormat::B8G8R8Srgb
+            | ImageFormat::B8G8R8Snorm
+            | ImageFormat::B8G8R8Sint => Some(3),
+            ImageFormat::R8G8B8A8Unorm
+            | ImageFormat::R8G8B8A8Srgb
+            | ImageFormat::R8G8B8A8Sscaled
+            | ImageFormat::R8G8B8A8Sint
+            | ImageFormat::R8G8B8A8Snorm
+            | ImageFormat::R8G8B8A8Uint
+            | ImageFormat::A2B10G10R10SintPack32
+            | ImageFormat::A2B10G10R10SnormPack32
+            | ImageFormat::A2B10G10R10SscaledPack32
+            | ImageFormat::A2B10G10R10UintPack32
+            | ImageFormat::B8G8R8A8Unorm
+            | ImageFormat::B8G8R8A8Srgb
+            | ImageFormat::D32Sfloat => Some(4),
+            ImageFormat::R16G16B16A16Sfloat => Some(8),
+            ImageFormat::R32G32B32A32Sfloat => Some(16),
+            ImageFormat::Undefined
+            | ImageFormat::D16UnormS8Uint
+            | ImageFormat::D24UnormS8Uint
+            | ImageFormat::D32SfloatS8Uint => None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn aspect(self) -> ImageAspectFlags {
+        let mut aspect = ImageAspectFlags::empty();
+        if self.is_depth() {
+            aspect |= ImageAspectFlags::DEPTH;
+        }
+        if self.has_stencil() {
+            aspect |= ImageAspectFlags::STENCIL;
+        }
+        if aspect.is_empty() {
+            ImageAspectFlags::COLOR
+        } else {
+            aspect
+        }
+    }
+}
+
+impl From<Format> for ImageFormat {
+    fn from(value: Format) -> Self {
+        match value {
             Format::R8G8B8A8_UNORM => ImageFormat::R8G8B8A8Unorm,
             Format::B8G8R8A8_UNORM => ImageFormat::B8G8R8A8Unorm,
             Format::A1R5G5B5_UNORM_PACK16 => ImageFormat::A1R5G5B5UnormPack16,
@@ -50,15 +135,24 @@ impl Into<ImageFormat> for Format {
             Format::R8G8B8A8_SSCALED => ImageFormat::R8G8B8A8Sscaled,
             Format::R8G8B8A8_SNORM => ImageFormat::R8G8B8A8Snorm,
             Format::R8G8B8A8_UINT => ImageFormat::R8G8B8A8Uint,
+            Format::B8G8R8A8_SRGB => ImageFormat::B8G8R8A8Srgb,
+            Format::R16G16B16A16_SFLOAT => ImageFormat::R16G16B16A16Sfloat,
+            Format::R32G32B32A32_SFLOAT => ImageFormat::R32G32B32A32Sfloat,
+            Format::D16_UNORM => ImageFormat::D16Unorm,
+            Format::D32_SFLOAT => ImageFormat::D32Sfloat,
+            Format::S8_UINT => ImageFormat::S8Uint,
+            Format::D16_UNORM_S8_UINT => ImageFormat::D16UnormS8Uint,
+            Format::D24_UNORM_S8_UINT => ImageFormat::D24UnormS8Uint,
+            Format::D32_SFLOAT_S8_UINT => ImageFormat::D32SfloatS8Uint,
 
             _ => ImageFormat::Undefined,
         }
     }
 }
 
-impl Into<Format> for ImageFormat {
-    fn into(self) -> Format {
-        match self {
+impl From<ImageFormat> for Format {
+    fn from(value: ImageFormat) -> Self {
+        match value {
             ImageFormat::R8G8B8A8Unorm => Format::R8G8B8A8_UNORM,
             ImageFormat::R8G8B8A8Srgb => Format::R8G8B8A8_SRGB,
             ImageFormat::R8G8B8A8Sscaled => Format::R8G8B8A8_SSCALED,
@@ -75,6 +169,15 @@ impl Into<Format> for ImageFormat {
             ImageFormat::A2B10G10R10SnormPack32 => Format::A2B10G10R10_SNORM_PACK32,
             ImageFormat::A2B10G10R10SscaledPack32 => Format::A2B10G10R10_SSCALED_PACK32,
             ImageFormat::A2B10G10R10UintPack32 => Format::A2B10G10R10_UINT_PACK32,
+            ImageFormat::B8G8R8A8Srgb => Format::B8G8R8A8_SRGB,
+            ImageFormat::R16G16B16A16Sfloat => Format::R16G16B16A16_SFLOAT,
+            ImageFormat::R32G32B32A32Sfloat => Format::R32G32B32A32_SFLOAT,
+            ImageFormat::D16Unorm => Format::D16_UNORM,
+            ImageFormat::D32Sfloat => Format::D32_SFLOAT,
+            ImageFormat::S8Uint => Format::S8_UINT,
+            ImageFormat::D16UnormS8Uint => Format::D16_UNORM_S8_UINT,
+            ImageFormat::D24UnormS8Uint => Format::D24_UNORM_S8_UINT,
+            ImageFormat::D32SfloatS8Uint => Format::D32_SFLOAT_S8_UINT,
 
             ImageFormat::Undefined => Format::UNDEFINED,
         }
@@ -88,27 +191,106 @@ pub enum ImageDimension {
     D3,
 }
 
-impl Into<ash::vk::ImageType> for ImageDimension {
-    fn into(self) -> ash::vk::ImageType {
-        match self {
-            ImageDimension::D2 => ash::vk::ImageType::TYPE_2D,
-            ImageDimension::D3 => ash::vk::ImageType::TYPE_3D,
+impl From<ImageDimension> for vk::ImageType {
+    fn from(value: ImageDimension) -> Self {
+        match value {
+            ImageDimension::D2 => vk::ImageType::TYPE_2D,
+            ImageDimension::D3 => vk::ImageType::TYPE_3D,
+        }
+    }
+}
+
+/// Indicates the arrangement of texels in memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageTiling {
+    /// Implementation-dependent arrangement. Usually faster, but cannot be read by mapping memory.
+    Optimal,
+    /// Row-major arrangement that can be read by mapping memory.
+    Linear,
+}
+
+impl From<ImageTiling> for vk::ImageTiling {
+    fn from(value: ImageTiling) -> Self {
+        match value {
+            ImageTiling::Optimal => vk::ImageTiling::OPTIMAL,
+            ImageTiling::Linear => vk::ImageTiling::LINEAR,
+        }
+    }
+}
+
+/// Indicates the layout of an image, which determines the operations it can be used for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageLayout {
+    /// Supports every operation, but may be slower.
+    General,
+    ColorAttachment,
+    DepthStencilAttachment,
+    /// Read by shaders, such as a sampled texture.
+    ShaderReadOnly,
+    TransferSrc,
+    TransferDst,
+}
+
+impl From<ImageLayout> for vk::ImageLayout {
+    fn from(value: ImageLayout) -> Self {
+        match value {
+            ImageLayout::General => vk::ImageLayout::GENERAL,
+            ImageLayout::ColorAttachment => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            ImageLayout::DepthStencilAttachment => {
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+            }
+            ImageLayout::ShaderReadOnly => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ImageLayout::TransferSrc => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ImageLayout::TransferDst => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        }
+    }
+}
+
+/// Indicates how an image is used.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageUsage {
+    TransferSrc,
+    TransferDst,
+    Sampled,
+    Storage,
+    ColorAttachment,
+    DepthStencilAttachment,
+    InputAttachment,
+}
+
+impl From<ImageUsage> for ImageUsageFlags {
+    fn from(value: ImageUsage) -> Self {
+        match value {
+            ImageUsage::TransferSrc => ImageUsageFlags::TRANSFER_SRC,
+            ImageUsage::TransferDst => ImageUsageFlags::TRANSFER_DST,
+            ImageUsage::Sampled => ImageUsageFlags::SAMPLED,
+            ImageUsage::Storage => ImageUsageFlags::STORAGE,
+            ImageUsage::ColorAttachment => ImageUsageFlags::COLOR_ATTACHMENT,
+            ImageUsage::DepthStencilAttachment => ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            ImageUsage::InputAttachment => ImageUsageFlags::INPUT_ATTACHMENT,
         }
     }
 }
 
 /// Stores information needed to create a Image.
 pub struct ImageDescriptor {
-    image_type: ImageDimension,
+    pub(crate) image_type: ImageDimension,
     extent: Extent3d,
     mip_levels: u32,
     array_layers: u32,
-    format: ImageFormat,
+    pub(crate) format: ImageFormat,
+    pub(crate) tiling: ImageTiling,
+    pub(crate) usage: ImageUsageFlags,
+    samples: u32,
+    location: MemoryLocation,
+    label: Option<String>,
 }
 
 impl ImageDescriptor {
-    #[inline]
     /// Initializes a new descriptor with default values.
+    ///
+    /// The default is a linear 100x100 `R8G8B8A8Unorm` color attachment with a single sample.
+    #[inline]
     pub const fn new() -> Self {
         Self {
             image_type: ImageDimension::D2,
@@ -116,110 +298,204 @@ impl ImageDescriptor {
             mip_levels: 1,
             array_layers: 1,
             format: ImageFormat::R8G8B8A8Unorm,
+            tiling: ImageTiling::Linear,
+            usage: ImageUsageFlags::COLOR_ATTACHMENT,
+            samples: 1,
+            location: MemoryLocation::Upload,
+            label: None,
         }
     }
 
-    #[inline]
     /// Specifies the dimension of the Image.
+    #[inline]
     pub fn image_type(mut self, image_type: ImageDimension) -> Self {
         self.image_type = image_type;
         self
     }
 
-    #[inline]
     /// Specifies the extent of the Image.
+    #[inline]
     pub fn extent(mut self, extent: Extent3d) -> Self {
         self.extent = extent;
         self
     }
 
+    /// Specifies the number of mip levels of the Image.
     #[inline]
+    pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = mip_levels;
+        self
+    }
+
+    /// Specifies the number of array layers of the Image.
+    #[inline]
+    pub fn array_layers(mut self, array_layers: u32) -> Self {
+        self.array_layers = array_layers;
+        self
+    }
+
     /// Specifies the format of the Image.
+    #[inline]
     pub fn format(mut self, format: ImageFormat) -> Self {
         self.format = format;
         self
     }
+
+    /// Specifies the tiling of the Image.
+    #[inline]
+    pub fn tiling(mut self, tiling: ImageTiling) -> Self {
+        self.tiling = tiling;
+        self
+    }
+
+    /// Specifies every usage of the Image.
+    #[inline]
+    pub fn usage(mut self, usage: &[ImageUsage]) -> Self {
+        self.usage = usage
+            .iter()
+            .fold(ImageUsageFlags::empty(), |acc, x| acc | (*x).into());
+        self
+    }
+
+    /// Specifies the number of samples per texel. This must be a power of two,
+    /// otherwise creating the image fails with `NxError::InvalidSampleCount`.
+    /// Supported counts can be checked with `DeviceConnecter::image_format_properties`.
+    #[inline]
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Specifies where the memory of the Image is placed. The default is `MemoryLocation::Upload`.
+    /// Images read with `Image::as_raw_data` should use `MemoryLocation::Readback`.
+    #[inline]
+    pub fn location(mut self, location: MemoryLocation) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Specifies the debug name of the Image. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
+}
+
+impl Default for ImageDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct Image {
-    image: ash::vk::Image,
+    pub(crate) image: ash::vk::Image,
+    pub(crate) format: ImageFormat,
+    extent: Extent3d,
+    mip_levels: u32,
+    array_layers: u32,
+    /// `None` for images of a swapchain, which do not own their memory.
     memory: Option<DeviceMemory>,
-
-    size: Option<u64>,
+    /// `None` for images of a swapchain, which are destroyed with the swapchain.
+    owner: Option<DeviceChild>,
+    _not_sync: NotSync,
 }
 
 impl Image {
     /// Create a new Image.
     /// # Arguments
     ///
-    /// * `instance` - appropriate Instance.
+    /// * `instance` - Unused. Memory is carved out of the blocks of the device's allocator.
     /// * `device` - Reference to the appropriate device.
-    /// * `connecter` - Appropriate device connecter.
+    /// * `connecter` - Unused.
     /// * `descriptor` - Appropriate ImageDescriptor.
     pub fn create(
-        instance: &Instance,
+        _instance: &Instance,
         device: &Device,
-        connecter: DeviceConnecter,
+        _connecter: DeviceConnecter,
         descriptor: &ImageDescriptor,
     ) -> NxResult<Self> {
+        device.check_validation()?;
+        if !descriptor.samples.is_power_of_two() || descriptor.samples > 64 {
+            return Err(NxError::InvalidSampleCount(descriptor.samples));
+        }
         let create_info = ImageCreateInfo::builder()
             .image_type(descriptor.image_type.into())
             .extent(descriptor.extent.into())
             .mip_levels(descriptor.mip_levels)
             .array_layers(descriptor.array_layers)
-            .format(Format::R8G8B8A8_UNORM)
-            .tiling(ImageTiling::LINEAR)
-            .initial_layout(ImageLayout::UNDEFINED)
-            .usage(ImageUsageFlags::COLOR_ATTACHMENT)
+            .format(descriptor.format.into())
+            .tiling(descriptor.tiling.into())
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(descriptor.usage)
             .sharing_mode(SharingMode::EXCLUSIVE)
-            .samples(SampleCountFlags::TYPE_1)
+            .samples(SampleCountFlags::from_raw(descriptor.samples))
             .build();
-        let image = unsafe { device.device.create_image(&create_info, None) }.unwrap();
-        let mem_props = connecter.get_memory_properties(instance);
-        let mem_req = unsafe { device.device.get_image_memory_requirements(image) };
-
-        let memory = DeviceMemory::alloc_image_memory(&device.device, image, mem_props, mem_req)?;
+        let image = unsafe {
+            device
+                .device
+                .create_image(&create_info, device.allocation_callbacks())
+        }
+        .map_err(|e| device.error("vkCreateImage", e))?;
+        let memory = match DeviceMemory::alloc_image_memory(
+            device,
+            image,
+            descriptor.location,
+            descriptor.tiling == ImageTiling::Linear,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                unsafe {
+                    device
+                        .device
+                        .destroy_image(image, device.allocation_callbacks())
+                };
+                return Err(e);
+            }
+        };
+        device.label(image, descriptor.label.as_deref());
         Ok(Self {
             image,
-            size: Some(mem_req.size),
+            format: descriptor.format,
+            extent: descriptor.extent,
+            mip_levels: descriptor.mip_levels,
+            array_layers: descriptor.array_layers,
             memory: Some(memory),
+            owner: Some(DeviceChild::new(device, image, descriptor.label.as_deref())),
+            _not_sync: PhantomData,
         })
     }
 
     #[deprecated(since = "0.1.0", note = "Please use as_raw_data()")]
     /// Maps the memory of the image
     pub fn map_memory(&self, device: &Device) -> NxResult<*mut c_void> {
-        match unsafe {
-            device.device.map_memory(
-                self.memory.as_ref().unwrap().memory,
-                0,
-                self.size.unwrap(),
-                MemoryMapFlags::empty(),
-            )
-        } {
-            Ok(x) => Ok(x),
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                ash::vk::Result::ERROR_MEMORY_MAP_FAILED => Err(NxError::MemoryMapFailed),
-                _ => Err(NxError::Unknown),
-            }?,
+        device.check_validation()?;
+        match &self.memory {
+            Some(x) => x.map(device),
+            None => Err(NxError::NoValue),
         }
     }
 
+    /// Copies `width * height` texels from the memory of the image, using the texel size of its format.
+    /// Fails with `NxError::FormatNotCopyable` for formats with both depth and stencil,
+    /// and with `NxError::OutOfBounds` if the texels exceed the memory of the image.
     pub fn as_raw_data(&self, device: &Device, width: u32, height: u32) -> NxResult<Vec<u8>> {
-        match self.memory.as_ref() {
-            None => Err(NxError::Unknown),
-            Some(x) => {
-                let data = x.map(device, self.size.unwrap())?;
-                let slice: &[u8] = unsafe {
-                    std::slice::from_raw_parts(data as *const u8, (width * height * 4) as usize)
-                };
-                let data = slice.to_vec();
-                x.unmap(device);
-                Ok(data)
-            }
+        device.check_validation()?;
+        let memory = self.memory.as_ref().ok_or(NxError::NoValue)?;
+        let texel = self
+            .format
+            .texel_size()
+            .ok_or(NxError::FormatNotCopyable(self.format))?;
+        let size = width as u64 * height as u64 * texel as u64;
+        if size > memory.size() {
+            return Err(NxError::OutOfBounds {
+                end: size,
+                size: memory.size(),
+            });
         }
+        let data = memory.map(device)?;
+        let slice: &[u8] = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+        Ok(slice.to_vec())
     }
 
     /// Create a image view from self.
@@ -227,22 +503,44 @@ impl Image {
         &self,
         device: &Device,
         descriptor: &ImageViewDescriptor,
-    ) -> ImageView {
-        ImageView::new(device, self, &descriptor)
+    ) -> NxResult<ImageView> {
+        ImageView::new(device, self, descriptor)
     }
 
+    /// Extent of a mip level, or `None` if the image has no such level or layer.
     #[doc(hidden)]
-    pub(crate) fn from_raw(image: ash::vk::Image) -> Self {
+    pub(crate) fn level_extent(&self, mip_level: u32, array_layer: u32) -> Option<Extent3d> {
+        if mip_level >= self.mip_levels || array_layer >= self.array_layers {
+            return None;
+        }
+        let level = |x: u32| x.checked_shr(mip_level).unwrap_or(0).max(1);
+        Some(Extent3d::new(
+            level(self.extent.width()),
+            level(self.extent.height()),
+            level(self.extent.depth()),
+        ))
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn from_raw(image: ash::vk::Image, format: ImageFormat, extent: Extent3d) -> Self {
         Self {
             image,
+            format,
+            extent,
+            mip_levels: 1,
+            array_layers: 1,
             memory: None,
-            size: None,
+            owner: None,
+            _not_sync: PhantomData,
         }
     }
 }
 
 pub struct ImageViewDescriptor {
     format: ImageFormat,
+    base_mip_level: u32,
+    mip_level_count: u32,
+    label: Option<String>,
 }
 
 impl ImageViewDescriptor {
@@ -250,6 +548,9 @@ impl ImageViewDescriptor {
     pub const fn empty() -> Self {
         Self {
             format: ImageFormat::R8G8B8A8Unorm,
+            base_mip_level: 0,
+            mip_level_count: vk::REMAINING_MIP_LEVELS,
+            label: None,
         }
     }
 
@@ -258,16 +559,37 @@ impl ImageViewDescriptor {
         self.format = format;
         self
     }
+
+    /// Specifies the mip levels seen through the view. By default, the view covers every mip level of the image.
+    #[inline]
+    pub const fn mip_levels(mut self, base_mip_level: u32, count: u32) -> Self {
+        self.base_mip_level = base_mip_level;
+        self.mip_level_count = count;
+        self
+    }
+
+    /// Specifies the debug name of the ImageView. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 pub struct ImageView {
     pub(crate) image_view: ash::vk::ImageView,
+    owner: DeviceChild,
 }
 
 impl ImageView {
     #[inline]
     #[doc(hidden)]
-    pub(crate) fn new(device: &Device, image: &Image, descriptor: &ImageViewDescriptor) -> Self {
+    pub(crate) fn new(
+        device: &Device,
+        image: &Image,
+        descriptor: &ImageViewDescriptor,
+    ) -> NxResult<Self> {
+        device.check_validation()?;
         let create_info = ImageViewCreateInfo::builder()
             .image(image.image)
             .view_type(ImageViewType::TYPE_2D)
@@ -282,16 +604,25 @@ impl ImageView {
             )
             .subresource_range(
                 ImageSubresourceRange::builder()
-                    .aspect_mask(ImageAspectFlags::COLOR)
-                    .base_mip_level(0)
-                    .level_count(1)
+                    .aspect_mask(descriptor.format.aspect())
+                    .base_mip_level(descriptor.base_mip_level)
+                    .level_count(descriptor.mip_level_count)
                     .base_array_layer(0)
                     .layer_count(1)
                     .build(),
             )
             .build();
-        let image_view = unsafe { device.device.create_image_view(&create_info, None) }.unwrap();
-        Self { image_view }
+        let image_view = unsafe {
+            device
+                .device
+                .create_image_view(&create_info, device.allocation_callbacks())
+        }
+        .map_err(|e| device.error("vkCreateImageView", e))?;
+        device.label(image_view, descriptor.label.as_deref());
+        Ok(Self {
+            image_view,
+            owner: DeviceChild::new(device, image_view, descriptor.label.as_deref()),
+        })
     }
 }
 
@@ -299,14 +630,20 @@ impl Destroy for Image {
     fn instance(&self, _: &Instance) {}
 
     fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_image(self.image, None);
+        if let Some(owner) = self.owner.as_ref() {
+            owner.destroy(Garbage::Image(self.image));
         }
-        match self.memory.as_ref() {
-            None => {}
-            Some(x) => {
-                device.destroy(x);
-            }
+        if let Some(x) = self.memory.as_ref() {
+            device.destroy(x);
+        }
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        // The memory is dropped after the image, so it is released at the same time.
+        if let Some(owner) = self.owner.as_ref() {
+            owner.defer(Garbage::Image(self.image));
         }
     }
 }
@@ -314,9 +651,13 @@ impl Destroy for Image {
 impl Destroy for ImageView {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_image_view(self.image_view, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::ImageView(self.image_view));
+    }
+}
+
+impl Drop for ImageView {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::ImageView(self.image_view));
     }
 }
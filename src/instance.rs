@@ -2,16 +2,31 @@ use crate::{NxError, NxResult};
 use ash::extensions::ext::DebugUtils;
 use ash::vk::{
     self, DebugUtilsMessengerEXT, DeviceCreateInfo, PhysicalDevice, PhysicalDeviceMemoryProperties,
+    PhysicalDeviceProperties,
 };
 use ash::{vk::InstanceCreateInfo, Entry};
-use std::ffi::c_char;
-
-use crate::{vulkan_debug_callback, Device, DeviceConnecter, DeviceFeature};
+use std::ffi::{c_char, c_void, CString};
+#[cfg(feature = "loaded")]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(not(any(feature = "linked", feature = "loaded")))]
+compile_error!("Either the \"linked\" or the \"loaded\" feature must be enabled.");
+
+use crate::{
+    debug_string, enumerate_extensions, enumerate_layers, vulkan_debug_callback, ConnecterSelector,
+    DebugMessageSink, DebugState, Device, DeviceConnecter, DeviceFeature, DeviceLoss,
+    ExtensionProperties, FeatureSet, HostAllocator, HostCallbacks, LayerProperties, LogSink,
+    MemoryAllocator, QueueFamilyIndices, QueueMap, ValidationDescriptor, Version,
+    VALIDATION_LAYER_NAME,
+};
 
 /// Represents an additional feature of the instance.
 pub struct InstanceFeature {
     #[doc(hidden)]
-    extensions: Vec<*const c_char>,
+    extensions: Vec<String>,
+    #[doc(hidden)]
+    layers: Vec<String>,
     #[doc(hidden)]
     device_exts: Vec<DeviceFeature>,
 }
@@ -22,13 +37,47 @@ impl InstanceFeature {
     pub const fn empty() -> Self {
         Self {
             extensions: vec![],
+            layers: vec![],
             device_exts: vec![],
         }
     }
 
+    /// Enables an instance extension by name, such as `VK_KHR_get_surface_capabilities2`.
+    /// Creating the instance fails with `NxError::ExtensionNotPresent` if it is not available.
+    /// Available extensions can be listed with `Instance::available_extensions`.
+    #[inline]
+    pub fn request_extension(&mut self, name: &str) {
+        self.push_extension(name);
+    }
+
+    /// Enables an instance layer by name.
+    /// Creating the instance fails with `NxError::LayerNotPresent` if it is not available.
+    /// Available layers can be listed with `Instance::available_layers`.
+    #[inline]
+    pub fn request_layer(&mut self, name: &str) {
+        if !self.layers.iter().any(|x| x == name) {
+            self.layers.push(name.to_owned());
+        }
+    }
+
+    #[doc(hidden)]
+    fn push_extension(&mut self, name: &str) {
+        if !self.extensions.iter().any(|x| x == name) {
+            self.extensions.push(name.to_owned());
+        }
+    }
+
+    #[doc(hidden)]
+    fn push_device_extension(&mut self, feature: DeviceFeature) {
+        if !self.device_exts.iter().any(|x| x.name() == feature.name()) {
+            self.device_exts.push(feature);
+        }
+    }
+
     /// Allows surfaces to be created.
     /// If this option is not enabled when creating an instance,
     /// Vulkan will force a termination at its convenience when initializing the surface.
+    /// `VK_KHR_swapchain` is enabled on every device created from the instance.
     /// **"window" feature is required.**
     #[cfg(feature = "window")]
     #[inline]
@@ -38,14 +87,25 @@ impl InstanceFeature {
     ) -> NxResult<()> {
         let ext = match ash_window::enumerate_required_extensions(handle.raw_display_handle()) {
             Ok(x) => x,
-            Err(e) => return Err(NxError::InternalError(e)),
+            Err(e) => return Err(NxError::vulkan("vkEnumerateInstanceExtensionProperties", e)),
         };
         for i in ext {
-            self.extensions.push(*i);
+            self.push_extension(&unsafe { std::ffi::CStr::from_ptr(*i) }.to_string_lossy());
         }
-        self.device_exts.push(DeviceFeature::Swapchain);
+        self.push_device_extension(DeviceFeature::Swapchain);
         Ok(())
     }
+
+    /// Allows headless surfaces to be created with `Surface::headless`.
+    /// This does not require a window or a display, so swapchains can be used on CI machines
+    /// with a software driver such as lavapipe.
+    /// `VK_KHR_swapchain` is enabled on every device created from the instance.
+    #[inline]
+    pub fn use_headless_surface(&mut self) {
+        self.push_extension(&ash::extensions::khr::Surface::name().to_string_lossy());
+        self.push_extension(&ash::extensions::ext::HeadlessSurface::name().to_string_lossy());
+        self.push_device_extension(DeviceFeature::Swapchain);
+    }
 }
 
 impl Default for InstanceFeature {
@@ -57,6 +117,17 @@ impl Default for InstanceFeature {
 /// Object that allows building windows.
 pub struct InstanceBuilder {
     feature: InstanceFeature,
+    validation: ValidationDescriptor,
+    sink: Box<dyn DebugMessageSink>,
+    application_name: String,
+    application_version: Version,
+    engine_name: String,
+    engine_version: Version,
+    api_version: Version,
+    min_api_version: Version,
+    host_allocator: Option<Box<dyn HostAllocator>>,
+    #[cfg(feature = "loaded")]
+    loader_path: Option<PathBuf>,
 }
 
 impl InstanceBuilder {
@@ -64,52 +135,254 @@ impl InstanceBuilder {
     pub fn new() -> Self {
         Self {
             feature: Default::default(),
+            validation: Default::default(),
+            sink: Box::new(LogSink),
+            application_name: String::new(),
+            application_version: Version::new(0, 0, 0),
+            engine_name: String::from("Nexg"),
+            engine_version: Version::new(0, 1, 1),
+            api_version: Version::V1_3,
+            min_api_version: Version::V1_0,
+            host_allocator: None,
+            #[cfg(feature = "loaded")]
+            loader_path: None,
         }
     }
 
+    /// Specifies the name of the application. Anything after a nul character is dropped.
+    pub fn application_name(mut self, name: &str) -> Self {
+        self.application_name = name.to_owned();
+        self
+    }
+
+    /// Specifies the version of the application.
+    pub fn application_version(mut self, version: Version) -> Self {
+        self.application_version = version;
+        self
+    }
+
+    /// Specifies the name of the engine. Anything after a nul character is dropped.
+    pub fn engine_name(mut self, name: &str) -> Self {
+        self.engine_name = name.to_owned();
+        self
+    }
+
+    /// Specifies the version of the engine.
+    pub fn engine_version(mut self, version: Version) -> Self {
+        self.engine_version = version;
+        self
+    }
+
+    /// Specifies the highest Vulkan version the application wants to use.
+    /// Defaults to **Vulkan 1.3**.
+    ///
+    /// The version actually used is the lower of this and the version supported by the loader,
+    /// and can be obtained with `Instance::api_version`.
+    pub fn api_version(mut self, version: Version) -> Self {
+        self.api_version = version;
+        self
+    }
+
+    /// Specifies the lowest Vulkan version the application can work with.
+    /// Defaults to **Vulkan 1.0**.
+    pub fn min_api_version(mut self, version: Version) -> Self {
+        self.min_api_version = version;
+        self
+    }
+
     /// Specifies the functionality used by the instance.
     pub fn feature(mut self, feature: InstanceFeature) -> Self {
         self.feature = feature;
         self
     }
 
+    /// Specifies the validation layer and debug messenger settings.
+    /// If not specified, `ValidationDescriptor::new()` is used.
+    pub fn validation(mut self, validation: ValidationDescriptor) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Specifies where the messages of the debug messenger are delivered.
+    /// If not specified, messages are forwarded to the `log` crate.
+    pub fn debug_sink(mut self, sink: impl DebugMessageSink + 'static) -> Self {
+        self.sink = Box::new(sink);
+        self
+    }
+
+    /// Specifies the allocator used for host memory allocations of the driver.
+    /// It is used by the instance, every device created from it and every object created from those.
+    /// If not specified, the driver uses its own allocator.
+    pub fn host_allocator(mut self, allocator: impl HostAllocator + 'static) -> Self {
+        self.host_allocator = Some(Box::new(allocator));
+        self
+    }
+
+    /// Specifies the path of the Vulkan loader library to load at runtime.
+    /// If not specified, the loader is searched for in the default locations of the platform.
+    /// **"loaded" feature is required.**
+    #[cfg(feature = "loaded")]
+    pub fn loader_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader_path = Some(path.into());
+        self
+    }
+
+    #[doc(hidden)]
+    fn load_entry(&self) -> NxResult<Entry> {
+        #[cfg(feature = "loaded")]
+        if let Some(path) = &self.loader_path {
+            return match unsafe { Entry::load_from(path) } {
+                Ok(x) => Ok(x),
+                Err(e) => Err(NxError::LoaderNotFound(format!(
+                    "{}: {}",
+                    path.display(),
+                    e
+                ))),
+            };
+        }
+        // "loaded" takes precedence, since "linked" is enabled by default.
+        #[cfg(feature = "loaded")]
+        match unsafe { Entry::load() } {
+            Ok(x) => Ok(x),
+            Err(e) => Err(NxError::LoaderNotFound(e.to_string())),
+        }
+        #[cfg(not(feature = "loaded"))]
+        {
+            Ok(Entry::linked())
+        }
+    }
+
     /// Create an instance.
-    /// This will fail if there is insufficient memory or if the device does not support **Vulkan 1.3** or **later**.
+    /// This will fail if the Vulkan loader cannot be loaded, if there is insufficient memory
+    /// or if the loader does not support the version specified by `min_api_version`.
     pub fn build(mut self) -> NxResult<Instance> {
-        self.feature
+        let validation = &self.validation;
+        if validation.messenger {
+            self.feature
+                .push_extension(&DebugUtils::name().to_string_lossy());
+        }
+        let validation_features = validation.enabled_features();
+        if !validation_features.is_empty() {
+            self.feature
+                .push_extension(&vk::ExtValidationFeaturesFn::name().to_string_lossy());
+        }
+        if validation.layer {
+            self.feature.request_layer(VALIDATION_LAYER_NAME);
+        }
+
+        let entry = self.load_entry()?;
+
+        let available_layers = enumerate_layers(&entry)?;
+        let mut available_extensions = enumerate_extensions(&entry, None)?;
+        for layer in &self.feature.layers {
+            if !available_layers.iter().any(|x| x.name() == layer) {
+                return Err(NxError::LayerNotPresent(layer.clone()));
+            }
+            // Layers may provide additional extensions, such as VK_EXT_validation_features.
+            let name = CString::new(layer.as_str()).unwrap();
+            available_extensions.extend(enumerate_extensions(&entry, Some(&name))?);
+        }
+        for extension in &self.feature.extensions {
+            if !available_extensions.iter().any(|x| x.name() == extension) {
+                return Err(NxError::ExtensionNotPresent(extension.clone()));
+            }
+        }
+        // Names have been checked against the available ones, so they cannot contain a nul byte.
+        let layer_names = self
+            .feature
+            .layers
+            .iter()
+            .map(|x| CString::new(x.as_str()).unwrap())
+            .collect::<Vec<CString>>();
+        let layers = layer_names
+            .iter()
+            .map(|x| x.as_ptr())
+            .collect::<Vec<*const c_char>>();
+        let extension_names = self
+            .feature
             .extensions
-            .push(DebugUtils::name().as_ptr() as *const c_char);
-        let entry = Entry::linked();
-        let create_info = InstanceCreateInfo::builder()
-            .enabled_extension_names(&self.feature.extensions)
-            .build();
-        let instance = match unsafe { entry.create_instance(&create_info, None) } {
+            .iter()
+            .map(|x| CString::new(x.as_str()).unwrap())
+            .collect::<Vec<CString>>();
+        let extensions = extension_names
+            .iter()
+            .map(|x| x.as_ptr())
+            .collect::<Vec<*const c_char>>();
+        let loader_version = match entry.try_enumerate_instance_version() {
+            Ok(Some(x)) => Version::from_raw(x),
+            Ok(None) => Version::V1_0,
+            Err(e) => return Err(NxError::vulkan("vkEnumerateInstanceVersion", e)),
+        };
+        let api_version = self.api_version.min(loader_version.without_patch());
+        if api_version < self.min_api_version {
+            return Err(NxError::UnsupportedVersion {
+                required: self.min_api_version,
+                available: loader_version,
+            });
+        }
+        let application_name = debug_string(&self.application_name);
+        let engine_name = debug_string(&self.engine_name);
+        let application_info = vk::ApplicationInfo::builder()
+            .application_name(&application_name)
+            .application_version(self.application_version.as_raw())
+            .engine_name(&engine_name)
+            .engine_version(self.engine_version.as_raw())
+            .api_version(api_version.as_raw());
+
+        let mut validation_info =
+            vk::ValidationFeaturesEXT::builder().enabled_validation_features(&validation_features);
+        let mut create_info = InstanceCreateInfo::builder()
+            .application_info(&application_info)
+            .enabled_extension_names(&extensions)
+            .enabled_layer_names(&layers);
+        if !validation_features.is_empty() {
+            create_info = create_info.push_next(&mut validation_info);
+        }
+        let host = self.host_allocator.map(HostCallbacks::new);
+        let allocation_callbacks = host.as_ref().map(|x| x.raw());
+        let instance = match unsafe { entry.create_instance(&create_info, allocation_callbacks) } {
             Ok(x) => x,
-            Err(e) => return Err(NxError::InternalError(e)),
+            Err(e) => return Err(NxError::vulkan("vkCreateInstance", e)),
         };
-        let mut debug_info = vk::DebugUtilsMessengerCreateInfoEXT::default();
-
-        debug_info.message_severity = vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
-            | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
-            | vk::DebugUtilsMessageSeverityFlagsEXT::INFO;
-        debug_info.message_type = vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
-            | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
-            | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE;
 
-        debug_info.pfn_user_callback = Some(vulkan_debug_callback);
-
-        let debug_utils = DebugUtils::new(&entry, &instance);
-        let debug_call_back =
-            match unsafe { debug_utils.create_debug_utils_messenger(&debug_info, None) } {
+        let debug_state = Arc::new(DebugState::new(self.sink, validation));
+        let (debug_utils, debug_call_back) = if validation.messenger {
+            let debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+                .message_severity(validation.severity_flags())
+                .message_type(validation.message_type_flags())
+                .pfn_user_callback(Some(vulkan_debug_callback))
+                .user_data(Arc::as_ptr(&debug_state) as *mut c_void);
+
+            let debug_utils = DebugUtils::new(&entry, &instance);
+            let debug_call_back = match unsafe {
+                debug_utils.create_debug_utils_messenger(&debug_info, allocation_callbacks)
+            } {
                 Ok(x) => x,
-                Err(e) => return Err(NxError::InternalError(e)),
+                Err(e) => {
+                    unsafe { instance.destroy_instance(allocation_callbacks) };
+                    return Err(NxError::vulkan("vkCreateDebugUtilsMessengerEXT", e));
+                }
             };
+            (Some(debug_utils), debug_call_back)
+        } else {
+            (None, DebugUtilsMessengerEXT::null())
+        };
         Ok(Instance {
+            owner: Arc::new(InstanceOwner {
+                instance: instance.clone(),
+                _entry: entry.clone(),
+                debug_utils,
+                debug_call_back,
+                _debug_state: debug_state.clone(),
+                host: host.clone(),
+            }),
             instance,
             entry,
             device_exts: self.feature.device_exts,
-            debug_utils,
-            debug_call_back,
+            extensions: self.feature.extensions,
+            debug_state,
+            api_version,
+            host,
         })
     }
 }
@@ -120,6 +393,8 @@ impl Default for InstanceBuilder {
     }
 }
 
+/// Stores the queue capabilities requested with `Instance::request_connecters`.
+/// Capabilities set to `false` are not required.
 pub struct RequestConnecterDescriptor {
     is_graphic_support: bool,
     is_compute_support: bool,
@@ -159,21 +434,50 @@ impl Default for RequestConnecterDescriptor {
     }
 }
 
+/// Destroys the `ash::Instance` once the `Instance` and every device and surface created from it are dropped.
+#[doc(hidden)]
+pub(crate) struct InstanceOwner {
+    instance: ash::Instance,
+    /// Keeps the loader loaded while the instance is alive.
+    _entry: Entry,
+    debug_utils: Option<DebugUtils>,
+    debug_call_back: DebugUtilsMessengerEXT,
+    /// Read by the debug messenger until it is destroyed.
+    _debug_state: Arc<DebugState>,
+    host: Option<Arc<HostCallbacks>>,
+}
+
+impl Drop for InstanceOwner {
+    fn drop(&mut self) {
+        let callbacks = self.host.as_ref().map(|x| x.raw());
+        unsafe {
+            if let Some(debug_utils) = self.debug_utils.as_ref() {
+                debug_utils.destroy_debug_utils_messenger(self.debug_call_back, callbacks);
+            }
+            self.instance.destroy_instance(callbacks)
+        }
+    }
+}
+
 pub struct Instance {
     pub(crate) instance: ash::Instance,
     pub(crate) entry: Entry,
+    #[doc(hidden)]
+    pub(crate) owner: Arc<InstanceOwner>,
 
     pub(crate) device_exts: Vec<DeviceFeature>,
+    extensions: Vec<String>,
 
-    debug_utils: DebugUtils,
-    debug_call_back: DebugUtilsMessengerEXT,
+    pub(crate) debug_state: Arc<DebugState>,
+    api_version: Version,
+    pub(crate) host: Option<Arc<HostCallbacks>>,
 }
 
 impl Instance {
     /// Enumerate available connectors.
     /// You can get the appropriate connector by getting the QueueFamilyProperties from the connector.
     /// # Example
-    /// ```
+    /// ```no_run
     /// use nexg::{InstanceFeature,InstanceBuilder};
     ///
     ///  let feature = InstanceFeature::empty();
@@ -199,11 +503,16 @@ impl Instance {
     ///
     ///  let device = connecter.create_device(&instance, index).unwrap();
     /// ```
-    #[deprecated(since = "0.1.1", note = "Please use request_connecters")]
+    #[deprecated(since = "0.1.1", note = "Please use select_connecter")]
     pub fn enumerate_connecters(&self) -> NxResult<Vec<DeviceConnecter>> {
+        self.physical_devices()
+    }
+
+    #[doc(hidden)]
+    fn physical_devices(&self) -> NxResult<Vec<DeviceConnecter>> {
         let devices = match unsafe { self.instance.enumerate_physical_devices() } {
             Ok(x) => x,
-            Err(e) => return Err(NxError::InternalError(e)),
+            Err(e) => return Err(NxError::vulkan("vkEnumeratePhysicalDevices", e)),
         };
         let devices = devices
             .iter()
@@ -216,42 +525,35 @@ impl Instance {
         }
     }
 
+    /// Find a connecter for each descriptor.
+    /// The first physical device with a queue family whose graphics, compute and transfer support
+    /// exactly matches the descriptor is returned, and `get_queue_family_index` of the connecter returns that family.
+    /// For example, a descriptor requesting only transfer finds a dedicated transfer family.
+    /// Use `select_connecter` to accept any family supporting the requested capabilities.
     pub fn request_connecters(
         &self,
         descriptors: &[RequestConnecterDescriptor],
     ) -> NxResult<Vec<DeviceConnecter>> {
+        let connecters = self.physical_devices()?;
         let mut connecter = vec![];
         for desc in descriptors {
-            let mut connecters = self.enumerate_connecters()?;
-            let mut index = 0;
-            let mut count = 0;
-            for i in &connecters {
-                let properties = i.get_queue_family_properties(self).unwrap();
-                for (n, i) in properties.iter().enumerate() {
-                    count = 0;
-                    if i.is_graphic_support() == desc.is_graphic_support {
-                        count += 1;
-                    }
-                    if i.is_compute_support() == desc.is_compute_support {
-                        count += 1;
-                    }
-                    if i.is_transfer_support() == desc.is_transfer_support {
-                        count += 1;
-                    }
-
-                    if count == 3 {
-                        index = n;
-                        break;
-                    }
+            let mut found = None;
+            for c in &connecters {
+                let properties = c.get_queue_family_properties(self)?;
+                let index = properties.iter().position(|i| {
+                    i.is_graphic_support() == desc.is_graphic_support
+                        && i.is_compute_support() == desc.is_compute_support
+                        && i.is_transfer_support() == desc.is_transfer_support
+                });
+                if let Some(index) = index {
+                    found = Some(DeviceConnecter(c.0, index));
+                    break;
                 }
             }
-            if count != 3 {
-                return Err(NxError::NoValue);
+            match found {
+                Some(x) => connecter.push(x),
+                None => return Err(NxError::NoValue),
             }
-
-            let mut c = connecters[index];
-            c.1 = index;
-            connecter.push(c);
         }
 
         Ok(connecter)
@@ -263,12 +565,89 @@ impl Instance {
         note = "Use enumerate_connecters() to manually get the appropriate one."
     )]
     pub fn default_connector(&self) -> DeviceConnecter {
-        let devices = self.enumerate_connecters().unwrap();
+        let devices = self.physical_devices().unwrap();
         devices[0]
     }
 
+    /// Choose the physical device with the highest score among those meeting the requirements of the selector.
+    /// Returns the connecter together with the queue families chosen for it.
+    /// `get_queue_family_index` of the connecter returns the graphics family if there is one.
+    pub fn select_connecter(
+        &self,
+        selector: &ConnecterSelector,
+    ) -> NxResult<(DeviceConnecter, QueueFamilyIndices)> {
+        let mut best: Option<(i64, DeviceConnecter, QueueFamilyIndices)> = None;
+        for connecter in self.physical_devices()? {
+            let (score, indices) = match selector.evaluate(self, connecter)? {
+                Some(x) => x,
+                None => continue,
+            };
+            match &best {
+                Some(x) if score <= x.0 => {}
+                _ => best = Some((score, connecter, indices)),
+            }
+        }
+        match best {
+            Some((_, connecter, indices)) => {
+                let index = indices
+                    .graphics()
+                    .or(indices.compute())
+                    .or(indices.transfer())
+                    .unwrap_or(0);
+                Ok((DeviceConnecter(connecter.0, index), indices))
+            }
+            None => Err(NxError::NoSuitableDevice),
+        }
+    }
+
+    /// Get the instance layers available on this system.
+    pub fn available_layers(&self) -> NxResult<Vec<LayerProperties>> {
+        enumerate_layers(&self.entry)
+    }
+
+    /// Get the instance extensions available on this system.
+    /// Extensions provided by layers are not included.
+    pub fn available_extensions(&self) -> NxResult<Vec<ExtensionProperties>> {
+        enumerate_extensions(&self.entry, None)
+    }
+
+    /// Get the instance extensions provided by a layer.
+    pub fn available_layer_extensions(&self, layer: &str) -> NxResult<Vec<ExtensionProperties>> {
+        let name = match CString::new(layer) {
+            Ok(x) => x,
+            Err(_) => return Err(NxError::LayerNotPresent(layer.to_owned())),
+        };
+        enumerate_extensions(&self.entry, Some(&name))
+    }
+
+    /// Check to see if an instance extension was enabled when the instance was created.
+    pub fn is_extension_enabled(&self, name: &str) -> bool {
+        self.extensions.iter().any(|x| x == name)
+    }
+
+    /// Returns the first validation error reported since the last fallible call.
+    /// Does nothing unless `ValidationDescriptor::on_error` was set to `Panic` or `Error`.
+    pub fn check_validation(&self) -> NxResult<()> {
+        self.debug_state.take_pending()
+    }
+
+    /// Get the Vulkan version negotiated when the instance was created.
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Get the highest Vulkan version supported by the loader.
+    pub fn loader_version(&self) -> NxResult<Version> {
+        match self.entry.try_enumerate_instance_version() {
+            Ok(Some(x)) => Ok(Version::from_raw(x)),
+            Ok(None) => Ok(Version::V1_0),
+            Err(e) => Err(NxError::vulkan("vkEnumerateInstanceVersion", e)),
+        }
+    }
+
     /// Get the version of Vulkan currently in use.
     /// This may not be possible to obtain.
+    #[deprecated(since = "0.1.1", note = "Please use api_version or loader_version")]
     pub fn vulkan_version(&self) -> Option<String> {
         match self.entry.try_enumerate_instance_version() {
             Ok(v) => match v {
@@ -289,12 +668,59 @@ impl Instance {
         &self,
         connecter: DeviceConnecter,
         info: &DeviceCreateInfo,
+        features: FeatureSet,
+        mut queues: QueueMap,
+        device_fault: bool,
     ) -> NxResult<Device> {
-        let device = match unsafe { self.instance.create_device(connecter.0, info, None) } {
+        self.check_validation()?;
+        let device = match unsafe {
+            self.instance
+                .create_device(connecter.0, info, self.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => return Err(NxError::InternalError(e)),
+            Err(e) => return Err(NxError::vulkan("vkCreateDevice", e)),
         };
-        Ok(Device::from(device))
+        let api_version = connecter.api_version(self);
+        let debug_utils = self
+            .is_extension_enabled(&DebugUtils::name().to_string_lossy())
+            .then(|| DebugUtils::new(&self.entry, &self.instance));
+        let fault_fn = device_fault.then(|| {
+            vk::ExtDeviceFaultFn::load(|name| unsafe {
+                std::mem::transmute(
+                    self.instance
+                        .get_device_proc_addr(device.handle(), name.as_ptr()),
+                )
+            })
+        });
+        let loss = DeviceLoss::new(device.handle(), fault_fn);
+        queues.record_granularities(unsafe {
+            &self
+                .instance
+                .get_physical_device_queue_family_properties(connecter.0)
+        });
+        let allocator = MemoryAllocator::new(
+            &self.get_memory_properties(connecter.0),
+            self.get_properties(connecter.0)
+                .limits
+                .non_coherent_atom_size,
+        );
+        Ok(Device::from(
+            device,
+            self.owner.clone(),
+            self.debug_state.clone(),
+            api_version,
+            self.host.clone(),
+            debug_utils,
+            features,
+            queues,
+            loss,
+            allocator,
+        ))
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks> {
+        self.host.as_ref().map(|x| x.raw())
     }
 
     #[doc(hidden)]
@@ -317,6 +743,17 @@ impl Instance {
         }
     }
 
+    #[doc(hidden)]
+    pub(crate) fn get_properties(
+        &self,
+        physical_device: PhysicalDevice,
+    ) -> PhysicalDeviceProperties {
+        unsafe {
+            self.instance
+                .get_physical_device_properties(physical_device)
+        }
+    }
+
     #[doc(hidden)]
     pub(crate) fn get_memory_properties(
         &self,
@@ -328,9 +765,3 @@ impl Instance {
         }
     }
 }
-
-impl Drop for Instance {
-    fn drop(&mut self) {
-        unsafe { self.instance.destroy_instance(None) }
-    }
-}
@@ -0,0 +1,144 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+
+use crate::{DebugObject, DebugState};
+
+#[doc(hidden)]
+struct LiveObject {
+    name: Option<String>,
+    backtrace: Backtrace,
+    /// Order of creation, so that leaks are reported in the order they were made.
+    order: u64,
+}
+
+#[doc(hidden)]
+#[derive(Default)]
+struct Registry {
+    created: u64,
+    objects: HashMap<(vk::ObjectType, u64), LiveObject>,
+}
+
+/// Objects created from a device that were not destroyed yet.
+/// Only tracked in debug builds; in release builds every method does nothing.
+#[doc(hidden)]
+pub(crate) struct LiveObjects {
+    registry: Mutex<Registry>,
+}
+
+impl LiveObjects {
+    pub(crate) fn new() -> Self {
+        Self {
+            registry: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn insert(&self, object_type: vk::ObjectType, handle: u64, name: Option<&str>) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let mut registry = self.registry.lock().unwrap();
+        registry.created += 1;
+        let object = LiveObject {
+            name: name.map(|x| x.to_owned()),
+            // Only captured if enabled with `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`.
+            backtrace: Backtrace::capture(),
+            order: registry.created,
+        };
+        registry.objects.insert((object_type, handle), object);
+    }
+
+    pub(crate) fn rename(&self, object_type: vk::ObjectType, handle: u64, name: &str) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        if let Some(x) = self
+            .registry
+            .lock()
+            .unwrap()
+            .objects
+            .get_mut(&(object_type, handle))
+        {
+            x.name = Some(name.to_owned());
+        }
+    }
+
+    pub(crate) fn remove(&self, object_type: vk::ObjectType, handle: u64) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        self.registry
+            .lock()
+            .unwrap()
+            .objects
+            .remove(&(object_type, handle));
+    }
+
+    /// Get the objects still alive, in the order they were created.
+    pub(crate) fn objects(&self) -> Vec<DebugObject> {
+        self.sorted(|object_type, handle, x| DebugObject::new(object_type, handle, x.name.clone()))
+    }
+
+    /// Reports every object still alive as a warning.
+    pub(crate) fn report(&self, debug_state: &DebugState) {
+        for (object, backtrace) in self.sorted(|object_type, handle, x| {
+            let backtrace = match x.backtrace.status() {
+                BacktraceStatus::Captured => format!("Created at:\n{}", x.backtrace),
+                _ => "Set RUST_BACKTRACE=1 to record where it was created.".to_owned(),
+            };
+            (
+                DebugObject::new(object_type, handle, x.name.clone()),
+                backtrace,
+            )
+        }) {
+            debug_state.report_leak(object, &backtrace);
+        }
+    }
+
+    fn sorted<T>(&self, f: impl Fn(vk::ObjectType, u64, &LiveObject) -> T) -> Vec<T> {
+        let registry = self.registry.lock().unwrap();
+        let mut sorted = registry.objects.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|x| x.1.order);
+        sorted
+            .into_iter()
+            .map(|((object_type, handle), x)| f(*object_type, *handle, x))
+            .collect()
+    }
+}
+
+/// Held by every handle of a `Device` given to the user.
+/// Objects hold handles that are not counted, so that dropping the last handle of the user reports the objects still alive.
+#[doc(hidden)]
+pub(crate) struct DeviceUsers {
+    pub(crate) live: Arc<LiveObjects>,
+    pub(crate) debug_state: Arc<DebugState>,
+}
+
+impl Drop for DeviceUsers {
+    fn drop(&mut self) {
+        self.live.report(&self.debug_state);
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_objects_until_removed() {
+        let live = LiveObjects::new();
+        live.insert(vk::ObjectType::BUFFER, 1, Some("vertices"));
+        live.insert(vk::ObjectType::FENCE, 2, None);
+        live.insert(vk::ObjectType::IMAGE, 3, None);
+        live.rename(vk::ObjectType::IMAGE, 3, "target");
+        live.remove(vk::ObjectType::FENCE, 2);
+
+        let objects = live.objects();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].name(), Some("vertices"));
+        assert_eq!(objects[1].object_type(), "IMAGE");
+        assert_eq!(objects[1].name(), Some("target"));
+    }
+}
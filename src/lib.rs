@@ -13,33 +13,41 @@
 //!
 //! # Set up Device
 //!
-//! ```
-//! use nexg::{InstanceFeature,InstanceBuilder};
+//! ```no_run
+//! use nexg::{ConnecterSelector, InstanceFeature, InstanceBuilder};
 //!
 //!  let feature = InstanceFeature::empty();
 //!  let instance = InstanceBuilder::new().feature(feature).build().unwrap();
-//!  let connecters = instance.enumerate_connecters().unwrap();
-//!  let mut index = 0;
-//!  let mut found_device = false;
-//!  for i in &connecters {
-//!     let properties = i.get_queue_family_properties(&instance).unwrap();
-//!     for i in properties {
-//!         if i.is_graphic_support() {
-//!             index = 0;
-//!             found_device = true;
-//!             break;
-//!         }
-//!     }
-//!  }
-//!  if !found_device {
-//!     panic!("No suitable device found.")
-//!  }
-//!
-//!  let connecter = connecters[index];
+//!  let (connecter, indices) = instance
+//!     .select_connecter(&ConnecterSelector::new())
+//!     .unwrap();
+//!  let index = indices.graphics().unwrap();
 //!
 //!  let device = connecter.create_device(&instance, index).unwrap();
 //! ```
 //!
+//! # Thread safety
+//!
+//! Vulkan requires some objects to be externally synchronized. Nexg enforces this with `Send` and `Sync`:
+//!
+//! - `Instance`, `Device`, `Queue`, `CommandPool`, `CommandRecorder` and immutable objects such as
+//!   `Pipeline`, `RenderPass` or `ImageView` are `Send + Sync`.
+//!   `Queue` serializes submissions and presentations, and the recorders of a `CommandPool`
+//!   serialize recording, so clones and references can be used from any thread.
+//! - `Fence`, `Semaphore`, `Buffer`, `Image`, `ResourcePool`, `Resource` and `Swapchain` are only `Send`,
+//!   because their methods or the submissions using them modify the object.
+//!   Move them to another thread, or wrap them in a `Mutex` to share them.
+//!
+//! # Loading Vulkan
+//!
+//! By default the Vulkan loader is linked at build time ("linked" feature).
+//! With the "loaded" feature it is loaded at runtime instead, even if "linked" is also enabled.
+//! To build without the loader, disable the default features:
+//!
+//! ```toml
+//! nexg = { version = "0.1", default-features = false, features = ["window", "loaded"] }
+//! ```
+//!
 //! ## Examples
 //!
 //! ### Triangle
@@ -50,49 +58,104 @@
 #[macro_use]
 extern crate log;
 
-use std::ffi::c_char;
-use std::{borrow::Cow, ffi::CStr};
+use std::ffi::{c_char, CString};
 
-use ash::vk::{
-    self, DebugUtilsMessageSeverityFlagsEXT, DeviceCreateInfo, DeviceQueueCreateInfo, QueueFlags,
-};
+use ash::vk::{self, DeviceCreateInfo, DeviceQueueCreateInfo, QueueFlags};
 
+#[doc(hidden)]
+mod allocator;
 mod buffer;
+mod debug;
 mod device;
+mod extension;
+mod fault;
+mod features;
 mod fence;
+mod format;
 mod frame_buffer;
+#[doc(hidden)]
+mod garbage;
+mod host_memory;
 mod image;
 mod instance;
 #[doc(hidden)]
+mod leak;
+#[doc(hidden)]
 mod mem;
+mod memory;
 mod pipeline;
+mod properties;
 mod queue;
 mod recorder;
 mod renderpass;
+mod result;
+mod selector;
 mod shader;
-#[cfg(feature = "window")]
+mod staging;
 mod surface;
-#[cfg(feature = "window")]
 mod swapchain;
 mod sync;
+mod version;
+
+// Checks the thread safety documented above.
+const _: () = {
+    const fn send_sync<T: Send + Sync>() {}
+    const fn send<T: Send>() {}
+    send_sync::<Instance>();
+    send_sync::<Device>();
+    send_sync::<DeviceConnecter>();
+    send_sync::<Queue>();
+    send_sync::<CommandPool>();
+    send_sync::<CommandRecorder>();
+    send_sync::<Pipeline>();
+    send_sync::<PipelineLayout>();
+    send_sync::<ResourceLayout>();
+    send_sync::<Shader>();
+    send_sync::<RenderPass>();
+    send_sync::<FrameBuffer>();
+    send_sync::<ImageView>();
+    send_sync::<Surface>();
+    send_sync::<NxError>();
+    send::<Fence>();
+    send::<Semaphore>();
+    send::<Buffer>();
+    send::<Image>();
+    send::<ResourcePool>();
+    send::<Resource>();
+    send::<Swapchain>();
+    send::<StagingBelt>();
+};
 
+pub(crate) use allocator::*;
 pub use buffer::*;
+pub use debug::*;
 pub use device::*;
+pub use extension::*;
+pub use fault::*;
+pub use features::*;
 pub use fence::*;
+pub use format::*;
 pub use frame_buffer::*;
+pub(crate) use garbage::*;
+pub use host_memory::*;
 pub use image::*;
 pub use instance::*;
+pub(crate) use leak::*;
 pub(crate) use mem::*;
+pub use memory::*;
 pub use pipeline::*;
+pub use properties::*;
 pub use queue::*;
 pub use recorder::*;
 pub use renderpass::*;
+pub use result::*;
+pub use selector::*;
 pub use shader::*;
-#[cfg(feature = "window")]
+pub use staging::*;
 pub use surface::*;
-#[cfg(feature = "window")]
 pub use swapchain::*;
 pub use sync::*;
+pub use version::*;
 
 use thiserror::Error;
 
@@ -107,16 +170,58 @@ pub enum NxError {
     NoValue,
     #[error("Device does not support this operation.")]
     HardwareError,
-    #[error("Out of host memory")]
-    OutOfHostMemory,
-    #[error("Out of device memory")]
-    OutOfDeviceMemory,
-    #[error("Failed to map memory.")]
-    MemoryMapFailed,
-    #[error("`{0}`")]
-    InternalError(#[from] ash::vk::Result),
+    /// A Vulkan command failed.
+    #[error("`{call}` failed: {error}")]
+    Vulkan { call: &'static str, error: VkError },
     #[error("`{0}`")]
     IoError(String),
+    #[error("Layer `{0}` is not available.")]
+    LayerNotPresent(String),
+    #[error("Extension `{0}` is not available.")]
+    ExtensionNotPresent(String),
+    #[error("Vulkan {required} is required, but only {available} is available.")]
+    UnsupportedVersion {
+        required: Version,
+        available: Version,
+    },
+    #[error("Validation error: {}", .0.message())]
+    Validation(Box<DebugMessage>),
+    /// The Vulkan loader could not be loaded at runtime.
+    #[error("Vulkan loader not found: {0}")]
+    LoaderNotFound(String),
+    #[error("No physical device meets the requirements.")]
+    NoSuitableDevice,
+    #[error("Feature `{0}` is not supported by the device.")]
+    FeatureNotSupported(Feature),
+    #[error("Queue {index} of queue family {family} is not available.")]
+    QueueNotAvailable { family: usize, index: usize },
+    /// The device was lost and must be recreated.
+    /// Holds the fault information if `VK_EXT_device_fault` is supported.
+    #[error("Device lost{}", .0.as_ref().map(|x| format!(": {}", x.description())).unwrap_or_default())]
+    DeviceLost(Option<Box<DeviceFault>>),
+    #[error("No memory type is suitable for the resource.")]
+    NoSuitableMemoryType,
+    /// The memory was allocated with `MemoryLocation::GpuOnly` or without `MemoryProperty::HostVisible`.
+    #[error("The memory is not host-visible and cannot be mapped.")]
+    MemoryNotMappable,
+    #[error("`{0}` must be specified in the descriptor.")]
+    MissingDescriptorField(&'static str),
+    /// Formats with both depth and stencil must be copied one aspect at a time.
+    #[error("Images of format `{0:?}` cannot be copied to or from a buffer.")]
+    FormatNotCopyable(ImageFormat),
+    #[error("Expected {expected} bytes of data, but {actual} were given.")]
+    DataSizeMismatch { expected: u64, actual: u64 },
+    #[error("A row of {0} bytes does not fit in the staging belt.")]
+    StagingBeltTooSmall(u64),
+    #[error("The range ends at byte {end}, past the end of the {size} bytes available.")]
+    OutOfBounds { end: u64, size: u64 },
+    #[error("{0} is not a valid sample count. It must be a power of two up to 64.")]
+    InvalidSampleCount(u32),
+    #[error("The region does not fit in mip level {mip_level} and array layer {array_layer} of the image.")]
+    RegionOutOfBounds { mip_level: u32, array_layer: u32 },
+    /// Transfer-only queue families may copy images in blocks of several texels.
+    #[error("Queue family {0} copies images in blocks larger than a texel.")]
+    ImageTransferGranularity(usize),
 }
 
 pub struct QueueFamilyProperties {
@@ -152,24 +257,128 @@ impl QueueFamilyProperties {
 pub struct DeviceConnecter(pub(crate) vk::PhysicalDevice, pub(crate) usize);
 
 impl DeviceConnecter {
-    /// Create a device.
+    /// Create a device with a single queue from `queue_family_index` and no optional features.
+    /// Use `request_device` to enable features.
     pub fn create_device(self, instance: &Instance, queue_family_index: usize) -> NxResult<Device> {
-        let extensions = &instance
+        self.request_device(
+            instance,
+            &DeviceDescriptor::new().queue_family_index(queue_family_index),
+        )
+    }
+
+    /// Create a device.
+    /// This will fail with `NxError::FeatureNotSupported` if a required feature is not supported.
+    ///
+    /// `VK_EXT_device_fault` is enabled whenever it is supported,
+    /// so that `NxError::DeviceLost` can report why the device was lost.
+    pub fn request_device(
+        self,
+        instance: &Instance,
+        descriptor: &DeviceDescriptor,
+    ) -> NxResult<Device> {
+        let supported = self.features(instance);
+        if let Some(feature) = descriptor.features.first_missing(&supported) {
+            return Err(NxError::FeatureNotSupported(feature));
+        }
+        let mut enabled = descriptor.features.clone();
+        for feature in descriptor.optional_features.iter() {
+            if supported.contains(feature) {
+                enabled.insert(feature);
+            }
+        }
+
+        let available = self.available_extensions(instance)?;
+        let mut extension_names = vec![];
+        let mut names = instance
             .device_exts
             .iter()
-            .map(|x| match x {
-                DeviceFeature::Swapchain => ash::extensions::khr::Swapchain::name().as_ptr(),
-            })
+            .map(|x| x.name())
+            .collect::<Vec<_>>();
+        for name in &descriptor.extensions {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        for name in names {
+            if !available.iter().any(|x| x.name() == name) {
+                return Err(NxError::ExtensionNotPresent(name));
+            }
+            extension_names.push(CString::new(name).unwrap());
+        }
+        let device_fault = is_device_fault_supported(instance, self, &available);
+        let fault_name = vk::ExtDeviceFaultFn::name();
+        if device_fault && !extension_names.iter().any(|x| x.as_c_str() == fault_name) {
+            extension_names.push(fault_name.to_owned());
+        }
+        let extensions = &extension_names
+            .iter()
+            .map(|x| x.as_ptr())
             .collect::<Vec<*const c_char>>();
-        let queue_infos = vec![DeviceQueueCreateInfo::builder()
-            .queue_family_index(queue_family_index as u32)
-            .queue_priorities(&[1.0])
-            .build()];
+        let (queues, requested) = QueueMap::resolve(descriptor)?;
+        let families = self.get_queue_family_properties(instance)?;
+        for family in &requested {
+            let available = families
+                .get(family.family_index)
+                .map_or(0, |x| x.queue_count as usize);
+            if family.priorities.len() > available {
+                return Err(NxError::QueueNotAvailable {
+                    family: family.family_index,
+                    index: available,
+                });
+            }
+        }
+        let queue_infos = requested
+            .iter()
+            .map(|x| {
+                DeviceQueueCreateInfo::builder()
+                    .queue_family_index(x.family_index as u32)
+                    .queue_priorities(&x.priorities)
+                    .build()
+            })
+            .collect::<Vec<DeviceQueueCreateInfo>>();
+
+        let version = self.api_version(instance);
+        let mut raw = RawFeatures::from_set(&enabled);
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder().features(raw.core);
+        if version >= Version::V1_2 {
+            features2 = features2
+                .push_next(&mut raw.vulkan11)
+                .push_next(&mut raw.vulkan12);
+        }
+        if version >= Version::V1_3 {
+            features2 = features2.push_next(&mut raw.vulkan13);
+        }
+        let mut fault_features = vk::PhysicalDeviceFaultFeaturesEXT::builder().device_fault(true);
+        if device_fault {
+            features2 = features2.push_next(&mut fault_features);
+        }
         let create_info = DeviceCreateInfo::builder()
             .queue_create_infos(&queue_infos)
-            .enabled_extension_names(extensions)
-            .build();
-        instance.create_device(self, &create_info)
+            .enabled_extension_names(extensions);
+        // Vulkan 1.0 only accepts the core features.
+        let create_info = if version >= Version::V1_1 {
+            create_info.push_next(&mut features2).build()
+        } else {
+            create_info.enabled_features(&features2.features).build()
+        };
+        instance.create_device(self, &create_info, enabled, queues, device_fault)
+    }
+
+    /// Get the features supported by the physical device.
+    ///
+    /// Features of the Vulkan 1.1, 1.2 and 1.3 structs are only reported
+    /// when both the instance and the device support the version given by `Feature::required_version`.
+    pub fn features(&self, instance: &Instance) -> FeatureSet {
+        RawFeatures::query(instance, *self).to_set()
+    }
+
+    /// Get the Vulkan version usable with this physical device.
+    /// This is the lower of the instance version and the version supported by the physical device.
+    pub fn api_version(&self, instance: &Instance) -> Version {
+        let properties = instance.get_properties(self.0);
+        instance
+            .api_version()
+            .min(Version::from_raw(properties.api_version).without_patch())
     }
 
     pub fn get_queue_family_properties(
@@ -183,6 +392,133 @@ impl DeviceConnecter {
         self.1
     }
 
+    /// Get the name of the physical device, such as the GPU model.
+    pub fn name(&self, instance: &Instance) -> String {
+        extension::to_string(&instance.get_properties(self.0).device_name)
+    }
+
+    /// Get the properties and limits of the physical device.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let properties = connecter.properties(&instance);
+    /// log::info!("{} ({:?})", properties.name(), properties.driver());
+    /// let alignment = properties.limits().min_uniform_buffer_offset_alignment();
+    /// ```
+    pub fn properties(&self, instance: &Instance) -> DeviceProperties {
+        DeviceProperties::query(instance, *self)
+    }
+
+    /// Get the type of the physical device.
+    pub fn device_type(&self, instance: &Instance) -> DeviceType {
+        instance.get_properties(self.0).device_type.into()
+    }
+
+    /// Get the memory heaps of the physical device, with their memory types.
+    ///
+    /// # Example
+    /// ```ignore
+    /// for heap in connecter.memory_heaps(&instance) {
+    ///     if let Some(available) = heap.available() {
+    ///         println!("heap {}: {} bytes left", heap.index(), available);
+    ///     }
+    /// }
+    /// ```
+    pub fn memory_heaps(&self, instance: &Instance) -> Vec<MemoryHeap> {
+        MemoryHeap::query(instance, *self)
+    }
+
+    /// Get the operations supported by a format.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let properties = connecter.format_properties(&instance, ImageFormat::R8G8B8A8Srgb);
+    /// if properties.optimal_tiling().contains(FormatFeature::ColorAttachmentBlend) {
+    ///     // The format can be used as a blended render target.
+    /// }
+    /// ```
+    pub fn format_properties(&self, instance: &Instance, format: ImageFormat) -> FormatProperties {
+        unsafe {
+            instance
+                .instance
+                .get_physical_device_format_properties(self.0, format.into())
+        }
+        .into()
+    }
+
+    /// Get the limits of images created with the format, dimension, tiling and usage of the descriptor.
+    /// Returns `None` if such images are not supported at all.
+    pub fn image_format_properties(
+        &self,
+        instance: &Instance,
+        descriptor: &ImageDescriptor,
+    ) -> NxResult<Option<ImageFormatProperties>> {
+        match unsafe {
+            instance
+                .instance
+                .get_physical_device_image_format_properties(
+                    self.0,
+                    descriptor.format.into(),
+                    descriptor.image_type.into(),
+                    descriptor.tiling.into(),
+                    descriptor.usage,
+                    vk::ImageCreateFlags::empty(),
+                )
+        } {
+            Ok(x) => Ok(Some(x.into())),
+            Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED) => Ok(None),
+            Err(e) => Err(NxError::vulkan(
+                "vkGetPhysicalDeviceImageFormatProperties",
+                e,
+            )),
+        }
+    }
+
+    /// Returns the first format of `candidates` that supports every operation of `features` with the given tiling.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let depth = connecter
+    ///     .find_format(
+    ///         &instance,
+    ///         &[ImageFormat::D32Sfloat, ImageFormat::D24UnormS8Uint, ImageFormat::D16Unorm],
+    ///         ImageTiling::Optimal,
+    ///         &[FormatFeature::DepthStencilAttachment],
+    ///     )
+    ///     .unwrap();
+    /// ```
+    pub fn find_format(
+        &self,
+        instance: &Instance,
+        candidates: &[ImageFormat],
+        tiling: ImageTiling,
+        features: &[FormatFeature],
+    ) -> Option<ImageFormat> {
+        candidates.iter().copied().find(|x| {
+            self.format_properties(instance, *x)
+                .tiling(tiling)
+                .contains_all(features)
+        })
+    }
+
+    /// Check to see if a queue family can present to the surface.
+    pub fn is_present_supported(
+        &self,
+        surface: &Surface,
+        queue_family_index: usize,
+    ) -> NxResult<bool> {
+        match unsafe {
+            surface.surface.get_physical_device_surface_support(
+                self.0,
+                queue_family_index as u32,
+                surface.khr,
+            )
+        } {
+            Ok(x) => Ok(x),
+            Err(e) => Err(NxError::vulkan("vkGetPhysicalDeviceSurfaceSupportKHR", e)),
+        }
+    }
+
     #[doc(hidden)]
     pub(crate) fn get_memory_properties(
         &self,
@@ -191,56 +527,63 @@ impl DeviceConnecter {
         instance.get_memory_properties(self.0)
     }
 
-    #[doc(hidden)]
-    #[cfg(feature = "window")]
-    pub(crate) fn is_support_swapchain(&self, instance: &Instance) -> bool {
-        let features = unsafe {
+    /// Get the device extensions supported by this physical device.
+    pub fn available_extensions(&self, instance: &Instance) -> NxResult<Vec<ExtensionProperties>> {
+        match unsafe {
             instance
                 .instance
                 .enumerate_device_extension_properties(self.0)
+        } {
+            Ok(x) => Ok(x.into_iter().map(ExtensionProperties::from).collect()),
+            Err(e) => Err(NxError::vulkan("vkEnumerateDeviceExtensionProperties", e)),
         }
-        .unwrap();
-        let mut support = false;
-        for i in features {
-            let name = unsafe { CStr::from_ptr(i.extension_name.as_ptr()) };
-            if name == ash::extensions::khr::Swapchain::name() {
-                support = true;
-            }
+    }
+
+    /// Check to see if a device extension is supported by this physical device.
+    pub fn is_extension_supported(&self, instance: &Instance, name: &str) -> bool {
+        match self.available_extensions(instance) {
+            Ok(x) => x.iter().any(|x| x.name() == name),
+            Err(_) => false,
         }
-        support
     }
 
     #[doc(hidden)]
-    #[cfg(feature = "window")]
-    pub(crate) fn get_surface_capabilities(&self, surface: &Surface) -> vk::SurfaceCapabilitiesKHR {
+    pub(crate) fn get_surface_capabilities(
+        &self,
+        surface: &Surface,
+    ) -> NxResult<vk::SurfaceCapabilitiesKHR> {
         unsafe {
             surface
                 .surface
                 .get_physical_device_surface_capabilities(self.0, surface.khr)
-                .unwrap()
         }
+        .map_err(|e| NxError::vulkan("vkGetPhysicalDeviceSurfaceCapabilitiesKHR", e))
     }
 
     #[doc(hidden)]
-    #[cfg(feature = "window")]
-    pub(crate) fn get_surface_formats(&self, surface: &Surface) -> Vec<vk::SurfaceFormatKHR> {
+    pub(crate) fn get_surface_formats(
+        &self,
+        surface: &Surface,
+    ) -> NxResult<Vec<vk::SurfaceFormatKHR>> {
         unsafe {
             surface
                 .surface
                 .get_physical_device_surface_formats(self.0, surface.khr)
-                .unwrap()
         }
+        .map_err(|e| NxError::vulkan("vkGetPhysicalDeviceSurfaceFormatsKHR", e))
     }
 
     #[doc(hidden)]
-    #[cfg(feature = "window")]
-    pub(crate) fn get_surface_present_modes(&self, surface: &Surface) -> Vec<vk::PresentModeKHR> {
+    pub(crate) fn get_surface_present_modes(
+        &self,
+        surface: &Surface,
+    ) -> NxResult<Vec<vk::PresentModeKHR>> {
         unsafe {
             surface
                 .surface
                 .get_physical_device_surface_present_modes(self.0, surface.khr)
-                .unwrap()
         }
+        .map_err(|e| NxError::vulkan("vkGetPhysicalDeviceSurfacePresentModesKHR", e))
     }
 }
 
@@ -297,41 +640,11 @@ impl From<Extent3d> for vk::Extent3D {
     }
 }
 
-#[doc(hidden)]
-unsafe extern "system" fn vulkan_debug_callback(
-    message_severity: DebugUtilsMessageSeverityFlagsEXT,
-    _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
-    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _user_data: *mut std::os::raw::c_void,
-) -> vk::Bool32 {
-    let callback_data = *p_callback_data;
-    let _message_id_number = callback_data.message_id_number;
-
-    let _message_id_name = if callback_data.p_message_id_name.is_null() {
-        Cow::from("")
-    } else {
-        CStr::from_ptr(callback_data.p_message_id_name).to_string_lossy()
-    };
-
-    let message = if callback_data.p_message.is_null() {
-        Cow::from("")
-    } else {
-        CStr::from_ptr(callback_data.p_message).to_string_lossy()
-    };
-
-    match message_severity {
-        DebugUtilsMessageSeverityFlagsEXT::INFO => info!("[Vulkan] {}", message),
-        DebugUtilsMessageSeverityFlagsEXT::WARNING => warn!("[Vulkan] {}", message),
-        DebugUtilsMessageSeverityFlagsEXT::VERBOSE => trace!("[Vulkan] {}", message),
-        DebugUtilsMessageSeverityFlagsEXT::ERROR => error!("[Vulkan] {}", message),
-        _ => todo!(),
-    }
-
-    vk::FALSE
-}
-
 /// Implement on objects that need to be destroyed.
 /// They are called from the instance or from the destroy method of the device.
+///
+/// Objects created from a device are also destroyed when dropped,
+/// so this is only needed to destroy them earlier.
 pub trait Destroy {
     /// Destroy objects using instance.
     fn instance(&self, instance: &Instance);
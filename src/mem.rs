@@ -1,124 +1,151 @@
-use crate::{Destroy, Device, Instance, NxError, NxResult};
-use ash::vk::{
-    MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, MemoryRequirements,
-    PhysicalDeviceMemoryProperties,
+use crate::{
+    Allocation, Destroy, Device, DeviceChild, Garbage, Instance, MemoryLocation, NxError, NxResult,
 };
+use ash::vk::{MappedMemoryRange, MemoryPropertyFlags};
 use std::ffi::c_void;
 
+/// Range of device memory bound to a buffer or an image, carved out of a block by the device's allocator.
+/// Host-visible memory stays mapped while it is alive.
 pub struct DeviceMemory {
-    pub(crate) memory: ash::vk::DeviceMemory,
+    pub(crate) allocation: Allocation,
+    owner: DeviceChild,
 }
 
 impl DeviceMemory {
-    fn alloc(
-        device: &ash::Device,
-        mem_props: PhysicalDeviceMemoryProperties,
-        mem_req: MemoryRequirements,
-    ) -> NxResult<ash::vk::DeviceMemory> {
-        let mut info = MemoryAllocateInfo::builder().allocation_size(mem_req.size);
-        let mut mem_found = false;
-
-        for i in 0..mem_props.memory_type_count {
-            if (mem_req.memory_type_bits & (1 << i)) != 0
-                && (mem_props.memory_types[i as usize].property_flags
-                    & MemoryPropertyFlags::HOST_VISIBLE)
-                    .as_raw()
-                    != 0
-            {
-                info.memory_type_index = i;
-                mem_found = true;
-            }
-        }
-
-        if !mem_found {
-            panic!("No suitable memory found");
-        }
-
-        match unsafe { device.allocate_memory(&info.build(), None) } {
-            Ok(x) => Ok(x),
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
-        }
-    }
-
+    /// `linear` is `false` for images with optimal tiling, which are kept apart from linear resources.
     pub fn alloc_image_memory(
-        device: &ash::Device,
+        device: &Device,
         image: ash::vk::Image,
-        mem_props: PhysicalDeviceMemoryProperties,
-        mem_req: MemoryRequirements,
+        location: MemoryLocation,
+        linear: bool,
     ) -> NxResult<Self> {
-        let memory = match Self::alloc(device, mem_props, mem_req) {
-            Ok(x) => x,
-            Err(e) => return Err(e),
+        let mem_req = unsafe { device.device.get_image_memory_requirements(image) };
+        let memory = Self {
+            allocation: device
+                .allocator()
+                .allocate(device, &mem_req, location, linear)?,
+            owner: DeviceChild::untracked(device),
         };
-        match unsafe { device.bind_image_memory(image, memory, 0) } {
+        match unsafe {
+            device.device.bind_image_memory(
+                image,
+                memory.allocation.memory,
+                memory.allocation.offset,
+            )
+        } {
             Ok(_) => {}
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkBindImageMemory", e)),
         }
-        Ok(Self { memory })
+        Ok(memory)
     }
 
     pub fn alloc_buffer_memory(
-        device: &ash::Device,
+        device: &Device,
         buffer: ash::vk::Buffer,
-        mem_props: PhysicalDeviceMemoryProperties,
-        mem_req: MemoryRequirements,
+        location: MemoryLocation,
     ) -> NxResult<Self> {
-        let memory = match Self::alloc(device, mem_props, mem_req) {
-            Ok(x) => x,
-            Err(e) => return Err(e),
+        let mem_req = unsafe { device.device.get_buffer_memory_requirements(buffer) };
+        let memory = Self {
+            allocation: device
+                .allocator()
+                .allocate(device, &mem_req, location, true)?,
+            owner: DeviceChild::untracked(device),
         };
-        match unsafe { device.bind_buffer_memory(buffer, memory, 0) } {
+        match unsafe {
+            device.device.bind_buffer_memory(
+                buffer,
+                memory.allocation.memory,
+                memory.allocation.offset,
+            )
+        } {
             Ok(_) => {}
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkBindBufferMemory", e)),
         }
-        Ok(Self { memory })
+        Ok(memory)
     }
 
-    pub fn size(&self, device: &Device) -> u64 {
-        unsafe { device.device.get_device_memory_commitment(self.memory) }
+    /// Size of the range, which may be larger than the resource.
+    pub fn size(&self) -> u64 {
+        self.allocation.size
     }
 
-    pub fn map(&self, device: &Device, size: u64) -> NxResult<*mut c_void> {
-        match unsafe {
-            device
-                .device
-                .map_memory(self.memory, 0, size, MemoryMapFlags::empty())
-        } {
-            Ok(x) => Ok(x),
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                ash::vk::Result::ERROR_MEMORY_MAP_FAILED => Err(NxError::MemoryMapFailed),
-                _ => Err(NxError::Unknown),
-            }?,
+    /// Returns the host address of the memory.
+    /// Fails with `NxError::MemoryNotMappable` if the memory is not host-visible.
+    /// Memory that is not host-coherent is invalidated so that writes from the GPU are visible.
+    pub fn map(&self, device: &Device) -> NxResult<*mut c_void> {
+        let data = self.mapped()?;
+        if !self.is_coherent() {
+            unsafe {
+                device
+                    .device
+                    .invalidate_mapped_memory_ranges(&[self.range()])
+            }
+            .map_err(|e| device.error("vkInvalidateMappedMemoryRanges", e))?;
+        }
+        Ok(data)
+    }
+
+    /// Makes writes from the host visible to the GPU. Does nothing for host-coherent memory.
+    pub fn flush(&self, device: &Device) -> NxResult<()> {
+        if self.is_coherent() {
+            return Ok(());
         }
+        unsafe { device.device.flush_mapped_memory_ranges(&[self.range()]) }
+            .map_err(|e| device.error("vkFlushMappedMemoryRanges", e))
     }
 
-    pub fn unmap(&self, device: &Device) {
-        unsafe {
-            device.device.unmap_memory(self.memory);
+    /// Makes writes from the host to `size` bytes at `offset` visible to the GPU.
+    #[doc(hidden)]
+    pub(crate) fn flush_range(&self, device: &Device, offset: u64, size: u64) -> NxResult<()> {
+        if self.is_coherent() {
+            return Ok(());
         }
+        let (offset, size) =
+            self.allocation
+                .atom_range(offset, size, device.allocator().atom_size());
+        let range = MappedMemoryRange::builder()
+            .memory(self.allocation.memory)
+            .offset(offset)
+            .size(size)
+            .build();
+        unsafe { device.device.flush_mapped_memory_ranges(&[range]) }
+            .map_err(|e| device.error("vkFlushMappedMemoryRanges", e))
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn mapped(&self) -> NxResult<*mut c_void> {
+        match self.allocation.mapped() {
+            Some(x) => Ok(x as *mut c_void),
+            None => Err(NxError::MemoryNotMappable),
+        }
+    }
+
+    fn is_coherent(&self) -> bool {
+        self.allocation
+            .flags
+            .contains(MemoryPropertyFlags::HOST_COHERENT)
+    }
+
+    /// The range is aligned to `nonCoherentAtomSize` by the allocator.
+    fn range(&self) -> MappedMemoryRange {
+        MappedMemoryRange::builder()
+            .memory(self.allocation.memory)
+            .offset(self.allocation.offset)
+            .size(self.allocation.size)
+            .build()
     }
 }
 
 impl Destroy for DeviceMemory {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.free_memory(self.memory, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::Memory(self.allocation));
+    }
+}
+
+impl Drop for DeviceMemory {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::Memory(self.allocation));
     }
 }
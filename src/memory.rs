@@ -0,0 +1,186 @@
+use ash::vk::MemoryPropertyFlags;
+
+/// Property of a memory type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MemoryProperty {
+    /// Memory close to the GPU.
+    DeviceLocal,
+    /// Memory that can be mapped.
+    HostVisible,
+    /// Writes are visible without flushing.
+    HostCoherent,
+    /// Memory cached on the host, fast to read from the CPU.
+    HostCached,
+    LazilyAllocated,
+    Protected,
+}
+
+impl From<MemoryProperty> for MemoryPropertyFlags {
+    fn from(value: MemoryProperty) -> Self {
+        match value {
+            MemoryProperty::DeviceLocal => MemoryPropertyFlags::DEVICE_LOCAL,
+            MemoryProperty::HostVisible => MemoryPropertyFlags::HOST_VISIBLE,
+            MemoryProperty::HostCoherent => MemoryPropertyFlags::HOST_COHERENT,
+            MemoryProperty::HostCached => MemoryPropertyFlags::HOST_CACHED,
+            MemoryProperty::LazilyAllocated => MemoryPropertyFlags::LAZILY_ALLOCATED,
+            MemoryProperty::Protected => MemoryPropertyFlags::PROTECTED,
+        }
+    }
+}
+
+/// Properties required and preferred for a `MemoryLocation::Custom`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryRequest {
+    required: MemoryPropertyFlags,
+    preferred: MemoryPropertyFlags,
+}
+
+impl MemoryRequest {
+    /// Initializes a request without required or preferred properties.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Specifies properties the memory type must have.
+    pub fn required(mut self, properties: &[MemoryProperty]) -> Self {
+        self.required = flags(properties);
+        self
+    }
+
+    /// Specifies properties the memory type should have if possible.
+    pub fn preferred(mut self, properties: &[MemoryProperty]) -> Self {
+        self.preferred = flags(properties);
+        self
+    }
+}
+
+fn flags(properties: &[MemoryProperty]) -> MemoryPropertyFlags {
+    properties
+        .iter()
+        .fold(MemoryPropertyFlags::empty(), |acc, x| acc | (*x).into())
+}
+
+/// Where the memory of a buffer or an image is placed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MemoryLocation {
+    /// Only accessed by the GPU. The memory cannot be mapped.
+    ///
+    /// Falls back to any memory type if no device-local type is suitable.
+    GpuOnly,
+    /// Written by the CPU and read by the GPU. Host-visible and coherent memory,
+    /// device-local if possible.
+    ///
+    /// Falls back to host-visible memory that is not coherent.
+    #[default]
+    Upload,
+    /// Written by the GPU and read by the CPU. Host-visible and cached memory,
+    /// coherent if possible.
+    ///
+    /// Falls back to host-visible memory that is not cached.
+    Readback,
+    /// Memory with the required properties, preferring the memory types with the most preferred properties.
+    Custom(MemoryRequest),
+}
+
+impl MemoryLocation {
+    /// Requests tried in order until a memory type is suitable.
+    fn requests(self) -> Vec<MemoryRequest> {
+        let request = |required, preferred| MemoryRequest {
+            required,
+            preferred,
+        };
+        match self {
+            MemoryLocation::GpuOnly => vec![
+                request(
+                    MemoryPropertyFlags::DEVICE_LOCAL,
+                    MemoryPropertyFlags::empty(),
+                ),
+                request(MemoryPropertyFlags::empty(), MemoryPropertyFlags::empty()),
+            ],
+            MemoryLocation::Upload => vec![
+                request(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                    MemoryPropertyFlags::DEVICE_LOCAL,
+                ),
+                request(
+                    MemoryPropertyFlags::HOST_VISIBLE,
+                    MemoryPropertyFlags::empty(),
+                ),
+            ],
+            MemoryLocation::Readback => vec![
+                request(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_CACHED,
+                    MemoryPropertyFlags::HOST_COHERENT,
+                ),
+                request(
+                    MemoryPropertyFlags::HOST_VISIBLE,
+                    MemoryPropertyFlags::empty(),
+                ),
+            ],
+            MemoryLocation::Custom(x) => vec![x],
+        }
+    }
+
+    /// Returns the indices of the memory types suitable for this location, the best first.
+    /// `types` are the property flags of each memory type, and `type_bits` the types allowed by the resource.
+    ///
+    /// If an allocation fails, the next memory type can be tried.
+    #[doc(hidden)]
+    pub(crate) fn memory_types(self, types: &[MemoryPropertyFlags], type_bits: u32) -> Vec<u32> {
+        let mut candidates: Vec<u32> = vec![];
+        for request in self.requests() {
+            let mut suitable = types
+                .iter()
+                .enumerate()
+                .filter(|(i, x)| type_bits & (1 << i) != 0 && x.contains(request.required))
+                .map(|(i, x)| (i as u32, (*x & request.preferred).as_raw().count_ones()))
+                .filter(|(i, _)| !candidates.contains(i))
+                .collect::<Vec<_>>();
+            // Drivers list faster memory types first, so the index breaks ties.
+            suitable.sort_by_key(|&(i, preferred)| (std::cmp::Reverse(preferred), i));
+            candidates.extend(suitable.into_iter().map(|x| x.0));
+        }
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICE_LOCAL: MemoryPropertyFlags = MemoryPropertyFlags::DEVICE_LOCAL;
+    const VISIBLE: MemoryPropertyFlags = MemoryPropertyFlags::HOST_VISIBLE;
+    const COHERENT: MemoryPropertyFlags = MemoryPropertyFlags::HOST_COHERENT;
+    const CACHED: MemoryPropertyFlags = MemoryPropertyFlags::HOST_CACHED;
+
+    #[test]
+    fn orders_memory_types_by_location() {
+        // Memory types of a typical discrete GPU with resizable BAR.
+        let types = [
+            DEVICE_LOCAL,
+            VISIBLE | COHERENT,
+            VISIBLE | COHERENT | CACHED,
+            DEVICE_LOCAL | VISIBLE | COHERENT,
+        ];
+        assert_eq!(
+            MemoryLocation::GpuOnly.memory_types(&types, !0),
+            [0, 3, 1, 2]
+        );
+        assert_eq!(MemoryLocation::Upload.memory_types(&types, !0), [3, 1, 2]);
+        assert_eq!(MemoryLocation::Readback.memory_types(&types, !0), [2, 1, 3]);
+        // The resource does not allow the device-local types.
+        assert_eq!(MemoryLocation::GpuOnly.memory_types(&types, 0b0110), [1, 2]);
+
+        let custom = MemoryRequest::new()
+            .required(&[MemoryProperty::HostVisible])
+            .preferred(&[MemoryProperty::HostCached]);
+        assert_eq!(
+            MemoryLocation::Custom(custom).memory_types(&types, !0),
+            [2, 1, 3]
+        );
+        let lazy = MemoryRequest::new().required(&[MemoryProperty::LazilyAllocated]);
+        assert!(MemoryLocation::Custom(lazy)
+            .memory_types(&types, !0)
+            .is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use std::ffi::CString;
+use std::marker::PhantomData;
 
 use ash::vk::{
     ColorComponentFlags, CullModeFlags, DescriptorPool, DescriptorPoolCreateInfo,
@@ -13,16 +14,16 @@ use ash::vk::{
 };
 
 use crate::{
-    Buffer, Destroy, Device, Instance, NxError, NxResult, RenderPass, ShaderStage,
-    ShaderStageDescriptor,
+    Buffer, Destroy, Device, DeviceChild, Garbage, Instance, NotSync, NxError, NxResult,
+    RenderPass, ShaderStage, ShaderStageDescriptor,
 };
 
-#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PrimitiveTopology {
     TriangleList,
     TriangleFan,
     TriangleStrip,
-    LineStrip
+    LineStrip,
 }
 
 impl From<crate::PrimitiveTopology> for ash::vk::PrimitiveTopology {
@@ -41,9 +42,9 @@ pub enum BindPoint {
     Graphics,
 }
 
-impl Into<ash::vk::PipelineBindPoint> for BindPoint {
-    fn into(self) -> ash::vk::PipelineBindPoint {
-        match self {
+impl From<BindPoint> for ash::vk::PipelineBindPoint {
+    fn from(value: BindPoint) -> Self {
+        match value {
             BindPoint::Graphics => ash::vk::PipelineBindPoint::GRAPHICS,
         }
     }
@@ -52,6 +53,7 @@ impl Into<ash::vk::PipelineBindPoint> for BindPoint {
 pub struct PipelineLayoutDescriptor<'a> {
     renderpass: Option<&'a RenderPass>,
     set_layout_descriptor: Option<&'a ResourceLayout>,
+    label: Option<String>,
 }
 
 impl<'a> PipelineLayoutDescriptor<'a> {
@@ -60,6 +62,7 @@ impl<'a> PipelineLayoutDescriptor<'a> {
         Self {
             renderpass: None,
             set_layout_descriptor: None,
+            label: None,
         }
     }
 
@@ -74,6 +77,13 @@ impl<'a> PipelineLayoutDescriptor<'a> {
         self.renderpass = Some(render_pass);
         self
     }
+
+    /// Specifies the debug name of the PipelineLayout. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 pub struct VertexInputBindingDescriptor {
@@ -107,9 +117,9 @@ pub enum DataFormat {
     R32G32B32A32SFloat,
 }
 
-impl Into<Format> for DataFormat {
-    fn into(self) -> Format {
-        match self {
+impl From<DataFormat> for Format {
+    fn from(value: DataFormat) -> Self {
+        match value {
             DataFormat::R32G32SFloat => Format::R32G32_SFLOAT,
             DataFormat::R32G32B32SFloat => Format::R32G32B32_SFLOAT,
             DataFormat::R32G32B32A32SFloat => Format::R32G32B32A32_SFLOAT,
@@ -209,6 +219,7 @@ impl ResourcePoolSize {
 pub struct ResourcePoolDescriptor<'a> {
     pool_sizes: &'a [ResourcePoolSize],
     max_sets: u32,
+    label: Option<String>,
 }
 
 impl<'a> ResourcePoolDescriptor<'a> {
@@ -216,6 +227,7 @@ impl<'a> ResourcePoolDescriptor<'a> {
         Self {
             pool_sizes: &[],
             max_sets: 1,
+            label: None,
         }
     }
 
@@ -228,14 +240,25 @@ impl<'a> ResourcePoolDescriptor<'a> {
         self.max_sets = max_sets;
         self
     }
+
+    /// Specifies the debug name of the ResourcePool. See `DebugName`.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
+/// Pool from which resources are allocated.
+/// Resources are freed with their pool.
 pub struct ResourcePool {
-    pool: DescriptorPool,
+    pub(crate) pool: DescriptorPool,
+    owner: DeviceChild,
+    _not_sync: NotSync,
 }
 
 impl ResourcePool {
-    pub fn new(device: &Device, descriptor: &ResourcePoolDescriptor) -> Self {
+    pub fn new(device: &Device, descriptor: &ResourcePoolDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let pool_sizes = descriptor
             .pool_sizes
             .iter()
@@ -250,18 +273,32 @@ impl ResourcePool {
             .max_sets(descriptor.max_sets)
             .pool_sizes(&pool_sizes)
             .build();
-        let pool = unsafe { device.device.create_descriptor_pool(&create_info, None) }.unwrap();
-        Self { pool }
+        let pool = unsafe {
+            device
+                .device
+                .create_descriptor_pool(&create_info, device.allocation_callbacks())
+        }
+        .map_err(|e| device.error("vkCreateDescriptorPool", e))?;
+        device.label(pool, descriptor.label.as_deref());
+        Ok(Self {
+            pool,
+            owner: DeviceChild::new(device, pool, descriptor.label.as_deref()),
+            _not_sync: PhantomData,
+        })
     }
 }
 
 impl Destroy for ResourcePool {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_descriptor_pool(self.pool, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::DescriptorPool(self.pool));
+    }
+}
+
+impl Drop for ResourcePool {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::DescriptorPool(self.pool));
     }
 }
 
@@ -308,42 +345,119 @@ impl<'a> ResourceUpdateDescriptor<'a> {
     }
 }
 
+/// Stores information needed to allocate Resources.
+pub struct ResourceDescriptor<'a> {
+    pool: Option<&'a ResourcePool>,
+    layout: Option<&'a ResourceLayout>,
+    label: Option<String>,
+}
+
+impl<'a> ResourceDescriptor<'a> {
+    /// Initializes a new descriptor with default values.
+    pub fn empty() -> Self {
+        Self {
+            pool: None,
+            layout: None,
+            label: None,
+        }
+    }
+
+    /// Specifies the pool the resources are allocated from.
+    pub fn pool(mut self, pool: &'a ResourcePool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Specifies the layout of the resources.
+    pub fn layout(mut self, layout: &'a ResourceLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Specifies the debug name of the Resource. See `DebugName`.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
+}
+
+/// Set of resources bound to shaders. It is freed with its pool.
 pub struct Resource {
     pub(crate) descriptor_set: DescriptorSet,
     pool: DescriptorPool,
+    owner: DeviceChild,
+    _not_sync: NotSync,
 }
 
 impl Resource {
-    pub fn allocate(device: &Device, pool: &ResourcePool, layout: &ResourceLayout) -> Vec<Self> {
+    /// Allocate resources from the pool.
+    /// Fails with `VkError::OutOfPoolMemory` or `VkError::FragmentedPool` if the pool is exhausted.
+    pub fn allocate(
+        device: &Device,
+        pool: &ResourcePool,
+        layout: &ResourceLayout,
+    ) -> NxResult<Vec<Self>> {
+        let descriptor = ResourceDescriptor::empty().pool(pool).layout(layout);
+        Self::create(device, &descriptor)
+    }
+
+    /// Allocate resources from the pool of the descriptor.
+    /// Fails with `NxError::MissingDescriptorField` if the pool or the layout is not specified.
+    pub fn create(device: &Device, descriptor: &ResourceDescriptor) -> NxResult<Vec<Self>> {
+        device.check_validation()?;
+        let pool = descriptor
+            .pool
+            .ok_or(NxError::MissingDescriptorField("pool"))?;
+        let layout = descriptor
+            .layout
+            .ok_or(NxError::MissingDescriptorField("layout"))?;
         let alloc_info = DescriptorSetAllocateInfo::builder()
             .set_layouts(&[layout.inner])
             .descriptor_pool(pool.pool)
             .build();
-        let descriptor_set =
-            unsafe { device.device.allocate_descriptor_sets(&alloc_info) }.unwrap();
-        descriptor_set
+        let descriptor_set = unsafe { device.device.allocate_descriptor_sets(&alloc_info) }
+            .map_err(|e| device.error("vkAllocateDescriptorSets", e))?;
+        let label = descriptor.label.as_deref();
+        Ok(descriptor_set
             .iter()
-            .map(|x| Self {
-                descriptor_set: *x,
-                pool: pool.pool,
+            .map(|x| {
+                device.label(*x, label);
+                Self {
+                    descriptor_set: *x,
+                    pool: pool.pool,
+                    owner: DeviceChild::new(device, *x, label),
+                    _not_sync: PhantomData,
+                }
             })
-            .collect()
+            .collect())
     }
 }
 
 impl Destroy for Resource {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
+    fn device(&self, _: &Device) {
+        let Some(device) = self.owner.release() else {
+            return;
+        };
+        // vkFreeDescriptorSets can only fail if the pool does not allow freeing individual sets.
+        if let Err(e) = unsafe {
             device
                 .device
                 .free_descriptor_sets(self.pool, &[self.descriptor_set])
-                .unwrap();
+        } {
+            log::warn!("Failed to free a resource: {}", e);
         }
     }
 }
 
+impl Drop for Resource {
+    fn drop(&mut self) {
+        // The descriptor set is freed with its pool.
+        self.owner.release();
+    }
+}
+
 pub struct ResourceLayoutBinding {
     binding: u32,
     desc_type: ResourceType,
@@ -382,15 +496,50 @@ impl ResourceLayoutBinding {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Stores information needed to create a ResourceLayout.
+pub struct ResourceLayoutDescriptor<'a> {
+    bindings: &'a [ResourceLayoutBinding],
+    label: Option<String>,
+}
+
+impl<'a> ResourceLayoutDescriptor<'a> {
+    /// Initializes a new descriptor with default values.
+    pub fn empty() -> Self {
+        Self {
+            bindings: &[],
+            label: None,
+        }
+    }
+
+    pub fn bindings(mut self, bindings: &'a [ResourceLayoutBinding]) -> Self {
+        self.bindings = bindings;
+        self
+    }
+
+    /// Specifies the debug name of the ResourceLayout. See `DebugName`.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
+}
+
 pub struct ResourceLayout {
-    inner: ash::vk::DescriptorSetLayout,
+    pub(crate) inner: ash::vk::DescriptorSetLayout,
+    owner: DeviceChild,
 }
 
 impl ResourceLayout {
-    pub fn new(device: &Device, descriptor: &[ResourceLayoutBinding]) -> Self {
+    pub fn new(device: &Device, descriptor: &[ResourceLayoutBinding]) -> NxResult<Self> {
+        Self::create(
+            device,
+            &ResourceLayoutDescriptor::empty().bindings(descriptor),
+        )
+    }
+
+    pub fn create(device: &Device, descriptor: &ResourceLayoutDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let mut bindings = vec![];
-        for descriptor in descriptor {
+        for descriptor in descriptor.bindings {
             bindings.push(
                 ash::vk::DescriptorSetLayoutBinding::builder()
                     .binding(descriptor.binding)
@@ -406,22 +555,28 @@ impl ResourceLayout {
         let inner = unsafe {
             device
                 .device
-                .create_descriptor_set_layout(&create_info, None)
+                .create_descriptor_set_layout(&create_info, device.allocation_callbacks())
         }
-        .unwrap();
-        Self { inner }
+        .map_err(|e| device.error("vkCreateDescriptorSetLayout", e))?;
+        device.label(inner, descriptor.label.as_deref());
+        Ok(Self {
+            inner,
+            owner: DeviceChild::new(device, inner, descriptor.label.as_deref()),
+        })
     }
 }
 
 impl Destroy for ResourceLayout {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device
-                .device
-                .destroy_descriptor_set_layout(self.inner, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::DescriptorSetLayout(self.inner));
+    }
+}
+
+impl Drop for ResourceLayout {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::DescriptorSetLayout(self.inner));
     }
 }
 
@@ -433,6 +588,7 @@ pub struct PipelineDescriptor<'a> {
     topology: PrimitiveTopology,
     shader_stages: &'a [ShaderStageDescriptor<'a>],
     input_descriptor: Option<&'a PipelineVertexInputDescriptor<'a>>,
+    label: Option<String>,
 }
 
 impl<'a> PipelineDescriptor<'a> {
@@ -445,11 +601,12 @@ impl<'a> PipelineDescriptor<'a> {
             topology: PrimitiveTopology::TriangleList,
             shader_stages: &[],
             input_descriptor: None,
+            label: None,
         }
     }
 
     #[inline]
-    pub const fn topology(mut self,topology: PrimitiveTopology) -> Self {
+    pub const fn topology(mut self, topology: PrimitiveTopology) -> Self {
         self.topology = topology;
         self
     }
@@ -480,16 +637,24 @@ impl<'a> PipelineDescriptor<'a> {
         self.shader_stages = shader_stages;
         self
     }
+
+    /// Specifies the debug name of the Pipeline. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
-#[derive(Clone, Copy)]
 pub struct PipelineLayout {
     pub(crate) layout: ash::vk::PipelineLayout,
+    owner: DeviceChild,
 }
 
 impl PipelineLayout {
     #[inline]
     pub fn new(device: &Device, descriptor: &PipelineLayoutDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let layout_info = PipelineLayoutCreateInfo::builder().set_layouts(&[]);
         let mut layouts = vec![];
         match descriptor.set_layout_descriptor {
@@ -499,47 +664,60 @@ impl PipelineLayout {
             }
         }
         let layout_info = layout_info.set_layouts(&layouts).build();
-        let layout = match unsafe { device.device.create_pipeline_layout(&layout_info, None) } {
+        let layout = match unsafe {
+            device
+                .device
+                .create_pipeline_layout(&layout_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkCreatePipelineLayout", e)),
         };
 
-        Ok(Self { layout })
+        device.label(layout, descriptor.label.as_deref());
+        Ok(Self {
+            layout,
+            owner: DeviceChild::new(device, layout, descriptor.label.as_deref()),
+        })
     }
 }
 
 impl Destroy for PipelineLayout {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_pipeline_layout(self.layout, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::PipelineLayout(self.layout));
+    }
+}
+
+impl Drop for PipelineLayout {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::PipelineLayout(self.layout));
     }
 }
 
 pub struct Pipeline {
     pub(crate) pipeline: ash::vk::Pipeline,
+    owner: DeviceChild,
 }
 
 impl Pipeline {
     #[inline]
     pub fn new(
         device: &Device,
-        pipeline_layout: PipelineLayout,
+        pipeline_layout: &PipelineLayout,
         renderpass: &RenderPass,
         descriptor: &PipelineDescriptor,
     ) -> NxResult<Vec<Self>> {
+        device.check_validation()?;
         let mut stages = vec![];
         let name = CString::new("main").unwrap();
         for i in descriptor.shader_stages {
+            let shader = i
+                .shaders
+                .ok_or(NxError::MissingDescriptorField("shaders"))?;
             let create_info = PipelineShaderStageCreateInfo::builder()
                 .stage(i.stage.into())
-                .module(i.shaders.unwrap().inner)
+                .module(shader.inner)
                 .name(name.as_c_str())
                 .build();
             stages.push(create_info);
@@ -650,14 +828,22 @@ impl Pipeline {
             .build();
 
         let pipelines = unsafe {
-            device
-                .device
-                .create_graphics_pipelines(PipelineCache::null(), &[create_info], None)
+            device.device.create_graphics_pipelines(
+                PipelineCache::null(),
+                &[create_info],
+                device.allocation_callbacks(),
+            )
+        }
+        .map_err(|(_, e)| device.error("vkCreateGraphicsPipelines", e))?;
+        for pipeline in &pipelines {
+            device.label(*pipeline, descriptor.label.as_deref());
         }
-        .unwrap();
         Ok(pipelines
             .iter()
-            .map(|x| Self { pipeline: *x })
+            .map(|x| Self {
+                pipeline: *x,
+                owner: DeviceChild::new(device, *x, descriptor.label.as_deref()),
+            })
             .collect::<Vec<Pipeline>>())
     }
 }
@@ -665,9 +851,13 @@ impl Pipeline {
 impl Destroy for Pipeline {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_pipeline(self.pipeline, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::Pipeline(self.pipeline));
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::Pipeline(self.pipeline));
     }
 }
@@ -0,0 +1,773 @@
+use std::ffi::CStr;
+
+use ash::vk;
+
+use crate::extension::to_string;
+use crate::{DeviceConnecter, DeviceType, Instance, Version};
+
+#[doc(hidden)]
+trait Property<T> {
+    fn convert(self) -> T;
+}
+
+impl<T> Property<T> for T {
+    fn convert(self) -> T {
+        self
+    }
+}
+
+impl Property<bool> for vk::Bool32 {
+    fn convert(self) -> bool {
+        self != vk::FALSE
+    }
+}
+
+impl Property<u32> for vk::SampleCountFlags {
+    fn convert(self) -> u32 {
+        self.as_raw()
+    }
+}
+
+/// Generates a struct with a getter for each field, converted from the given Vulkan struct.
+macro_rules! properties {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident from $raw:ty {
+            $($field:ident: $ty:ty,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub struct $name {
+            $($field: $ty,)*
+        }
+
+        impl $name {
+            $(
+                pub fn $field(&self) -> $ty {
+                    self.$field
+                }
+            )*
+        }
+
+        impl From<$raw> for $name {
+            fn from(value: $raw) -> Self {
+                Self {
+                    $($field: Property::convert(value.$field),)*
+                }
+            }
+        }
+    };
+}
+
+properties! {
+    /// Limits of a physical device.
+    ///
+    /// Each getter corresponds to the member of `VkPhysicalDeviceLimits` with the same name.
+    /// Sample counts are bitmasks in which each set bit is a supported count,
+    /// so `framebuffer_color_sample_counts() & 4 != 0` means 4 samples are supported.
+    pub struct DeviceLimits from vk::PhysicalDeviceLimits {
+        max_image_dimension1_d: u32,
+        max_image_dimension2_d: u32,
+        max_image_dimension3_d: u32,
+        max_image_dimension_cube: u32,
+        max_image_array_layers: u32,
+        max_texel_buffer_elements: u32,
+        max_uniform_buffer_range: u32,
+        max_storage_buffer_range: u32,
+        max_push_constants_size: u32,
+        max_memory_allocation_count: u32,
+        max_sampler_allocation_count: u32,
+        buffer_image_granularity: u64,
+        sparse_address_space_size: u64,
+        max_bound_descriptor_sets: u32,
+        max_per_stage_descriptor_samplers: u32,
+        max_per_stage_descriptor_uniform_buffers: u32,
+        max_per_stage_descriptor_storage_buffers: u32,
+        max_per_stage_descriptor_sampled_images: u32,
+        max_per_stage_descriptor_storage_images: u32,
+        max_per_stage_descriptor_input_attachments: u32,
+        max_per_stage_resources: u32,
+        max_descriptor_set_samplers: u32,
+        max_descriptor_set_uniform_buffers: u32,
+        max_descriptor_set_uniform_buffers_dynamic: u32,
+        max_descriptor_set_storage_buffers: u32,
+        max_descriptor_set_storage_buffers_dynamic: u32,
+        max_descriptor_set_sampled_images: u32,
+        max_descriptor_set_storage_images: u32,
+        max_descriptor_set_input_attachments: u32,
+        max_vertex_input_attributes: u32,
+        max_vertex_input_bindings: u32,
+        max_vertex_input_attribute_offset: u32,
+        max_vertex_input_binding_stride: u32,
+        max_vertex_output_components: u32,
+        max_tessellation_generation_level: u32,
+        max_tessellation_patch_size: u32,
+        max_tessellation_control_per_vertex_input_components: u32,
+        max_tessellation_control_per_vertex_output_components: u32,
+        max_tessellation_control_per_patch_output_components: u32,
+        max_tessellation_control_total_output_components: u32,
+        max_tessellation_evaluation_input_components: u32,
+        max_tessellation_evaluation_output_components: u32,
+        max_geometry_shader_invocations: u32,
+        max_geometry_input_components: u32,
+        max_geometry_output_components: u32,
+        max_geometry_output_vertices: u32,
+        max_geometry_total_output_components: u32,
+        max_fragment_input_components: u32,
+        max_fragment_output_attachments: u32,
+        max_fragment_dual_src_attachments: u32,
+        max_fragment_combined_output_resources: u32,
+        max_compute_shared_memory_size: u32,
+        max_compute_work_group_count: [u32; 3],
+        max_compute_work_group_invocations: u32,
+        max_compute_work_group_size: [u32; 3],
+        sub_pixel_precision_bits: u32,
+        sub_texel_precision_bits: u32,
+        mipmap_precision_bits: u32,
+        max_draw_indexed_index_value: u32,
+        max_draw_indirect_count: u32,
+        max_sampler_lod_bias: f32,
+        max_sampler_anisotropy: f32,
+        max_viewports: u32,
+        max_viewport_dimensions: [u32; 2],
+        viewport_bounds_range: [f32; 2],
+        viewport_sub_pixel_bits: u32,
+        min_memory_map_alignment: usize,
+        min_texel_buffer_offset_alignment: u64,
+        min_uniform_buffer_offset_alignment: u64,
+        min_storage_buffer_offset_alignment: u64,
+        min_texel_offset: i32,
+        max_texel_offset: u32,
+        min_texel_gather_offset: i32,
+        max_texel_gather_offset: u32,
+        min_interpolation_offset: f32,
+        max_interpolation_offset: f32,
+        sub_pixel_interpolation_offset_bits: u32,
+        max_framebuffer_width: u32,
+        max_framebuffer_height: u32,
+        max_framebuffer_layers: u32,
+        framebuffer_color_sample_counts: u32,
+        framebuffer_depth_sample_counts: u32,
+        framebuffer_stencil_sample_counts: u32,
+        framebuffer_no_attachments_sample_counts: u32,
+        max_color_attachments: u32,
+        sampled_image_color_sample_counts: u32,
+        sampled_image_integer_sample_counts: u32,
+        sampled_image_depth_sample_counts: u32,
+        sampled_image_stencil_sample_counts: u32,
+        storage_image_sample_counts: u32,
+        max_sample_mask_words: u32,
+        timestamp_compute_and_graphics: bool,
+        timestamp_period: f32,
+        max_clip_distances: u32,
+        max_cull_distances: u32,
+        max_combined_clip_and_cull_distances: u32,
+        discrete_queue_priorities: u32,
+        point_size_range: [f32; 2],
+        line_width_range: [f32; 2],
+        point_size_granularity: f32,
+        line_width_granularity: f32,
+        strict_lines: bool,
+        standard_sample_locations: bool,
+        optimal_buffer_copy_offset_alignment: u64,
+        optimal_buffer_copy_row_pitch_alignment: u64,
+        non_coherent_atom_size: u64,
+    }
+}
+
+properties! {
+    /// Limits of descriptor indexing.
+    ///
+    /// Each getter corresponds to the member of `VkPhysicalDeviceDescriptorIndexingProperties` with the same name.
+    pub struct DescriptorIndexingProperties from vk::PhysicalDeviceDescriptorIndexingProperties {
+        max_update_after_bind_descriptors_in_all_pools: u32,
+        shader_uniform_buffer_array_non_uniform_indexing_native: bool,
+        shader_sampled_image_array_non_uniform_indexing_native: bool,
+        shader_storage_buffer_array_non_uniform_indexing_native: bool,
+        shader_storage_image_array_non_uniform_indexing_native: bool,
+        shader_input_attachment_array_non_uniform_indexing_native: bool,
+        robust_buffer_access_update_after_bind: bool,
+        quad_divergent_implicit_lod: bool,
+        max_per_stage_descriptor_update_after_bind_samplers: u32,
+        max_per_stage_descriptor_update_after_bind_uniform_buffers: u32,
+        max_per_stage_descriptor_update_after_bind_storage_buffers: u32,
+        max_per_stage_descriptor_update_after_bind_sampled_images: u32,
+        max_per_stage_descriptor_update_after_bind_storage_images: u32,
+        max_per_stage_descriptor_update_after_bind_input_attachments: u32,
+        max_per_stage_update_after_bind_resources: u32,
+        max_descriptor_set_update_after_bind_samplers: u32,
+        max_descriptor_set_update_after_bind_uniform_buffers: u32,
+        max_descriptor_set_update_after_bind_uniform_buffers_dynamic: u32,
+        max_descriptor_set_update_after_bind_storage_buffers: u32,
+        max_descriptor_set_update_after_bind_storage_buffers_dynamic: u32,
+        max_descriptor_set_update_after_bind_sampled_images: u32,
+        max_descriptor_set_update_after_bind_storage_images: u32,
+        max_descriptor_set_update_after_bind_input_attachments: u32,
+    }
+}
+
+/// Subgroup support of a physical device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SubgroupProperties {
+    subgroup_size: u32,
+    stages: vk::ShaderStageFlags,
+    operations: vk::SubgroupFeatureFlags,
+    quad_operations_in_all_stages: bool,
+}
+
+impl SubgroupProperties {
+    /// Default number of invocations in a subgroup.
+    pub fn subgroup_size(&self) -> u32 {
+        self.subgroup_size
+    }
+
+    /// Check to see if subgroup operations are supported in vertex shaders.
+    pub fn is_vertex_support(&self) -> bool {
+        self.stages.contains(vk::ShaderStageFlags::VERTEX)
+    }
+
+    /// Check to see if subgroup operations are supported in fragment shaders.
+    pub fn is_fragment_support(&self) -> bool {
+        self.stages.contains(vk::ShaderStageFlags::FRAGMENT)
+    }
+
+    /// Check to see if subgroup operations are supported in compute shaders.
+    pub fn is_compute_support(&self) -> bool {
+        self.stages.contains(vk::ShaderStageFlags::COMPUTE)
+    }
+
+    pub fn is_basic_support(&self) -> bool {
+        self.operations.contains(vk::SubgroupFeatureFlags::BASIC)
+    }
+
+    pub fn is_vote_support(&self) -> bool {
+        self.operations.contains(vk::SubgroupFeatureFlags::VOTE)
+    }
+
+    pub fn is_arithmetic_support(&self) -> bool {
+        self.operations
+            .contains(vk::SubgroupFeatureFlags::ARITHMETIC)
+    }
+
+    pub fn is_ballot_support(&self) -> bool {
+        self.operations.contains(vk::SubgroupFeatureFlags::BALLOT)
+    }
+
+    pub fn is_shuffle_support(&self) -> bool {
+        self.operations.contains(vk::SubgroupFeatureFlags::SHUFFLE)
+    }
+
+    pub fn is_shuffle_relative_support(&self) -> bool {
+        self.operations
+            .contains(vk::SubgroupFeatureFlags::SHUFFLE_RELATIVE)
+    }
+
+    pub fn is_clustered_support(&self) -> bool {
+        self.operations
+            .contains(vk::SubgroupFeatureFlags::CLUSTERED)
+    }
+
+    pub fn is_quad_support(&self) -> bool {
+        self.operations.contains(vk::SubgroupFeatureFlags::QUAD)
+    }
+
+    /// Check to see if quad operations are supported in all stages,
+    /// rather than only in fragment and compute shaders.
+    pub fn quad_operations_in_all_stages(&self) -> bool {
+        self.quad_operations_in_all_stages
+    }
+}
+
+impl From<vk::PhysicalDeviceSubgroupProperties> for SubgroupProperties {
+    fn from(value: vk::PhysicalDeviceSubgroupProperties) -> Self {
+        Self {
+            subgroup_size: value.subgroup_size,
+            stages: value.supported_stages,
+            operations: value.supported_operations,
+            quad_operations_in_all_stages: value.quad_operations_in_all_stages.convert(),
+        }
+    }
+}
+
+/// Information about the driver of a physical device.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DriverProperties {
+    driver_id: i32,
+    name: String,
+    info: String,
+    conformance_version: Version,
+}
+
+impl DriverProperties {
+    /// Identifier of the driver, as in `VkDriverId`.
+    pub fn driver_id(&self) -> i32 {
+        self.driver_id
+    }
+
+    /// Name of the driver, such as `NVIDIA` or `radv`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Additional information, usually the version of the driver.
+    pub fn info(&self) -> &str {
+        &self.info
+    }
+
+    /// Version of the Vulkan conformance test suite the driver passed.
+    /// The patch number holds the subminor version of the test suite.
+    pub fn conformance_version(&self) -> Version {
+        self.conformance_version
+    }
+}
+
+impl From<vk::PhysicalDeviceDriverProperties> for DriverProperties {
+    fn from(value: vk::PhysicalDeviceDriverProperties) -> Self {
+        let conformance = value.conformance_version;
+        Self {
+            driver_id: value.driver_id.as_raw(),
+            name: to_string(&value.driver_name),
+            info: to_string(&value.driver_info),
+            conformance_version: Version::new(
+                conformance.major as u32,
+                conformance.minor as u32,
+                conformance.subminor as u32,
+            ),
+        }
+    }
+}
+
+/// Properties of a physical device.
+///
+/// Obtained with `DeviceConnecter::properties`.
+/// The optional properties are only available on Vulkan 1.1 or later,
+/// and driver and descriptor indexing properties also require Vulkan 1.2 or the corresponding extension.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceProperties {
+    name: String,
+    vendor_id: u32,
+    device_id: u32,
+    device_type: DeviceType,
+    driver_version: u32,
+    api_version: Version,
+    pipeline_cache_uuid: [u8; 16],
+    limits: DeviceLimits,
+    subgroup: Option<SubgroupProperties>,
+    descriptor_indexing: Option<DescriptorIndexingProperties>,
+    driver: Option<DriverProperties>,
+}
+
+impl DeviceProperties {
+    /// Name of the device, such as the GPU model.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// PCI vendor ID, such as `0x10DE` for NVIDIA.
+    pub fn vendor_id(&self) -> u32 {
+        self.vendor_id
+    }
+
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    pub fn device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    /// Version of the driver. The encoding is vendor-specific.
+    pub fn driver_version(&self) -> u32 {
+        self.driver_version
+    }
+
+    /// Highest Vulkan version supported by the device.
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Identifier of the device for pipeline caches.
+    /// A pipeline cache can only be reused on a device with the same UUID.
+    pub fn pipeline_cache_uuid(&self) -> [u8; 16] {
+        self.pipeline_cache_uuid
+    }
+
+    pub fn limits(&self) -> &DeviceLimits {
+        &self.limits
+    }
+
+    pub fn subgroup(&self) -> Option<&SubgroupProperties> {
+        self.subgroup.as_ref()
+    }
+
+    pub fn descriptor_indexing(&self) -> Option<&DescriptorIndexingProperties> {
+        self.descriptor_indexing.as_ref()
+    }
+
+    pub fn driver(&self) -> Option<&DriverProperties> {
+        self.driver.as_ref()
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn query(instance: &Instance, connecter: DeviceConnecter) -> Self {
+        let properties = instance.get_properties(connecter.0);
+        let api_version = Version::from_raw(properties.api_version);
+        let mut result = Self {
+            name: to_string(&properties.device_name),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            device_type: properties.device_type.into(),
+            driver_version: properties.driver_version,
+            api_version,
+            pipeline_cache_uuid: properties.pipeline_cache_uuid,
+            limits: properties.limits.into(),
+            subgroup: None,
+            descriptor_indexing: None,
+            driver: None,
+        };
+
+        let Some((has_descriptor_indexing, has_driver)) =
+            Self::optional_properties(connecter.api_version(instance), |x| {
+                connecter.is_extension_supported(instance, &x.to_string_lossy())
+            })
+        else {
+            return result;
+        };
+
+        let mut subgroup = vk::PhysicalDeviceSubgroupProperties::default();
+        let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingProperties::default();
+        let mut driver = vk::PhysicalDeviceDriverProperties::default();
+        let mut properties2 = vk::PhysicalDeviceProperties2::builder().push_next(&mut subgroup);
+        if has_descriptor_indexing {
+            properties2 = properties2.push_next(&mut descriptor_indexing);
+        }
+        if has_driver {
+            properties2 = properties2.push_next(&mut driver);
+        }
+        let mut properties2 = properties2.build();
+        unsafe {
+            instance
+                .instance
+                .get_physical_device_properties2(connecter.0, &mut properties2)
+        };
+
+        result.subgroup = Some(subgroup.into());
+        if has_descriptor_indexing {
+            result.descriptor_indexing = Some(descriptor_indexing.into());
+        }
+        if has_driver {
+            result.driver = Some(driver.into());
+        }
+        result
+    }
+
+    /// Returns whether descriptor indexing and driver properties can be queried,
+    /// or `None` if `vkGetPhysicalDeviceProperties2` is not available.
+    #[doc(hidden)]
+    fn optional_properties(
+        version: Version,
+        is_extension_supported: impl Fn(&CStr) -> bool,
+    ) -> Option<(bool, bool)> {
+        // vkGetPhysicalDeviceProperties2 is core in Vulkan 1.1.
+        if version < Version::V1_1 {
+            return None;
+        }
+        let core = version >= Version::V1_2;
+        Some((
+            core || is_extension_supported(vk::ExtDescriptorIndexingFn::name()),
+            core || is_extension_supported(vk::KhrDriverPropertiesFn::name()),
+        ))
+    }
+}
+
+/// Memory type of a physical device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoryType {
+    index: u32,
+    flags: vk::MemoryPropertyFlags,
+}
+
+impl MemoryType {
+    /// Index of the memory type among all memory types of the physical device.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn is_device_local(&self) -> bool {
+        self.flags.contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+    }
+
+    /// Check to see if the memory can be mapped.
+    pub fn is_host_visible(&self) -> bool {
+        self.flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+    }
+
+    /// Check to see if writes are visible without flushing.
+    pub fn is_host_coherent(&self) -> bool {
+        self.flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+    }
+
+    pub fn is_host_cached(&self) -> bool {
+        self.flags.contains(vk::MemoryPropertyFlags::HOST_CACHED)
+    }
+
+    pub fn is_lazily_allocated(&self) -> bool {
+        self.flags
+            .contains(vk::MemoryPropertyFlags::LAZILY_ALLOCATED)
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.flags.contains(vk::MemoryPropertyFlags::PROTECTED)
+    }
+}
+
+/// Memory heap of a physical device.
+///
+/// Usage and budget are only reported when the physical device supports `VK_EXT_memory_budget`
+/// and Vulkan 1.1 is usable. They change over time, so query the heaps again before relying on them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryHeap {
+    index: u32,
+    size: u64,
+    flags: vk::MemoryHeapFlags,
+    memory_types: Vec<MemoryType>,
+    usage: Option<u64>,
+    budget: Option<u64>,
+}
+
+impl MemoryHeap {
+    /// Index of the heap among all heaps of the physical device.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Total size of the heap in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_device_local(&self) -> bool {
+        self.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL)
+    }
+
+    pub fn is_multi_instance(&self) -> bool {
+        self.flags.contains(vk::MemoryHeapFlags::MULTI_INSTANCE)
+    }
+
+    /// Memory types allocated from this heap.
+    pub fn memory_types(&self) -> &[MemoryType] {
+        &self.memory_types
+    }
+
+    /// Bytes currently used by this process.
+    pub fn usage(&self) -> Option<u64> {
+        self.usage
+    }
+
+    /// Bytes this process can use before allocations may fail or degrade performance.
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// Bytes left in the budget.
+    pub fn available(&self) -> Option<u64> {
+        Some(self.budget?.saturating_sub(self.usage?))
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn query(instance: &Instance, connecter: DeviceConnecter) -> Vec<Self> {
+        // vkGetPhysicalDeviceMemoryProperties2 is core in Vulkan 1.1.
+        let has_budget = connecter.api_version(instance) >= Version::V1_1
+            && connecter
+                .is_extension_supported(instance, &vk::ExtMemoryBudgetFn::name().to_string_lossy());
+        if !has_budget {
+            return Self::from_raw(&connecter.get_memory_properties(instance), None);
+        }
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties2 = vk::PhysicalDeviceMemoryProperties2::builder()
+            .push_next(&mut budget)
+            .build();
+        unsafe {
+            instance
+                .instance
+                .get_physical_device_memory_properties2(connecter.0, &mut properties2)
+        };
+        let properties = properties2.memory_properties;
+        Self::from_raw(&properties, Some(&budget))
+    }
+
+    #[doc(hidden)]
+    fn from_raw(
+        properties: &vk::PhysicalDeviceMemoryProperties,
+        budget: Option<&vk::PhysicalDeviceMemoryBudgetPropertiesEXT>,
+    ) -> Vec<Self> {
+        let types = &properties.memory_types[..properties.memory_type_count as usize];
+        properties.memory_heaps[..properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, heap)| Self {
+                index: i as u32,
+                size: heap.size,
+                flags: heap.flags,
+                memory_types: types
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, x)| x.heap_index as usize == i)
+                    .map(|(index, x)| MemoryType {
+                        index: index as u32,
+                        flags: x.property_flags,
+                    })
+                    .collect(),
+                usage: budget.map(|x| x.heap_usage[i]),
+                budget: budget.map(|x| x.heap_budget[i]),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_convert_from_raw() {
+        let raw = vk::PhysicalDeviceLimits {
+            max_image_dimension2_d: 16384,
+            max_compute_work_group_size: [1024, 1024, 64],
+            timestamp_compute_and_graphics: vk::TRUE,
+            strict_lines: vk::FALSE,
+            framebuffer_color_sample_counts: vk::SampleCountFlags::TYPE_1
+                | vk::SampleCountFlags::TYPE_4,
+            non_coherent_atom_size: 64,
+            ..Default::default()
+        };
+        let limits = DeviceLimits::from(raw);
+        assert_eq!(limits.max_image_dimension2_d(), 16384);
+        assert_eq!(limits.max_compute_work_group_size(), [1024, 1024, 64]);
+        assert!(limits.timestamp_compute_and_graphics());
+        assert!(!limits.strict_lines());
+        assert_eq!(limits.framebuffer_color_sample_counts(), 0b101);
+        assert_eq!(limits.non_coherent_atom_size(), 64);
+    }
+
+    #[test]
+    fn driver_and_subgroup_convert_from_raw() {
+        let mut raw = vk::PhysicalDeviceDriverProperties {
+            driver_id: vk::DriverId::MESA_LLVMPIPE,
+            conformance_version: vk::ConformanceVersion {
+                major: 1,
+                minor: 3,
+                subminor: 1,
+                patch: 0,
+            },
+            ..Default::default()
+        };
+        for (dst, src) in raw.driver_name.iter_mut().zip(b"llvmpipe") {
+            *dst = *src as _;
+        }
+        let driver = DriverProperties::from(raw);
+        assert_eq!(driver.name(), "llvmpipe");
+        assert_eq!(driver.info(), "");
+        assert_eq!(driver.driver_id(), vk::DriverId::MESA_LLVMPIPE.as_raw());
+        assert_eq!(driver.conformance_version(), Version::new(1, 3, 1));
+
+        let subgroup = SubgroupProperties::from(vk::PhysicalDeviceSubgroupProperties {
+            subgroup_size: 32,
+            supported_stages: vk::ShaderStageFlags::COMPUTE,
+            supported_operations: vk::SubgroupFeatureFlags::BASIC
+                | vk::SubgroupFeatureFlags::BALLOT,
+            quad_operations_in_all_stages: vk::TRUE,
+            ..Default::default()
+        });
+        assert_eq!(subgroup.subgroup_size(), 32);
+        assert!(subgroup.is_compute_support() && !subgroup.is_vertex_support());
+        assert!(subgroup.is_ballot_support() && !subgroup.is_vote_support());
+        assert!(subgroup.quad_operations_in_all_stages());
+    }
+
+    #[test]
+    fn optional_properties_depend_on_version_and_extensions() {
+        let none = |_: &CStr| false;
+        let driver_only = |x: &CStr| x == vk::KhrDriverPropertiesFn::name();
+        assert_eq!(
+            DeviceProperties::optional_properties(Version::V1_0, |_| true),
+            None
+        );
+        assert_eq!(
+            DeviceProperties::optional_properties(Version::V1_1, none),
+            Some((false, false))
+        );
+        assert_eq!(
+            DeviceProperties::optional_properties(Version::V1_1, driver_only),
+            Some((false, true))
+        );
+        assert_eq!(
+            DeviceProperties::optional_properties(Version::new(1, 2, 198), none),
+            Some((true, true))
+        );
+    }
+
+    fn memory_properties() -> vk::PhysicalDeviceMemoryProperties {
+        let mut properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: 3,
+            memory_heap_count: 2,
+            ..Default::default()
+        };
+        properties.memory_heaps[0] = vk::MemoryHeap {
+            size: 8 << 30,
+            flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+        };
+        properties.memory_heaps[1] = vk::MemoryHeap {
+            size: 16 << 30,
+            flags: vk::MemoryHeapFlags::empty(),
+        };
+        let flags = [
+            (vk::MemoryPropertyFlags::DEVICE_LOCAL, 0),
+            (vk::MemoryPropertyFlags::HOST_VISIBLE, 1),
+            (
+                vk::MemoryPropertyFlags::DEVICE_LOCAL | vk::MemoryPropertyFlags::HOST_VISIBLE,
+                0,
+            ),
+        ];
+        for (i, (property_flags, heap_index)) in flags.into_iter().enumerate() {
+            properties.memory_types[i] = vk::MemoryType {
+                property_flags,
+                heap_index,
+            };
+        }
+        properties
+    }
+
+    #[test]
+    fn heaps_group_memory_types() {
+        let heaps = MemoryHeap::from_raw(&memory_properties(), None);
+        assert_eq!(heaps.len(), 2);
+        assert!(heaps[0].is_device_local() && !heaps[1].is_device_local());
+        let types = heaps[0]
+            .memory_types()
+            .iter()
+            .map(|x| x.index())
+            .collect::<Vec<_>>();
+        assert_eq!(types, [0, 2]);
+        assert!(heaps[0].memory_types()[1].is_host_visible());
+        assert_eq!(heaps[1].memory_types()[0].index(), 1);
+        assert_eq!(heaps[0].usage(), None);
+        assert_eq!(heaps[0].available(), None);
+    }
+
+    #[test]
+    fn heaps_report_usage_and_budget() {
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        budget.heap_usage[0] = 3 << 30;
+        budget.heap_budget[0] = 7 << 30;
+        // Usage can exceed the budget when other processes use the heap.
+        budget.heap_usage[1] = 5 << 30;
+        budget.heap_budget[1] = 4 << 30;
+        let heaps = MemoryHeap::from_raw(&memory_properties(), Some(&budget));
+        assert_eq!(heaps[0].usage(), Some(3 << 30));
+        assert_eq!(heaps[0].budget(), Some(7 << 30));
+        assert_eq!(heaps[0].available(), Some(4 << 30));
+        assert_eq!(heaps[1].available(), Some(0));
+    }
+}
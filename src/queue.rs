@@ -1,5 +1,5 @@
-use crate::{CommandRecorder, Device, NxError, NxResult, Semaphore};
-use ash::vk::{CommandBuffer, Fence, SubmitInfo};
+use crate::{CommandRecorder, DebugLabelScope, Device, HostLock, LabelTarget, NxResult, Semaphore};
+use ash::vk::{CommandBuffer, SubmitInfo};
 
 pub struct QueuePresentDescriptor<'a> {
     pub(crate) wait_semaphores: &'a [Semaphore],
@@ -73,11 +73,57 @@ impl<'a> QueueSubmitDescriptor<'a> {
     }
 }
 
+/// Queue to which work is submitted.
+///
+/// Vulkan requires submissions to a queue to be externally synchronized.
+/// Every handle of the same queue shares a lock that serializes submissions, presentations and debug labels,
+/// so clones can be used from several threads at once.
 #[derive(Clone)]
-pub struct Queue(pub(crate) ash::vk::Queue);
+pub struct Queue {
+    pub(crate) queue: ash::vk::Queue,
+    pub(crate) lock: HostLock,
+}
 
 impl Queue {
+    #[doc(hidden)]
+    fn label_target(&self) -> LabelTarget {
+        LabelTarget::Queue(self.queue, self.lock.clone())
+    }
+
+    /// Opens a debug label region. Submissions until `end_debug_label` belong to it.
+    /// Does nothing if `VK_EXT_debug_utils` is not enabled.
+    #[inline]
+    pub fn begin_debug_label(&self, device: &Device, name: &str, color: [f32; 4]) {
+        self.label_target().begin(device, name, color);
+    }
+
+    /// Closes the debug label region opened last.
+    #[inline]
+    pub fn end_debug_label(&self, device: &Device) {
+        self.label_target().end(device);
+    }
+
+    /// Inserts a single debug label between submissions.
+    #[inline]
+    pub fn insert_debug_label(&self, device: &Device, name: &str, color: [f32; 4]) {
+        self.label_target().insert(device, name, color);
+    }
+
+    /// Opens a debug label region that is closed when the returned scope is dropped.
+    #[inline]
+    pub fn debug_label_scope<'a>(
+        &self,
+        device: &'a Device,
+        name: &str,
+        color: [f32; 4],
+    ) -> DebugLabelScope<'a> {
+        DebugLabelScope::new(device, self.label_target(), name, color)
+    }
+
     /// Submits the queue.
+    ///
+    /// Objects dropped after this call are destroyed once the submitted work has completed,
+    /// by a later `submit` or by `Device::collect_garbage`.
     #[inline]
     pub fn submit(
         &self,
@@ -85,6 +131,20 @@ impl Queue {
         descriptor: &QueueSubmitDescriptor,
         recorders: &[CommandRecorder],
     ) -> NxResult<()> {
+        self.submit_tracked(device, descriptor, recorders)
+            .map(|_| ())
+    }
+
+    /// Submits the queue and returns the index the garbage collector tracks the submission by.
+    pub(crate) fn submit_tracked(
+        &self,
+        device: &Device,
+        descriptor: &QueueSubmitDescriptor,
+        recorders: &[CommandRecorder],
+    ) -> NxResult<u64> {
+        device.check_validation()?;
+        // Retires the submissions that have completed, so their fences are reused.
+        device.garbage().collect(device)?;
         let buffers = recorders
             .iter()
             .map(|x| x.buffer)
@@ -99,24 +159,46 @@ impl Queue {
             .iter()
             .map(|x| x.semaphore)
             .collect::<Vec<ash::vk::Semaphore>>();
-        let fence = match descriptor.fence {
-            Some(x) => x.fence,
-            None => Fence::null(),
-        };
         let submit_info = SubmitInfo::builder()
             .wait_semaphores(&w_semaphores)
             .signal_semaphores(&s_semaphores)
             .command_buffers(&buffers)
             .build();
-        unsafe {
-            match device.device.queue_submit(self.0, &[submit_info], fence) {
-                Ok(_) => Ok(()),
-                Err(e) => match e {
-                    ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                    ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                    _ => Err(NxError::Unknown),
-                }?,
+        // The submission is reserved first, so objects dropped while it is being made wait for it.
+        let (submission, fence) = device.garbage().begin_submission(device)?;
+        let lock = self.lock.lock();
+        let (work, tracking) = unsafe {
+            match descriptor.fence {
+                // A submission without work signals its fence once the previous submissions complete.
+                Some(x) => match device
+                    .device
+                    .queue_submit(self.queue, &[submit_info], x.fence)
+                {
+                    Ok(_) => (Ok(()), device.device.queue_submit(self.queue, &[], fence)),
+                    Err(e) => (Err(e), Ok(())),
+                },
+                None => (
+                    device
+                        .device
+                        .queue_submit(self.queue, &[submit_info], fence),
+                    Ok(()),
+                ),
             }
+        };
+        drop(lock);
+        if let Err(e) = work {
+            device.garbage().cancel_submission(submission);
+            return Err(device.error("vkQueueSubmit", e));
+        }
+        if let Err(e) = tracking {
+            // The work was submitted but nothing signals the tracking fence,
+            // so the submission is only known to be complete once the queue is idle.
+            let lock = self.lock.lock();
+            let _ = unsafe { device.device.queue_wait_idle(self.queue) };
+            drop(lock);
+            device.garbage().abandon_submission(device, submission);
+            return Err(device.error("vkQueueSubmit", e));
         }
+        Ok(submission)
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
-    Buffer, Destroy, Device, Instance, NxError, NxResult, Pipeline, PipelineLayout,
-    RenderPassBeginDescriptor, Resource,
+    Buffer, DebugLabelScope, Destroy, Device, DeviceChild, Garbage, HostLock, Instance,
+    LabelTarget, NxError, NxResult, Pipeline, PipelineLayout, RenderPassBeginDescriptor, Resource,
 };
 use ash::vk::{
     ClearValue, CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo,
@@ -11,6 +11,7 @@ use ash::vk::{
 /// Stores information needed to create a CommandPool.
 pub struct CommandPoolDescriptor {
     queue_family_index: Option<usize>,
+    label: Option<String>,
 }
 
 impl CommandPoolDescriptor {
@@ -19,6 +20,7 @@ impl CommandPoolDescriptor {
     pub fn empty() -> Self {
         Self {
             queue_family_index: None,
+            label: None,
         }
     }
 
@@ -28,55 +30,81 @@ impl CommandPoolDescriptor {
         self.queue_family_index = Some(queue_family_index);
         self
     }
+
+    /// Specifies the debug name of the CommandPool. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
-pub struct CommandPool(pub(crate) ash::vk::CommandPool);
+/// Pool from which command recorders are allocated.
+/// Recorders are freed with their pool.
+///
+/// Vulkan requires a pool and its recorders to be externally synchronized,
+/// so the recorders of a pool share a lock that is held while a command is recorded.
+pub struct CommandPool {
+    pub(crate) pool: ash::vk::CommandPool,
+    pub(crate) lock: HostLock,
+    owner: DeviceChild,
+}
 
 impl CommandPool {
     #[doc(hidden)]
-    pub(crate) fn create(
-        device: &ash::Device,
-        descriptor: &CommandPoolDescriptor,
-    ) -> NxResult<Self> {
-        let queue_family_index = descriptor.queue_family_index.unwrap();
+    pub(crate) fn create(device: &Device, descriptor: &CommandPoolDescriptor) -> NxResult<Self> {
+        let queue_family_index = descriptor
+            .queue_family_index
+            .ok_or(NxError::MissingDescriptorField("queue_family_index"))?;
         let create_info = CommandPoolCreateInfo::builder()
             .queue_family_index(queue_family_index as u32)
             .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
             .build();
-        let pool = match unsafe { device.create_command_pool(&create_info, None) } {
+        let pool = match unsafe {
+            device
+                .device
+                .create_command_pool(&create_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => {
-                return match e {
-                    ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                    ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                    _ => Err(NxError::Unknown),
-                }
-            }
+            Err(e) => return Err(device.error("vkCreateCommandPool", e)),
         };
-        Ok(Self(pool))
+        device.label(pool, descriptor.label.as_deref());
+        Ok(Self {
+            pool,
+            lock: HostLock::default(),
+            owner: DeviceChild::new(device, pool, descriptor.label.as_deref()),
+        })
     }
 }
 
 impl Destroy for CommandPool {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_command_pool(self.0, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::CommandPool(self.pool));
+    }
+}
+
+impl Drop for CommandPool {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::CommandPool(self.pool));
     }
 }
 
 /// Stores information needed to create a CommandRecorder.
 pub struct CommandRecorderDescriptor {
     recorder_count: u32,
+    label: Option<String>,
 }
 
 impl CommandRecorderDescriptor {
     /// Initializes a new descriptor with default values.
     #[inline]
     pub fn empty() -> Self {
-        Self { recorder_count: 1 }
+        Self {
+            recorder_count: 1,
+            label: None,
+        }
     }
 
     /// Specifies the count of CommandRecorder.
@@ -85,44 +113,74 @@ impl CommandRecorderDescriptor {
         self.recorder_count = count;
         self
     }
+
+    /// Specifies the debug name of the CommandRecorder. See `DebugName`.
+    /// When more than one recorder is allocated, the index is appended, such as `name[1]`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 pub struct CommandRecorder {
     pub(crate) buffer: CommandBuffer,
+    /// Lock of the pool the recorder was allocated from.
+    pub(crate) pool: HostLock,
 }
 
 impl CommandRecorder {
     #[doc(hidden)]
     pub(crate) fn create(
         device: &Device,
-        pool: CommandPool,
+        pool: &CommandPool,
         descriptor: &CommandRecorderDescriptor,
     ) -> NxResult<Vec<Self>> {
         let create_info = CommandBufferAllocateInfo::builder()
-            .command_pool(pool.0)
+            .command_pool(pool.pool)
             .command_buffer_count(descriptor.recorder_count)
             .level(CommandBufferLevel::PRIMARY)
             .build();
+        let lock = pool.lock.lock();
         let buffers = match unsafe { device.device.allocate_command_buffers(&create_info) } {
             Ok(x) => x,
-            Err(e) => {
-                return match e {
-                    ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                    ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                    _ => Err(NxError::Unknown),
-                }
-            }
+            Err(e) => return Err(device.error("vkAllocateCommandBuffers", e)),
         };
+        drop(lock);
         assert_eq!(descriptor.recorder_count, buffers.len() as u32);
+        if let Some(label) = &descriptor.label {
+            for (i, buffer) in buffers.iter().enumerate() {
+                if buffers.len() == 1 {
+                    device.label(*buffer, Some(label));
+                } else {
+                    device.label(*buffer, Some(&format!("{}[{}]", label, i)));
+                }
+            }
+        }
         Ok(buffers
             .iter()
-            .map(|x| Self { buffer: *x })
+            .map(|x| Self {
+                buffer: *x,
+                pool: pool.lock.clone(),
+            })
             .collect::<Vec<Self>>())
     }
 
+    #[doc(hidden)]
+    fn label_target(&self) -> LabelTarget {
+        LabelTarget::Recorder(self.buffer, self.pool.clone())
+    }
+
     /// Starts recording commands.
     #[inline]
     pub fn begin(&self, device: &Device, descriptor: RenderPassBeginDescriptor) -> NxResult<()> {
+        device.check_validation()?;
+        let render_pass = descriptor
+            .render_pass
+            .ok_or(NxError::MissingDescriptorField("render_pass"))?;
+        let frame_buffer = descriptor
+            .frame_buffer
+            .ok_or(NxError::MissingDescriptorField("frame_buffer"))?;
         let create_info = CommandBufferBeginInfo::builder().build();
         let mut clear = ClearValue::default();
         unsafe {
@@ -132,8 +190,8 @@ impl CommandRecorder {
             clear.color.float32[3] = descriptor.a;
         }
         let begin_info = RenderPassBeginInfo::builder()
-            .render_pass(descriptor.render_pass.unwrap().render_pass)
-            .framebuffer(descriptor.frame_buffer.unwrap().frame_buffer)
+            .render_pass(render_pass.render_pass)
+            .framebuffer(frame_buffer.frame_buffer)
             .render_area(
                 Rect2D::builder()
                     .extent(
@@ -152,17 +210,14 @@ impl CommandRecorder {
             )
             .clear_values(&[clear])
             .build();
+        let _pool = self.pool.lock();
         unsafe {
             match device
                 .device
                 .begin_command_buffer(self.buffer, &create_info)
             {
                 Ok(_) => {}
-                Err(e) => match e {
-                    ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                    ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                    _ => Err(NxError::Unknown),
-                }?,
+                Err(e) => return Err(device.error("vkBeginCommandBuffer", e)),
             }
             device
                 .device
@@ -174,15 +229,13 @@ impl CommandRecorder {
     /// End recording commands.
     #[inline]
     pub fn end(&self, device: &Device) -> NxResult<()> {
+        device.check_validation()?;
+        let _pool = self.pool.lock();
         unsafe {
             device.device.cmd_end_render_pass(self.buffer);
             match device.device.end_command_buffer(self.buffer) {
                 Ok(_) => Ok(()),
-                Err(e) => match e {
-                    ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                    ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                    _ => Err(NxError::Unknown),
-                },
+                Err(e) => Err(device.error("vkEndCommandBuffer", e)),
             }
         }
     }
@@ -190,6 +243,7 @@ impl CommandRecorder {
     /// Bind the pipeline.
     #[inline]
     pub fn bind_pipeline(&self, device: &Device, pipeline: &Pipeline) {
+        let _pool = self.pool.lock();
         unsafe {
             device.device.cmd_bind_pipeline(
                 self.buffer,
@@ -202,6 +256,7 @@ impl CommandRecorder {
     /// Binds the vertex buffer.
     #[inline]
     pub fn bind_vertex_buffer(&self, device: &Device, buffer: &Buffer) {
+        let _pool = self.pool.lock();
         unsafe {
             device
                 .device
@@ -210,9 +265,9 @@ impl CommandRecorder {
     }
 
     /// Binds the index buffer.
-
     #[inline]
     pub fn bind_index_buffer(&self, device: &Device, buffer: &Buffer) {
+        let _pool = self.pool.lock();
         unsafe {
             device
                 .device
@@ -223,6 +278,7 @@ impl CommandRecorder {
     /// Binds the resource.
     #[inline]
     pub fn bind_resource(&self, device: &Device, resource: &Resource, layout: &PipelineLayout) {
+        let _pool = self.pool.lock();
         unsafe {
             device.device.cmd_bind_descriptor_sets(
                 self.buffer,
@@ -245,6 +301,7 @@ impl CommandRecorder {
         first_vertex: u32,
         first_instance: u32,
     ) {
+        let _pool = self.pool.lock();
         unsafe {
             device.device.cmd_draw(
                 self.buffer,
@@ -267,6 +324,7 @@ impl CommandRecorder {
         vertex_offset: i32,
         first_instance: u32,
     ) {
+        let _pool = self.pool.lock();
         unsafe {
             device.device.cmd_draw_indexed(
                 self.buffer,
@@ -279,16 +337,57 @@ impl CommandRecorder {
         }
     }
 
+    /// Opens a debug label region. Commands recorded until `end_debug_label` belong to it.
+    /// Regions can be nested, and are shown by debugging tools and in validation messages.
+    /// Does nothing if `VK_EXT_debug_utils` is not enabled.
+    #[inline]
+    pub fn begin_debug_label(&self, device: &Device, name: &str, color: [f32; 4]) {
+        self.label_target().begin(device, name, color);
+    }
+
+    /// Closes the debug label region opened last.
+    #[inline]
+    pub fn end_debug_label(&self, device: &Device) {
+        self.label_target().end(device);
+    }
+
+    /// Inserts a single debug label between commands.
+    #[inline]
+    pub fn insert_debug_label(&self, device: &Device, name: &str, color: [f32; 4]) {
+        self.label_target().insert(device, name, color);
+    }
+
+    /// Opens a debug label region that is closed when the returned scope is dropped.
+    ///
+    /// # Example
+    /// ```ignore
+    /// {
+    ///     let _scope = recorder.debug_label_scope(&device, "Shadow pass", [0.5, 0.5, 0.5, 1.0]);
+    ///     recorder.draw(&device, 3, 1, 0, 0);
+    /// }
+    /// ```
+    #[inline]
+    pub fn debug_label_scope<'a>(
+        &self,
+        device: &'a Device,
+        name: &str,
+        color: [f32; 4],
+    ) -> DebugLabelScope<'a> {
+        DebugLabelScope::new(device, self.label_target(), name, color)
+    }
+
     /// Reset CommandRecorder.
     #[inline]
     pub fn reset(&self, device: &Device) -> NxResult<()> {
+        device.check_validation()?;
+        let _pool = self.pool.lock();
         unsafe {
             match device
                 .device
                 .reset_command_buffer(self.buffer, CommandBufferResetFlags::empty())
             {
                 Ok(_) => Ok(()),
-                Err(_) => Err(NxError::OutOfDeviceMemory),
+                Err(e) => Err(device.error("vkResetCommandBuffer", e)),
             }
         }
     }
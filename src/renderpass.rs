@@ -4,7 +4,8 @@ use ash::vk::{
 };
 
 use crate::{
-    BindPoint, Destroy, Device, DeviceConnecter, FrameBuffer, Instance, NxError, NxResult,
+    BindPoint, Destroy, Device, DeviceChild, DeviceConnecter, FrameBuffer, Garbage, Instance,
+    NxResult,
 };
 
 /// Stores information needed to start a render pass.
@@ -149,6 +150,7 @@ pub struct RenderPassDescriptor<'a> {
     load_op: LoadOp,
     store_op: StoreOp,
     subpasses: &'a [SubPass],
+    label: Option<String>,
 }
 
 impl<'a> RenderPassDescriptor<'a> {
@@ -159,6 +161,7 @@ impl<'a> RenderPassDescriptor<'a> {
             load_op: LoadOp::DontCare,
             store_op: StoreOp::Store,
             subpasses: &[],
+            label: None,
         }
     }
 
@@ -179,10 +182,18 @@ impl<'a> RenderPassDescriptor<'a> {
         self.subpasses = subpasses;
         self
     }
+
+    /// Specifies the debug name of the RenderPass. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
 }
 
 pub struct RenderPass {
     pub(crate) render_pass: ash::vk::RenderPass,
+    owner: DeviceChild,
 }
 
 impl RenderPass {
@@ -193,6 +204,7 @@ impl RenderPass {
     /// * `descriptor` - Appropriate RenderPassDescriptor.
     #[inline]
     pub fn new(device: &Device, descriptor: &RenderPassDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let subpasses = descriptor
             .subpasses
             .iter()
@@ -213,24 +225,32 @@ impl RenderPass {
             .subpasses(&subpasses)
             .dependencies(&[])
             .build();
-        let render_pass = match unsafe { device.device.create_render_pass(&create_info, None) } {
+        let render_pass = match unsafe {
+            device
+                .device
+                .create_render_pass(&create_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkCreateRenderPass", e)),
         };
-        Ok(Self { render_pass })
+        device.label(render_pass, descriptor.label.as_deref());
+        Ok(Self {
+            render_pass,
+            owner: DeviceChild::new(device, render_pass, descriptor.label.as_deref()),
+        })
     }
 }
 
 impl Destroy for RenderPass {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_render_pass(self.render_pass, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::RenderPass(self.render_pass));
+    }
+}
+
+impl Drop for RenderPass {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::RenderPass(self.render_pass));
     }
 }
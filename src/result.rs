@@ -0,0 +1,108 @@
+use std::fmt;
+
+use ash::vk;
+
+use crate::NxError;
+
+/// Generates `VkError` with a variant for each listed `vk::Result` code.
+macro_rules! vk_errors {
+    ($($(#[$meta:meta])* $name:ident = $raw:ident,)*) => {
+        /// Result code of a failed Vulkan call.
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+        pub enum VkError {
+            $($(#[$meta])* $name,)*
+            /// A code without a variant, such as one from a newer Vulkan version.
+            Other(i32),
+        }
+
+        impl From<vk::Result> for VkError {
+            fn from(value: vk::Result) -> Self {
+                match value {
+                    $(vk::Result::$raw => VkError::$name,)*
+                    x => VkError::Other(x.as_raw()),
+                }
+            }
+        }
+
+        impl From<VkError> for vk::Result {
+            fn from(value: VkError) -> Self {
+                match value {
+                    $(VkError::$name => vk::Result::$raw,)*
+                    VkError::Other(x) => vk::Result::from_raw(x),
+                }
+            }
+        }
+    };
+}
+
+vk_errors! {
+    NotReady = NOT_READY,
+    Timeout = TIMEOUT,
+    Incomplete = INCOMPLETE,
+    Suboptimal = SUBOPTIMAL_KHR,
+    OutOfHostMemory = ERROR_OUT_OF_HOST_MEMORY,
+    OutOfDeviceMemory = ERROR_OUT_OF_DEVICE_MEMORY,
+    InitializationFailed = ERROR_INITIALIZATION_FAILED,
+    DeviceLost = ERROR_DEVICE_LOST,
+    MemoryMapFailed = ERROR_MEMORY_MAP_FAILED,
+    LayerNotPresent = ERROR_LAYER_NOT_PRESENT,
+    ExtensionNotPresent = ERROR_EXTENSION_NOT_PRESENT,
+    FeatureNotPresent = ERROR_FEATURE_NOT_PRESENT,
+    IncompatibleDriver = ERROR_INCOMPATIBLE_DRIVER,
+    TooManyObjects = ERROR_TOO_MANY_OBJECTS,
+    FormatNotSupported = ERROR_FORMAT_NOT_SUPPORTED,
+    /// A pool allocation failed due to fragmentation of the pool's memory.
+    FragmentedPool = ERROR_FRAGMENTED_POOL,
+    OutOfPoolMemory = ERROR_OUT_OF_POOL_MEMORY,
+    InvalidExternalHandle = ERROR_INVALID_EXTERNAL_HANDLE,
+    Fragmentation = ERROR_FRAGMENTATION,
+    InvalidOpaqueCaptureAddress = ERROR_INVALID_OPAQUE_CAPTURE_ADDRESS,
+    SurfaceLost = ERROR_SURFACE_LOST_KHR,
+    NativeWindowInUse = ERROR_NATIVE_WINDOW_IN_USE_KHR,
+    /// The surface changed and the swapchain must be recreated.
+    OutOfDate = ERROR_OUT_OF_DATE_KHR,
+    IncompatibleDisplay = ERROR_INCOMPATIBLE_DISPLAY_KHR,
+    ValidationFailed = ERROR_VALIDATION_FAILED_EXT,
+    InvalidShader = ERROR_INVALID_SHADER_NV,
+    FullScreenExclusiveModeLost = ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT,
+    CompressionExhausted = ERROR_COMPRESSION_EXHAUSTED_EXT,
+    Unknown = ERROR_UNKNOWN,
+}
+
+impl fmt::Display for VkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", vk::Result::from(*self))
+    }
+}
+
+impl NxError {
+    /// Converts the result of the Vulkan command `call`.
+    /// Results of commands on a device should go through `Device::error` so that device loss is tracked.
+    #[doc(hidden)]
+    pub(crate) fn vulkan(call: &'static str, result: vk::Result) -> Self {
+        NxError::Vulkan {
+            call,
+            error: result.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_every_code() {
+        for raw in [
+            vk::Result::ERROR_FRAGMENTED_POOL,
+            vk::Result::ERROR_OUT_OF_DATE_KHR,
+            vk::Result::from_raw(-1_000_999_000),
+        ] {
+            assert_eq!(vk::Result::from(VkError::from(raw)), raw);
+        }
+        assert_eq!(
+            VkError::from(vk::Result::ERROR_OUT_OF_POOL_MEMORY),
+            VkError::OutOfPoolMemory
+        );
+    }
+}
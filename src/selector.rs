@@ -0,0 +1,300 @@
+use ash::vk;
+
+use crate::{
+    DeviceConnecter, Feature, FeatureSet, Instance, NxResult, QueueFamilyProperties, Surface,
+};
+
+/// Indicates the type of a physical device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum DeviceType {
+    /// A GPU separate from the host processor.
+    Discrete,
+    /// A GPU embedded in or tightly coupled with the host processor.
+    Integrated,
+    /// A GPU in a virtualized environment.
+    Virtual,
+    /// Running on the host processor, such as a software rasterizer.
+    Cpu,
+    Other,
+}
+
+impl DeviceType {
+    #[doc(hidden)]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl From<vk::PhysicalDeviceType> for DeviceType {
+    fn from(value: vk::PhysicalDeviceType) -> Self {
+        match value {
+            vk::PhysicalDeviceType::DISCRETE_GPU => DeviceType::Discrete,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => DeviceType::Integrated,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => DeviceType::Virtual,
+            vk::PhysicalDeviceType::CPU => DeviceType::Cpu,
+            _ => DeviceType::Other,
+        }
+    }
+}
+
+/// Queue family indices chosen for a physical device.
+///
+/// A capability that was not requested, or that no queue family supports, is `None`.
+/// Several capabilities may share the same family.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct QueueFamilyIndices {
+    graphics: Option<usize>,
+    compute: Option<usize>,
+    transfer: Option<usize>,
+    present: Option<usize>,
+}
+
+impl QueueFamilyIndices {
+    /// Family supporting graphics.
+    pub fn graphics(&self) -> Option<usize> {
+        self.graphics
+    }
+
+    /// Family supporting compute. A family without graphics support is preferred.
+    pub fn compute(&self) -> Option<usize> {
+        self.compute
+    }
+
+    /// Family supporting transfer. A family supporting neither graphics nor compute is preferred.
+    pub fn transfer(&self) -> Option<usize> {
+        self.transfer
+    }
+
+    /// Family that can present to the surface given to `ConnecterSelector::surface`.
+    /// The graphics family is preferred.
+    pub fn present(&self) -> Option<usize> {
+        self.present
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn find(families: &[QueueFamilyProperties], present_support: &[bool]) -> Self {
+        let position = |f: &dyn Fn(&QueueFamilyProperties) -> bool| families.iter().position(f);
+
+        let graphics = position(&|x| x.graphic_support);
+        let compute = position(&|x| x.compute_support && !x.graphic_support)
+            .or_else(|| position(&|x| x.compute_support));
+        // Graphics and compute families always support transfer, even if they do not report it.
+        let transfer =
+            position(&|x| x.transfer_support && !x.graphic_support && !x.compute_support)
+                .or_else(|| position(&|x| x.transfer_support))
+                .or(graphics)
+                .or(compute);
+        let present = match graphics {
+            Some(x) if present_support.get(x).copied().unwrap_or(false) => Some(x),
+            _ => present_support.iter().position(|x| *x),
+        };
+        Self {
+            graphics,
+            compute,
+            transfer,
+            present,
+        }
+    }
+}
+
+#[doc(hidden)]
+type Scorer<'a> = Box<dyn Fn(&Instance, DeviceConnecter) -> Option<i64> + 'a>;
+
+/// Stores the requirements and preferences used to choose a physical device.
+///
+/// Every physical device that meets the requirements is given a score,
+/// and the one with the highest score is chosen by `Instance::select_connecter`.
+/// By default, graphics support is required and discrete GPUs are preferred.
+///
+/// # Example
+/// ```no_run
+/// use nexg::{ConnecterSelector, DeviceType, InstanceBuilder};
+///
+/// let instance = InstanceBuilder::new().build().unwrap();
+/// let selector = ConnecterSelector::new()
+///     .compute(true)
+///     .device_type_score(DeviceType::Integrated, 2000);
+/// let (connecter, indices) = instance.select_connecter(&selector).unwrap();
+/// let index = indices.graphics().unwrap();
+/// let device = connecter.create_device(&instance, index).unwrap();
+/// ```
+pub struct ConnecterSelector<'a> {
+    device_type_scores: [i64; 5],
+    graphics: bool,
+    compute: bool,
+    transfer: bool,
+    surface: Option<&'a Surface>,
+    extensions: Vec<String>,
+    features: FeatureSet,
+    scorer: Option<Scorer<'a>>,
+}
+
+impl<'a> ConnecterSelector<'a> {
+    /// Initializes a new selector with default values.
+    pub fn new() -> Self {
+        Self {
+            device_type_scores: [1000, 500, 200, 100, 0],
+            graphics: true,
+            compute: false,
+            transfer: false,
+            surface: None,
+            extensions: vec![],
+            features: FeatureSet::new(),
+            scorer: None,
+        }
+    }
+
+    /// Specifies the score added to devices of the given type.
+    ///
+    /// Defaults to 1000 for `Discrete`, 500 for `Integrated`, 200 for `Virtual`, 100 for `Cpu` and 0 for `Other`.
+    pub fn device_type_score(mut self, device_type: DeviceType, score: i64) -> Self {
+        self.device_type_scores[device_type.index()] = score;
+        self
+    }
+
+    /// Requires a queue family supporting graphics. Defaults to `true`.
+    pub fn graphics(mut self, required: bool) -> Self {
+        self.graphics = required;
+        self
+    }
+
+    /// Requires a queue family supporting compute. Defaults to `false`.
+    pub fn compute(mut self, required: bool) -> Self {
+        self.compute = required;
+        self
+    }
+
+    /// Requires a queue family supporting transfer. Defaults to `false`.
+    pub fn transfer(mut self, required: bool) -> Self {
+        self.transfer = required;
+        self
+    }
+
+    /// Requires a queue family that can present to the surface.
+    pub fn surface(mut self, surface: &'a Surface) -> Self {
+        self.surface = Some(surface);
+        self
+    }
+
+    /// Requires a device extension, such as `VK_KHR_swapchain`.
+    /// Extensions requested with `InstanceFeature` are always required.
+    pub fn require_extension(mut self, name: &str) -> Self {
+        if !self.extensions.iter().any(|x| x == name) {
+            self.extensions.push(name.to_owned());
+        }
+        self
+    }
+
+    /// Requires a device feature.
+    pub fn require_feature(mut self, feature: Feature) -> Self {
+        self.features.insert(feature);
+        self
+    }
+
+    /// Specifies a function that scores each device meeting the requirements.
+    /// The returned score is added to the device type score; returning `None` rejects the device.
+    pub fn scorer(
+        mut self,
+        scorer: impl Fn(&Instance, DeviceConnecter) -> Option<i64> + 'a,
+    ) -> Self {
+        self.scorer = Some(Box::new(scorer));
+        self
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn evaluate(
+        &self,
+        instance: &Instance,
+        connecter: DeviceConnecter,
+    ) -> NxResult<Option<(i64, QueueFamilyIndices)>> {
+        let families = connecter.get_queue_family_properties(instance)?;
+        let present_support = match self.surface {
+            Some(surface) => (0..families.len())
+                .map(|i| connecter.is_present_supported(surface, i))
+                .collect::<NxResult<Vec<bool>>>()?,
+            None => vec![],
+        };
+        let indices = QueueFamilyIndices::find(&families, &present_support);
+        if (self.graphics && indices.graphics.is_none())
+            || (self.compute && indices.compute.is_none())
+            || (self.transfer && indices.transfer.is_none())
+            || (self.surface.is_some() && indices.present.is_none())
+        {
+            return Ok(None);
+        }
+
+        let available = connecter.available_extensions(instance)?;
+        let required = self
+            .extensions
+            .iter()
+            .cloned()
+            .chain(instance.device_exts.iter().map(|x| x.name()));
+        for name in required {
+            if !available.iter().any(|x| x.name() == name) {
+                return Ok(None);
+            }
+        }
+
+        if !self.features.is_empty()
+            && self
+                .features
+                .first_missing(&connecter.features(instance))
+                .is_some()
+        {
+            return Ok(None);
+        }
+
+        let mut score = self.device_type_scores[connecter.device_type(instance).index()];
+        if let Some(scorer) = &self.scorer {
+            match scorer(instance, connecter) {
+                Some(x) => score += x,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((score, indices)))
+    }
+}
+
+impl Default for ConnecterSelector<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family(graphic: bool, compute: bool, transfer: bool) -> QueueFamilyProperties {
+        QueueFamilyProperties {
+            graphic_support: graphic,
+            compute_support: compute,
+            transfer_support: transfer,
+            queue_count: 1,
+        }
+    }
+
+    #[test]
+    fn prefers_dedicated_families() {
+        let families = [
+            family(true, true, true),
+            family(false, true, true),
+            family(false, false, true),
+        ];
+        let indices = QueueFamilyIndices::find(&families, &[false, true, false]);
+        assert_eq!(indices.graphics(), Some(0));
+        assert_eq!(indices.compute(), Some(1));
+        assert_eq!(indices.transfer(), Some(2));
+        assert_eq!(indices.present(), Some(1));
+    }
+
+    #[test]
+    fn falls_back_to_the_graphics_family() {
+        let families = [family(false, false, false), family(true, true, false)];
+        let indices = QueueFamilyIndices::find(&families, &[true, true]);
+        assert_eq!(indices.graphics(), Some(1));
+        assert_eq!(indices.compute(), Some(1));
+        assert_eq!(indices.transfer(), Some(1));
+        assert_eq!(indices.present(), Some(1));
+    }
+}
@@ -5,20 +5,16 @@ use ash::{
 };
 use std::io::{Cursor, Read};
 
-use crate::{Destroy, Device, Instance, NxError, NxResult};
+use crate::{Destroy, Device, DeviceChild, Garbage, Instance, NxError, NxResult};
 
 /// Represents a Spir-V intermediate representation
 ///
 /// This structure contains binary data that has been processed so that Vulkan can read it
 ///
 /// # Example
-/// ```no_run
-/// let fragment_shader = device
-/// .create_shader_module(
-///     Spirv::new("examples/shader/shader.frag.spv"),
-///     ShaderKind::Fragment,
-/// )
-/// .unwrap();
+/// ```ignore
+/// let spirv = Spirv::new("examples/shader/shader.frag.spv")?;
+/// let fragment_shader = Shader::new(&device, &spirv)?;
 /// ```
 pub struct Spirv {
     pub(crate) data: Vec<u32>,
@@ -30,7 +26,10 @@ impl Spirv {
     ///
     /// * `file` - Spv file path.
     pub fn new(file: &str) -> NxResult<Self> {
-        let mut file = std::fs::File::open(file).expect("file open failed");
+        let mut file = match std::fs::File::open(file) {
+            Ok(x) => x,
+            Err(e) => return Err(NxError::IoError(e.to_string())),
+        };
         let mut buf = Vec::new();
         match file.read_to_end(&mut buf) {
             Ok(_) => {}
@@ -60,34 +59,79 @@ impl Spirv {
     }
 }
 
+/// Stores information needed to create a Shader.
+pub struct ShaderDescriptor<'a> {
+    spirv: Option<&'a Spirv>,
+    label: Option<String>,
+}
+
+impl<'a> ShaderDescriptor<'a> {
+    /// Initializes a new descriptor with default values.
+    pub fn empty() -> Self {
+        Self {
+            spirv: None,
+            label: None,
+        }
+    }
+
+    /// Specifies the code of the Shader.
+    pub fn spirv(mut self, spirv: &'a Spirv) -> Self {
+        self.spirv = Some(spirv);
+        self
+    }
+
+    /// Specifies the debug name of the Shader. See `DebugName`.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
+}
+
 /// Represents a shader
 ///
 /// It can be created with create_shader_module from Device
-#[derive(Clone, Copy, Debug)]
 pub struct Shader {
     pub(crate) inner: ShaderModule,
+    owner: DeviceChild,
 }
 
 impl Shader {
-    pub fn new(device: &Device, spirv: &Spirv) -> Shader {
+    pub fn new(device: &Device, spirv: &Spirv) -> NxResult<Shader> {
+        Self::create(device, &ShaderDescriptor::empty().spirv(spirv))
+    }
+
+    /// Fails with `NxError::MissingDescriptorField` if the code is not specified.
+    pub fn create(device: &Device, descriptor: &ShaderDescriptor) -> NxResult<Shader> {
+        device.check_validation()?;
+        let spirv = descriptor
+            .spirv
+            .ok_or(NxError::MissingDescriptorField("spirv"))?;
         let shader_create_info = ShaderModuleCreateInfo::builder().code(&spirv.data).build();
         let shader = unsafe {
             device
                 .device
-                .create_shader_module(&shader_create_info, None)
+                .create_shader_module(&shader_create_info, device.allocation_callbacks())
         }
-        .unwrap();
-        Shader { inner: shader }
+        .map_err(|e| device.error("vkCreateShaderModule", e))?;
+        device.label(shader, descriptor.label.as_deref());
+        Ok(Shader {
+            inner: shader,
+            owner: DeviceChild::new(device, shader, descriptor.label.as_deref()),
+        })
     }
 }
 
 impl Destroy for Shader {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_shader_module(self.inner, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::ShaderModule(self.inner));
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::ShaderModule(self.inner));
     }
 }
 
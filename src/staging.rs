@@ -0,0 +1,683 @@
+use std::collections::VecDeque;
+
+use ash::vk;
+
+use crate::{
+    Buffer, BufferDescriptor, BufferUsage, CommandPool, CommandPoolDescriptor, CommandRecorder,
+    CommandRecorderDescriptor, Device, Extent3d, Image, ImageLayout, MemoryLocation, NxError,
+    NxResult, Queue, QueueSubmitDescriptor,
+};
+
+/// Marks types that can be uploaded as plain bytes.
+///
+/// # Safety
+/// Every byte of the type must be initialized, so it must have no padding, and any bit pattern must be valid.
+/// Implement it for `#[repr(C)]` structs whose fields are all `Pod` and leave no gap between them.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Views the data as bytes, which `Pod` guarantees are all initialized.
+fn as_bytes<T: Pod>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+/// Ring of bytes handed out in submission order.
+/// Positions only grow; the offset of a position in the ring is `position % capacity`.
+#[doc(hidden)]
+pub(crate) struct Ring {
+    capacity: u64,
+    /// Position of the next range.
+    head: u64,
+    /// Position of the oldest range still in use.
+    tail: u64,
+    /// Submission using the ranges before each end position, in submission order.
+    submitted: VecDeque<(u64, u64)>,
+}
+
+impl Ring {
+    pub(crate) fn new(capacity: u64) -> Self {
+        Self {
+            capacity,
+            head: 0,
+            tail: 0,
+            submitted: VecDeque::new(),
+        }
+    }
+
+    /// Contiguous free spans: the rest of the current lap, and the start of the next one.
+    fn spans(&self, alignment: u64) -> [(u64, u64); 2] {
+        let offset = self.head % self.capacity;
+        let lap_end = self.head - offset + self.capacity;
+        let limit = self.tail + self.capacity;
+        [
+            (
+                self.head - offset + align_up(offset, alignment),
+                lap_end.min(limit),
+            ),
+            (lap_end, limit),
+        ]
+    }
+
+    /// Size of the largest range that can be allocated now.
+    pub(crate) fn available(&self, alignment: u64) -> u64 {
+        self.spans(alignment)
+            .iter()
+            .map(|(start, end)| end.saturating_sub(*start))
+            .max()
+            .unwrap()
+    }
+
+    /// Returns the offset of a range of `size` bytes, or `None` if the ring is too full.
+    pub(crate) fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let (start, _) = self
+            .spans(alignment)
+            .into_iter()
+            .find(|(start, end)| start + size <= *end)?;
+        self.head = start + size;
+        Some(start % self.capacity)
+    }
+
+    /// Marks the ranges allocated since the last call as used by `submission`.
+    pub(crate) fn submit(&mut self, submission: u64) {
+        self.submitted.push_back((submission, self.head));
+    }
+
+    /// Releases the ranges of the submissions up to `completed`.
+    pub(crate) fn retire(&mut self, completed: u64) {
+        while let Some(&(submission, end)) = self.submitted.front() {
+            if submission > completed {
+                break;
+            }
+            self.tail = end;
+            self.submitted.pop_front();
+        }
+        if self.tail == self.head {
+            // Restarts at the beginning of the ring, so that the whole ring is contiguous.
+            self.head = align_up(self.head, self.capacity);
+            self.tail = self.head;
+        }
+    }
+}
+
+/// Stores information needed to create a StagingBelt.
+pub struct StagingBeltDescriptor {
+    size: u64,
+    queue_family_index: Option<usize>,
+    label: Option<String>,
+}
+
+impl StagingBeltDescriptor {
+    /// Initializes a new descriptor with default values.
+    /// The default size is 4 MiB.
+    #[inline]
+    pub fn empty() -> Self {
+        Self {
+            size: 4 << 20,
+            queue_family_index: None,
+            label: None,
+        }
+    }
+
+    /// Specifies the size of the ring in bytes. Larger uploads are split into several copies.
+    #[inline]
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Specifies the queue family of the queues the uploads are submitted to.
+    #[inline]
+    pub fn queue_family_index(mut self, queue_family_index: usize) -> Self {
+        self.queue_family_index = Some(queue_family_index);
+        self
+    }
+
+    /// Specifies the debug name of the ring buffer and its command pool. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
+    }
+}
+
+/// Stores the region of an image written by `StagingBelt::upload_image`.
+pub struct ImageUploadDescriptor {
+    offset: [u32; 3],
+    extent: Extent3d,
+    mip_level: u32,
+    array_layer: u32,
+    current_layout: Option<ImageLayout>,
+    final_layout: ImageLayout,
+}
+
+impl ImageUploadDescriptor {
+    /// Initializes a new descriptor writing `extent` texels at the origin of the first mip level and layer.
+    /// The image is left in `ImageLayout::ShaderReadOnly`.
+    #[inline]
+    pub fn new(extent: Extent3d) -> Self {
+        Self {
+            offset: [0; 3],
+            extent,
+            mip_level: 0,
+            array_layer: 0,
+            current_layout: None,
+            final_layout: ImageLayout::ShaderReadOnly,
+        }
+    }
+
+    /// Specifies the first texel of the region.
+    #[inline]
+    pub fn offset(mut self, x: u32, y: u32, z: u32) -> Self {
+        self.offset = [x, y, z];
+        self
+    }
+
+    #[inline]
+    pub fn mip_level(mut self, mip_level: u32) -> Self {
+        self.mip_level = mip_level;
+        self
+    }
+
+    #[inline]
+    pub fn array_layer(mut self, array_layer: u32) -> Self {
+        self.array_layer = array_layer;
+        self
+    }
+
+    /// Specifies the layout of the image before the upload, so that the rest of the image is preserved.
+    /// Without it, the previous contents of the image are discarded.
+    #[inline]
+    pub fn current_layout(mut self, layout: ImageLayout) -> Self {
+        self.current_layout = Some(layout);
+        self
+    }
+
+    /// Specifies the layout of the image once the upload completes.
+    #[inline]
+    pub fn final_layout(mut self, layout: ImageLayout) -> Self {
+        self.final_layout = layout;
+        self
+    }
+}
+
+/// Identifies an upload of a `StagingBelt`.
+/// An upload is complete once the GPU has finished copying its data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+pub struct UploadToken(u64);
+
+#[doc(hidden)]
+struct Chunk {
+    submission: u64,
+    recorder: CommandRecorder,
+}
+
+/// Uploads data to device-local buffers and images through a host-visible ring buffer.
+///
+/// Each upload copies its data into the ring, then submits the copy commands to a queue.
+/// When the ring is full, an upload waits for the oldest copies to complete and is split into several copies.
+/// Subsequent submissions to the same queue see the uploaded data.
+///
+/// Data is copied as bytes, so its type must implement `Pod`.
+///
+/// # Example
+/// ```ignore
+/// let desc = BufferDescriptor::empty()
+///     .size(std::mem::size_of_val(&VERTICES))
+///     .location(MemoryLocation::GpuOnly);
+/// let vertex_buffer = Buffer::new(&instance, connecter, &device, &desc).unwrap();
+///
+/// let desc = StagingBeltDescriptor::empty().queue_family_index(index);
+/// let mut belt = StagingBelt::new(&device, &desc).unwrap();
+/// // `Vertex` is `#[repr(C)]` and made of `f32` fields only, so it has no padding.
+/// unsafe impl Pod for Vertex {}
+/// let token = belt
+///     .upload_buffer(&device, &queue, &vertex_buffer, 0, &VERTICES)
+///     .unwrap();
+/// belt.wait(&device, token).unwrap();
+/// ```
+pub struct StagingBelt {
+    buffer: Buffer,
+    ring: Ring,
+    pool: CommandPool,
+    queue_family_index: usize,
+    in_flight: VecDeque<Chunk>,
+    idle: Vec<CommandRecorder>,
+    /// Submission of the last copy, as tracked by the garbage collector of the device.
+    last: u64,
+}
+
+impl StagingBelt {
+    pub fn new(device: &Device, descriptor: &StagingBeltDescriptor) -> NxResult<Self> {
+        let mut buffer = BufferDescriptor::empty()
+            .size(descriptor.size as usize)
+            .usage(BufferUsage::Transfer)
+            .location(MemoryLocation::Upload);
+        let queue_family_index = descriptor
+            .queue_family_index
+            .ok_or(NxError::MissingDescriptorField("queue_family_index"))?;
+        let mut pool = CommandPoolDescriptor::empty().queue_family_index(queue_family_index);
+        if let Some(label) = &descriptor.label {
+            buffer = buffer.label(label);
+            pool = pool.label(label);
+        }
+        Ok(Self {
+            buffer: Buffer::create(device, &buffer)?,
+            ring: Ring::new(descriptor.size),
+            pool: CommandPool::create(device, &pool)?,
+            queue_family_index,
+            in_flight: VecDeque::new(),
+            idle: vec![],
+            last: 0,
+        })
+    }
+
+    /// Copies `data` into `buffer` at `offset` bytes.
+    /// `queue` must belong to the queue family of the belt.
+    ///
+    /// Fails with `NxError::OutOfBounds` if the data does not fit in the buffer.
+    pub fn upload_buffer<T: Pod>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        buffer: &Buffer,
+        offset: u64,
+        data: &[T],
+    ) -> NxResult<UploadToken> {
+        device.check_validation()?;
+        let bytes = as_bytes(data);
+        let size = buffer.size(device);
+        let end = offset.saturating_add(bytes.len() as u64);
+        if end > size {
+            return Err(NxError::OutOfBounds { end, size });
+        }
+        let mut written = 0;
+        while written < bytes.len() {
+            let remaining = (bytes.len() - written) as u64;
+            let (staging, size) = self.reserve(device, remaining, 1, 1)?;
+            self.write(device, staging, &bytes[written..written + size as usize])?;
+            let region = vk::BufferCopy::builder()
+                .src_offset(staging)
+                .dst_offset(offset + written as u64)
+                .size(size)
+                .build();
+            let last = size == remaining;
+            let source = self.buffer.buffer;
+            self.submit(device, queue, |x| unsafe {
+                device
+                    .device
+                    .cmd_copy_buffer(x, source, buffer.buffer, &[region]);
+                if last {
+                    let barrier = vk::MemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(
+                            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                        )
+                        .build();
+                    device.device.cmd_pipeline_barrier(
+                        x,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::ALL_COMMANDS,
+                        vk::DependencyFlags::empty(),
+                        &[barrier],
+                        &[],
+                        &[],
+                    );
+                }
+            })?;
+            written += size as usize;
+        }
+        Ok(UploadToken(self.last))
+    }
+
+    /// Copies `data` into a region of `image`, with rows of texels stored contiguously.
+    /// The image must be created with `ImageUsage::TransferDst`, and `queue` must belong to the queue family of the belt.
+    ///
+    /// Fails with `NxError::FormatNotCopyable` for formats with both depth and stencil,
+    /// with `NxError::RegionOutOfBounds` if the region does not fit in the image,
+    /// and with `NxError::ImageTransferGranularity` if the queue family copies images in blocks of several texels.
+    pub fn upload_image<T: Pod>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        image: &Image,
+        descriptor: &ImageUploadDescriptor,
+        data: &[T],
+    ) -> NxResult<UploadToken> {
+        device.check_validation()?;
+        let bytes = as_bytes(data);
+        // Copies are split by rows, which only the granularity of (1, 1, 1) allows anywhere in the image.
+        let unit = vk::Extent3D {
+            width: 1,
+            height: 1,
+            depth: 1,
+        };
+        if device.image_transfer_granularity(self.queue_family_index) != Some(unit) {
+            return Err(NxError::ImageTransferGranularity(self.queue_family_index));
+        }
+        let texel = image
+            .format
+            .texel_size()
+            .ok_or(NxError::FormatNotCopyable(image.format))? as u64;
+        let extent = descriptor.extent;
+        let fits = image
+            .level_extent(descriptor.mip_level, descriptor.array_layer)
+            .is_some_and(|x| region_fits(descriptor.offset, extent, x));
+        if !fits {
+            return Err(NxError::RegionOutOfBounds {
+                mip_level: descriptor.mip_level,
+                array_layer: descriptor.array_layer,
+            });
+        }
+        let row = extent.width() as u64 * texel;
+        let expected = row * extent.height() as u64 * extent.depth() as u64;
+        if bytes.len() as u64 != expected {
+            return Err(NxError::DataSizeMismatch {
+                expected,
+                actual: bytes.len() as u64,
+            });
+        }
+        // Offsets in the buffer must be a multiple of both the texel size and 4.
+        let alignment = match texel % 4 {
+            0 => texel,
+            2 => texel * 2,
+            _ => texel * 4,
+        };
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(image.format.aspect())
+            .mip_level(descriptor.mip_level)
+            .base_array_layer(descriptor.array_layer)
+            .layer_count(1)
+            .build();
+        let range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(subresource.aspect_mask)
+            .base_mip_level(descriptor.mip_level)
+            .level_count(1)
+            .base_array_layer(descriptor.array_layer)
+            .layer_count(1)
+            .build();
+        let barrier = |old, new, src_access, dst_access| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(old)
+                .new_layout(new)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image.image)
+                .subresource_range(range)
+                .build()
+        };
+
+        let rows = extent.height() as u64 * extent.depth() as u64;
+        let mut first = true;
+        let mut copied = 0;
+        while copied < rows {
+            // A copy stays within a slice, so that it covers a rectangle.
+            let (y, z) = (
+                copied % extent.height() as u64,
+                copied / extent.height() as u64,
+            );
+            let slice_rows = extent.height() as u64 - y;
+            let (staging, size) = self.reserve(device, slice_rows * row, row, alignment)?;
+            let count = size / row;
+            let start = (copied * row) as usize;
+            self.write(device, staging, &bytes[start..start + size as usize])?;
+            let region = vk::BufferImageCopy::builder()
+                .buffer_offset(staging)
+                .image_subresource(subresource)
+                .image_offset(vk::Offset3D {
+                    x: descriptor.offset[0] as i32,
+                    y: (descriptor.offset[1] as u64 + y) as i32,
+                    z: (descriptor.offset[2] as u64 + z) as i32,
+                })
+                .image_extent(vk::Extent3D {
+                    width: extent.width(),
+                    height: count as u32,
+                    depth: 1,
+                })
+                .build();
+            let old_layout = match (first, descriptor.current_layout) {
+                (true, Some(x)) => x.into(),
+                (true, None) => vk::ImageLayout::UNDEFINED,
+                (false, _) => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            };
+            let last = copied + count == rows;
+            let source = self.buffer.buffer;
+            self.submit(device, queue, |x| unsafe {
+                device.device.cmd_pipeline_barrier(
+                    x,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(
+                        old_layout,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::AccessFlags::MEMORY_WRITE,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    )],
+                );
+                device.device.cmd_copy_buffer_to_image(
+                    x,
+                    source,
+                    image.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[region],
+                );
+                if last {
+                    device.device.cmd_pipeline_barrier(
+                        x,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::ALL_COMMANDS,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[],
+                        &[barrier(
+                            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            descriptor.final_layout.into(),
+                            vk::AccessFlags::TRANSFER_WRITE,
+                            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                        )],
+                    );
+                }
+            })?;
+            first = false;
+            copied += count;
+        }
+        Ok(UploadToken(self.last))
+    }
+
+    /// Check whether the upload has completed, and recycle the space of completed uploads.
+    pub fn is_complete(&mut self, device: &Device, token: UploadToken) -> NxResult<bool> {
+        self.recall(device)?;
+        Ok(device.garbage().completed() >= token.0)
+    }
+
+    /// Wait until the upload has completed.
+    pub fn wait(&mut self, device: &Device, token: UploadToken) -> NxResult<()> {
+        device.garbage().wait(device, token.0)?;
+        self.recall(device)
+    }
+
+    /// Reserves a range of the ring for up to `size` bytes, in multiples of `unit`.
+    /// Waits for the oldest copies while the ring is full.
+    fn reserve(
+        &mut self,
+        device: &Device,
+        size: u64,
+        unit: u64,
+        alignment: u64,
+    ) -> NxResult<(u64, u64)> {
+        loop {
+            self.recall(device)?;
+            let size = size.min(self.ring.available(alignment) / unit * unit);
+            if size > 0 {
+                return Ok((self.ring.allocate(size, alignment).unwrap(), size));
+            }
+            if self.in_flight.is_empty() {
+                return Err(NxError::StagingBeltTooSmall(unit));
+            }
+            self.wait_oldest(device)?;
+        }
+    }
+
+    fn write(&self, device: &Device, offset: u64, data: &[u8]) -> NxResult<()> {
+        let mapped = self.buffer.memory.mapped()? as *mut u8;
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.add(offset as usize), data.len())
+        };
+        self.buffer
+            .memory
+            .flush_range(device, offset, data.len() as u64)
+    }
+
+    /// Records commands with `record` and submits them.
+    fn submit(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        record: impl FnOnce(vk::CommandBuffer),
+    ) -> NxResult<()> {
+        let recorder = match self.idle.pop() {
+            Some(x) => x,
+            None => {
+                CommandRecorder::create(device, &self.pool, &CommandRecorderDescriptor::empty())?
+                    .remove(0)
+            }
+        };
+        // The fence tracking the submission for the garbage collector also tracks the copy.
+        let result = Self::record(device, &recorder, record).and_then(|_| {
+            queue.submit_tracked(
+                device,
+                &QueueSubmitDescriptor::empty(),
+                std::slice::from_ref(&recorder),
+            )
+        });
+        match result {
+            Ok(submission) => {
+                self.ring.submit(submission);
+                self.last = submission;
+                self.in_flight.push_back(Chunk {
+                    submission,
+                    recorder,
+                });
+                Ok(())
+            }
+            Err(e) => {
+                // The range was not used, so it is released with the previous copy.
+                self.ring.submit(self.last);
+                self.idle.push(recorder);
+                Err(e)
+            }
+        }
+    }
+
+    fn record(
+        device: &Device,
+        recorder: &CommandRecorder,
+        record: impl FnOnce(vk::CommandBuffer),
+    ) -> NxResult<()> {
+        let _pool = recorder.pool.lock();
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+        unsafe {
+            device
+                .device
+                .begin_command_buffer(recorder.buffer, &begin_info)
+        }
+        .map_err(|e| device.error("vkBeginCommandBuffer", e))?;
+        record(recorder.buffer);
+        unsafe { device.device.end_command_buffer(recorder.buffer) }
+            .map_err(|e| device.error("vkEndCommandBuffer", e))
+    }
+
+    /// Recycles the copies that have completed, in submission order.
+    /// Also retires the completed submissions of the device, so their fences are reused.
+    fn recall(&mut self, device: &Device) -> NxResult<()> {
+        device.garbage().collect(device)?;
+        let completed = device.garbage().completed();
+        while let Some(chunk) = self.in_flight.front() {
+            if chunk.submission > completed {
+                break;
+            }
+            let chunk = self.in_flight.pop_front().unwrap();
+            self.idle.push(chunk.recorder);
+        }
+        self.ring.retire(completed);
+        Ok(())
+    }
+
+    fn wait_oldest(&mut self, device: &Device) -> NxResult<()> {
+        if let Some(chunk) = self.in_flight.front() {
+            device.garbage().wait(device, chunk.submission)?;
+        }
+        self.recall(device)
+    }
+}
+
+/// Whether a region at `offset` of `extent` texels lies within a mip level of `level` texels.
+fn region_fits(offset: [u32; 3], extent: Extent3d, level: Extent3d) -> bool {
+    let fits = |offset: u32, size: u32, limit: u32| offset as u64 + size as u64 <= limit as u64;
+    fits(offset[0], extent.width(), level.width())
+        && fits(offset[1], extent.height(), level.height())
+        && fits(offset[2], extent.depth(), level.depth())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_around_and_waits_for_submissions() {
+        let mut ring = Ring::new(256);
+        assert_eq!(ring.allocate(100, 1), Some(0));
+        ring.submit(1);
+        assert_eq!(ring.allocate(100, 1), Some(100));
+        ring.submit(2);
+        // 56 bytes are left at the end, and the start is still used.
+        assert_eq!(ring.available(1), 56);
+        assert_eq!(ring.allocate(100, 1), None);
+
+        ring.retire(1);
+        assert_eq!(ring.allocate(100, 1), Some(0));
+        ring.submit(3);
+        assert_eq!(ring.available(1), 0);
+        ring.retire(2);
+        assert_eq!(ring.allocate(10, 16), Some(112));
+        ring.submit(4);
+
+        // The whole ring is available once every submission has completed.
+        ring.retire(4);
+        assert_eq!(ring.available(1), 256);
+        assert_eq!(ring.allocate(256, 1), Some(0));
+    }
+
+    #[test]
+    fn checks_regions_against_the_mip_level() {
+        let level = Extent3d::new(64, 32, 1);
+        assert!(region_fits([0; 3], level, level));
+        assert!(region_fits([16, 0, 0], Extent3d::new(48, 32, 1), level));
+        assert!(!region_fits([16, 0, 0], Extent3d::new(64, 32, 1), level));
+        assert!(!region_fits([0, 0, 1], Extent3d::new(1, 1, 1), level));
+        assert!(!region_fits(
+            [u32::MAX, 0, 0],
+            Extent3d::new(2, 1, 1),
+            level
+        ));
+    }
+}
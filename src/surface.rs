@@ -1,13 +1,26 @@
+#[cfg(feature = "window")]
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
-use crate::{Instance, NxResult};
+use ash::extensions::ext::HeadlessSurface;
+use ash::vk::{Extent2D, HeadlessSurfaceCreateInfoEXT};
+
+use std::sync::Arc;
+
+use crate::{HostCallbacks, Instance, InstanceOwner, NxError, NxResult};
 
 pub struct Surface {
     pub(crate) surface: ash::extensions::khr::Surface,
     pub(crate) khr: ash::vk::SurfaceKHR,
+    pub(crate) extent: Option<Extent2D>,
+    host: Option<Arc<HostCallbacks>>,
+    /// Released after the surface is destroyed, so the instance outlives it.
+    _instance: Arc<InstanceOwner>,
 }
 
 impl Surface {
+    /// Create a surface for a window.
+    /// **"window" feature is required.**
+    #[cfg(feature = "window")]
     pub fn new(
         instance: &Instance,
         handle: &(impl HasRawWindowHandle + HasRawDisplayHandle),
@@ -19,18 +32,58 @@ impl Surface {
                 &instance.instance,
                 handle.raw_display_handle(),
                 handle.raw_window_handle(),
-                None,
+                instance.allocation_callbacks(),
             )
         }
-        .unwrap();
-        Ok(Self { surface, khr })
+        .map_err(|e| NxError::vulkan("vkCreate*SurfaceKHR", e))?;
+        Ok(Self {
+            surface,
+            khr,
+            extent: None,
+            host: instance.host.clone(),
+            _instance: instance.owner.clone(),
+        })
+    }
+
+    /// Create a surface that is not associated with any window.
+    /// Images presented to it are discarded, which allows swapchains to be tested without a display.
+    ///
+    /// `InstanceFeature::use_headless_surface` must be enabled when creating the instance,
+    /// otherwise this fails with `NxError::ExtensionNotPresent`.
+    /// # Arguments
+    ///
+    /// * `instance` - Appropriate Instance.
+    /// * `width` - Width of the swapchain images.
+    /// * `height` - Height of the swapchain images.
+    pub fn headless(instance: &Instance, width: u32, height: u32) -> NxResult<Self> {
+        let name = HeadlessSurface::name().to_string_lossy();
+        if !instance.is_extension_enabled(&name) {
+            return Err(NxError::ExtensionNotPresent(name.into_owned()));
+        }
+        let surface = ash::extensions::khr::Surface::new(&instance.entry, &instance.instance);
+        let headless = HeadlessSurface::new(&instance.entry, &instance.instance);
+        let create_info = HeadlessSurfaceCreateInfoEXT::builder().build();
+        let khr = match unsafe {
+            headless.create_headless_surface(&create_info, instance.allocation_callbacks())
+        } {
+            Ok(x) => x,
+            Err(e) => return Err(NxError::vulkan("vkCreateHeadlessSurfaceEXT", e)),
+        };
+        Ok(Self {
+            surface,
+            khr,
+            extent: Some(Extent2D { width, height }),
+            host: instance.host.clone(),
+            _instance: instance.owner.clone(),
+        })
     }
 }
 
 impl Drop for Surface {
     fn drop(&mut self) {
         unsafe {
-            self.surface.destroy_surface(self.khr, None);
+            self.surface
+                .destroy_surface(self.khr, self.host.as_ref().map(|x| x.raw()));
         }
     }
 }
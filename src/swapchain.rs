@@ -1,9 +1,11 @@
 use ash::vk::{
-    ImageUsageFlags, PresentInfoKHR, Semaphore, SharingMode, SwapchainCreateInfoKHR, SwapchainKHR,
+    CompositeAlphaFlagsKHR, Extent2D, ImageUsageFlags, PresentInfoKHR, PresentModeKHR, Semaphore,
+    SharingMode, SwapchainCreateInfoKHR, SwapchainKHR,
 };
+use std::marker::PhantomData;
 
 use crate::{
-    Device, DeviceConnecter, Image, ImageFormat, Instance, NxError, NxResult,
+    Device, DeviceConnecter, Extent3d, Image, ImageFormat, Instance, NotSync, NxError, NxResult,
     QueuePresentDescriptor, Surface,
 };
 
@@ -16,8 +18,11 @@ pub enum SwapchainState {
 
 pub struct Swapchain {
     swapchain: ash::extensions::khr::Swapchain,
-    khr: SwapchainKHR,
+    pub(crate) khr: SwapchainKHR,
     format: ImageFormat,
+    extent: Extent3d,
+    device: Device,
+    _not_sync: NotSync,
 }
 
 impl Swapchain {
@@ -27,40 +32,85 @@ impl Swapchain {
         device: &Device,
         connecter: DeviceConnecter,
     ) -> NxResult<Self> {
-        if !connecter.is_support_swapchain(instance) {
+        device.check_validation()?;
+        let name = ash::extensions::khr::Swapchain::name().to_string_lossy();
+        if !connecter.is_extension_supported(instance, &name) {
             return Err(NxError::HardwareError);
         }
 
-        let surface_capabilities = connecter.get_surface_capabilities(surface);
-        let surface_formats = connecter.get_surface_formats(surface);
-        let surface_present_modes = connecter.get_surface_present_modes(surface);
+        let surface_capabilities = connecter.get_surface_capabilities(surface)?;
+        let surface_formats = connecter.get_surface_formats(surface)?;
+        let surface_present_modes = connecter.get_surface_present_modes(surface)?;
 
-        let format = surface_formats[0];
-        let present_mode = surface_present_modes[0];
+        let format = *surface_formats.first().ok_or(NxError::NoValue)?;
+        // FIFO is the only present mode that is always supported.
+        let present_mode = surface_present_modes
+            .first()
+            .copied()
+            .unwrap_or(PresentModeKHR::FIFO);
+
+        // A current extent of u32::MAX means the size is determined by the swapchain,
+        // which is the case for headless surfaces.
+        let extent = if surface_capabilities.current_extent.width == u32::MAX {
+            let extent = surface
+                .extent
+                .unwrap_or(surface_capabilities.min_image_extent);
+            Extent2D {
+                width: extent.width.clamp(
+                    surface_capabilities.min_image_extent.width,
+                    surface_capabilities.max_image_extent.width,
+                ),
+                height: extent.height.clamp(
+                    surface_capabilities.min_image_extent.height,
+                    surface_capabilities.max_image_extent.height,
+                ),
+            }
+        } else {
+            surface_capabilities.current_extent
+        };
+        let mut image_count = surface_capabilities.min_image_count + 1;
+        if surface_capabilities.max_image_count != 0 {
+            image_count = image_count.min(surface_capabilities.max_image_count);
+        }
+        let composite_alpha = [
+            CompositeAlphaFlagsKHR::OPAQUE,
+            CompositeAlphaFlagsKHR::INHERIT,
+            CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+            CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+        ]
+        .into_iter()
+        .find(|x| surface_capabilities.supported_composite_alpha.contains(*x))
+        .unwrap_or(CompositeAlphaFlagsKHR::OPAQUE);
 
         let create_info = SwapchainCreateInfoKHR::builder()
             .surface(surface.khr)
-            .min_image_count(surface_capabilities.min_image_count + 1)
+            .min_image_count(image_count)
             .image_format(format.format)
             .image_color_space(format.color_space)
-            .image_extent(surface_capabilities.current_extent)
+            .image_extent(extent)
             .image_array_layers(1)
             .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
             .image_sharing_mode(SharingMode::EXCLUSIVE)
             .pre_transform(surface_capabilities.current_transform)
+            .composite_alpha(composite_alpha)
             .present_mode(present_mode)
             .clipped(true)
             .build();
         let swapchain = ash::extensions::khr::Swapchain::new(&instance.instance, &device.device);
-        let khr = match unsafe { swapchain.create_swapchain(&create_info, None) } {
+        let khr = match unsafe {
+            swapchain.create_swapchain(&create_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => return Err(NxError::InternalError(e)),
+            Err(e) => return Err(device.error("vkCreateSwapchainKHR", e)),
         };
         let format = format.format.into();
         Ok(Self {
             swapchain,
             khr,
             format,
+            extent: Extent3d::new(extent.width, extent.height, 1),
+            device: device.uncounted(),
+            _not_sync: PhantomData,
         })
     }
 
@@ -90,7 +140,7 @@ impl Swapchain {
                 Ok((image, state))
             }
 
-            Err(e) => Err(NxError::InternalError(e)),
+            Err(e) => Err(self.device.error("vkAcquireNextImageKHR", e)),
         }
     }
 
@@ -99,6 +149,9 @@ impl Swapchain {
     }
 
     pub fn present(&self, descriptor: &QueuePresentDescriptor, image: u32) -> NxResult<()> {
+        let queue = descriptor
+            .queue
+            .ok_or(NxError::MissingDescriptorField("queue"))?;
         let w_semaphores: Vec<Semaphore> = descriptor
             .wait_semaphores
             .iter()
@@ -110,20 +163,19 @@ impl Swapchain {
             .wait_semaphores(&w_semaphores)
             .build();
 
-        match unsafe {
-            self.swapchain
-                .queue_present(descriptor.queue.unwrap().0, &present_info)
-        } {
+        let _lock = queue.lock.lock();
+        match unsafe { self.swapchain.queue_present(queue.queue, &present_info) } {
             Ok(_) => Ok(()),
-            Err(e) => Err(NxError::InternalError(e)),
+            Err(e) => Err(self.device.error("vkQueuePresentKHR", e)),
         }
     }
 
     pub fn images(&self) -> NxResult<Vec<Image>> {
-        let images = unsafe { self.swapchain.get_swapchain_images(self.khr).unwrap() };
+        let images = unsafe { self.swapchain.get_swapchain_images(self.khr) }
+            .map_err(|e| self.device.error("vkGetSwapchainImagesKHR", e))?;
         let images = images
             .iter()
-            .map(|x| Image::from_raw(*x))
+            .map(|x| Image::from_raw(*x, self.format, self.extent))
             .collect::<Vec<Image>>();
         if !images.is_empty() {
             Ok(images)
@@ -136,7 +188,8 @@ impl Swapchain {
 impl Drop for Swapchain {
     fn drop(&mut self) {
         unsafe {
-            self.swapchain.destroy_swapchain(self.khr, None);
+            self.swapchain
+                .destroy_swapchain(self.khr, self.device.allocation_callbacks());
         }
     }
 }
@@ -0,0 +1,52 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Serializes host access to an object that Vulkan requires to be externally synchronized,
+/// such as a queue or a command pool. Clones share the same lock.
+#[doc(hidden)]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HostLock(Arc<Mutex<()>>);
+
+impl HostLock {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ()> {
+        // The lock protects no data, so a panic while holding it leaves nothing inconsistent.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Makes a type `Send` but not `Sync`.
+///
+/// Used by objects with methods taking `&self` that Vulkan requires to be externally synchronized,
+/// such as `Fence::reset` or `Buffer::write`.
+///
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::Fence>();
+/// ```
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::Semaphore>();
+/// ```
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::Buffer>();
+/// ```
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::Image>();
+/// ```
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::ResourcePool>();
+/// ```
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::Resource>();
+/// ```
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<nexg::Swapchain>();
+/// ```
+#[doc(hidden)]
+pub(crate) type NotSync = PhantomData<Cell<()>>;
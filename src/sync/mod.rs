@@ -1,2 +1,4 @@
+mod lock;
 mod semaphore;
+pub(crate) use lock::*;
 pub use semaphore::*;
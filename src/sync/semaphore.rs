@@ -1,22 +1,33 @@
-use crate::{Destroy, Device, Instance, NxError, NxResult};
+use crate::{Destroy, Device, DeviceChild, Garbage, Instance, NotSync, NxResult};
 use ash::vk::SemaphoreCreateInfo;
+use std::marker::PhantomData;
 
 /// Stores information needed to create a Semaphore.
-pub struct SemaphoreDescriptor {}
+pub struct SemaphoreDescriptor {
+    label: Option<String>,
+}
 
 impl SemaphoreDescriptor {
     /// Initializes a new descriptor with default values.
     #[inline]
     pub fn empty() -> Self {
-        Self {}
+        Self { label: None }
+    }
+
+    /// Specifies the debug name of the Semaphore. See `DebugName`.
+    #[inline]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_owned());
+        self
     }
 }
 
 /// An object to wait for work.
 /// Used to wait for a specific operation.
-#[derive(Clone, Copy)]
 pub struct Semaphore {
     pub(crate) semaphore: ash::vk::Semaphore,
+    owner: DeviceChild,
+    _not_sync: NotSync,
 }
 
 impl Semaphore {
@@ -25,26 +36,36 @@ impl Semaphore {
     ///
     /// * `device` - Reference to the appropriate device.
     /// * `descriptor` - Appropriate SemaphoreDescriptor.
-    pub fn new(device: &Device, _descriptor: &SemaphoreDescriptor) -> NxResult<Self> {
+    pub fn new(device: &Device, descriptor: &SemaphoreDescriptor) -> NxResult<Self> {
+        device.check_validation()?;
         let create_info = SemaphoreCreateInfo::builder().build();
-        let semaphore = match unsafe { device.device.create_semaphore(&create_info, None) } {
+        let semaphore = match unsafe {
+            device
+                .device
+                .create_semaphore(&create_info, device.allocation_callbacks())
+        } {
             Ok(x) => x,
-            Err(e) => match e {
-                ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(NxError::OutOfDeviceMemory),
-                ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(NxError::OutOfHostMemory),
-                _ => Err(NxError::Unknown),
-            }?,
+            Err(e) => return Err(device.error("vkCreateSemaphore", e)),
         };
-        Ok(Self { semaphore })
+        device.label(semaphore, descriptor.label.as_deref());
+        Ok(Self {
+            semaphore,
+            owner: DeviceChild::new(device, semaphore, descriptor.label.as_deref()),
+            _not_sync: PhantomData,
+        })
     }
 }
 
 impl Destroy for Semaphore {
     fn instance(&self, _: &Instance) {}
 
-    fn device(&self, device: &Device) {
-        unsafe {
-            device.device.destroy_semaphore(self.semaphore, None);
-        }
+    fn device(&self, _: &Device) {
+        self.owner.destroy(Garbage::Semaphore(self.semaphore));
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        self.owner.defer(Garbage::Semaphore(self.semaphore));
     }
 }
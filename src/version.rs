@@ -0,0 +1,104 @@
+use std::fmt;
+
+use ash::vk;
+
+/// Represents a Vulkan version number.
+///
+/// Used for the API version as well as for application and engine versions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Version {
+    variant: u32,
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl Version {
+    /// Vulkan 1.0
+    pub const V1_0: Self = Self::new(1, 0, 0);
+    /// Vulkan 1.1
+    pub const V1_1: Self = Self::new(1, 1, 0);
+    /// Vulkan 1.2
+    pub const V1_2: Self = Self::new(1, 2, 0);
+    /// Vulkan 1.3
+    pub const V1_3: Self = Self::new(1, 3, 0);
+
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            variant: 0,
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    pub fn variant(&self) -> u32 {
+        self.variant
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u32 {
+        self.patch
+    }
+
+    /// Drops the patch number, so `1.3.250` becomes `1.3.0`.
+    /// Useful for comparing against the `V1_*` constants.
+    pub const fn without_patch(self) -> Self {
+        Self { patch: 0, ..self }
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn from_raw(version: u32) -> Self {
+        Self {
+            variant: vk::api_version_variant(version),
+            major: vk::api_version_major(version),
+            minor: vk::api_version_minor(version),
+            patch: vk::api_version_patch(version),
+        }
+    }
+
+    #[doc(hidden)]
+    pub(crate) fn as_raw(&self) -> u32 {
+        vk::make_api_version(self.variant, self.major, self.minor, self.patch)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_raw_versions() {
+        let version = Version::new(1, 3, 250);
+        assert_eq!(version.as_raw(), vk::make_api_version(0, 1, 3, 250));
+        assert_eq!(Version::from_raw(version.as_raw()), version);
+        assert_eq!(Version::from_raw(vk::API_VERSION_1_2), Version::V1_2);
+        assert_eq!(version.without_patch(), Version::V1_3);
+        assert_eq!(version.to_string(), "1.3.250");
+    }
+
+    #[test]
+    fn orders_by_major_minor_then_patch() {
+        assert!(Version::V1_0 < Version::V1_1);
+        assert!(Version::new(1, 2, 200) < Version::V1_3);
+        assert!(Version::V1_3 < Version::new(1, 3, 1));
+        assert!(Version::new(2, 0, 0) > Version::new(1, 9, 9));
+        assert_eq!(
+            Version::new(1, 3, 250).max(Version::V1_2),
+            Version::new(1, 3, 250)
+        );
+    }
+}